@@ -0,0 +1,17 @@
+use std::process::Command;
+
+fn main() {
+    // Get git hash
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output();
+
+    let git_hash = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        _ => "unknown".to_string(),
+    };
+
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
+}
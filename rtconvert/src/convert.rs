@@ -0,0 +1,433 @@
+use rtsimh::{
+    SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, TapeBlockReader, TapeBlockWriter,
+};
+
+const BAD_RECORD_CLASS: u8 = 0x8;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConvertOptions {
+    /// Reblock good records of each tape file into records of this many bytes.
+    pub block_size: Option<usize>,
+    /// Restrict SIMH output to what pre-extension readers understand: good and
+    /// bad records, tape marks, erase gaps and end of medium.
+    pub classic: bool,
+}
+
+/// How a tape file ended in the source image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileEnd {
+    TapeMark,
+    EndOfMedium,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ConvertStats {
+    pub files: usize,
+    pub records_read: usize,
+    pub records_written: usize,
+    pub data_bytes: u64,
+}
+
+/// Copies tape files between formats, applying record transforms and counting
+/// anything the target cannot represent.
+pub struct Converter {
+    options: ConvertOptions,
+    pending: Vec<u8>,
+    /// Records written for the current tape file.
+    written_in_file: usize,
+    pub stats: ConvertStats,
+    warnings: Vec<(String, usize)>,
+}
+
+impl Converter {
+    pub fn new(options: ConvertOptions) -> Self {
+        Self {
+            options,
+            pending: Vec::new(),
+            written_in_file: 0,
+            stats: ConvertStats::default(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Lossy conversions so far, each with the number of times it happened.
+    pub fn warnings(&self) -> &[(String, usize)] {
+        &self.warnings
+    }
+
+    /// Copies the records, gaps and markers of one tape file. The tape mark that
+    /// ends the file is left for the caller to write; returns how the file ended
+    /// and how many records were read from it.
+    pub fn copy_file(
+        &mut self,
+        reader: &mut dyn TapeBlockReader,
+        writer: &mut dyn TapeBlockWriter,
+    ) -> SimhResult<(FileEnd, usize)> {
+        let mut records = 0;
+        self.written_in_file = 0;
+        let end = loop {
+            match reader.next_block()? {
+                SimhTapeBlock::Record(record) => {
+                    records += 1;
+                    self.stats.records_read += 1;
+                    self.stats.data_bytes += record.data.len() as u64;
+                    self.record(writer, record.header.class, &record.data)?;
+                }
+                SimhTapeBlock::TapeMark {
+                    kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                    ..
+                } => break FileEnd::TapeMark,
+                SimhTapeBlock::TapeMark {
+                    kind: SimhTapeMark::EndOfTape,
+                    ..
+                }
+                | SimhTapeBlock::EndOfStream
+                | SimhTapeBlock::BeginningOfTape => break FileEnd::EndOfMedium,
+                SimhTapeBlock::TapeMark { kind, .. } => {
+                    self.flush(writer)?;
+                    self.mark(writer, kind)?;
+                }
+                SimhTapeBlock::Resync(resync) => self.warn(format!(
+                    "damaged spans skipped in the source ({})",
+                    resync.error
+                )),
+            }
+        };
+
+        self.flush(writer)?;
+        if records > 0 {
+            self.stats.files += 1;
+        }
+        Ok((end, records))
+    }
+
+    /// Ends a tape file in a single-image conversion, counting tape marks the
+    /// target cannot hold.
+    pub fn end_file(&mut self, writer: &mut dyn TapeBlockWriter) -> SimhResult<()> {
+        match writer.write_tape_mark() {
+            Err(SimhError::Unsupported { format, feature }) => {
+                self.warn(format!(
+                    "{feature} dropped ({format} images cannot hold them)"
+                ));
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn record(
+        &mut self,
+        writer: &mut dyn TapeBlockWriter,
+        class: u8,
+        data: &[u8],
+    ) -> SimhResult<()> {
+        let mut class = class;
+        if self.options.classic && class != 0 && class != BAD_RECORD_CLASS {
+            self.warn(format!(
+                "class {class:X} records written as good records (classic SIMH has no record classes)"
+            ));
+            class = 0;
+        }
+
+        match self.options.block_size {
+            Some(block_size) if class == 0 => {
+                self.pending.extend_from_slice(data);
+                while self.pending.len() >= block_size {
+                    let rest = self.pending.split_off(block_size);
+                    let block = std::mem::replace(&mut self.pending, rest);
+                    self.write(writer, 0, &block)?;
+                }
+                Ok(())
+            }
+            _ => {
+                self.flush(writer)?;
+                self.write(writer, class, data)
+            }
+        }
+    }
+
+    fn flush(&mut self, writer: &mut dyn TapeBlockWriter) -> SimhResult<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let block = std::mem::take(&mut self.pending);
+        self.write(writer, 0, &block)
+    }
+
+    fn write(
+        &mut self,
+        writer: &mut dyn TapeBlockWriter,
+        class: u8,
+        data: &[u8],
+    ) -> SimhResult<()> {
+        let result = match writer.write_record_with_class(class, data) {
+            Err(SimhError::Unsupported { format, feature }) if class != 0 => {
+                self.warn(format!(
+                    "class {class:X} records written as good records ({format} images cannot hold {feature})"
+                ));
+                writer.write_record(data)
+            }
+            result => result,
+        };
+
+        match result {
+            Ok(()) => {
+                self.stats.records_written += 1;
+                self.written_in_file += 1;
+                if self.written_in_file > 1 && !writer.keeps_record_boundaries() {
+                    self.warn(
+                        "record boundaries lost (records joined into one stream)".to_string(),
+                    );
+                }
+                Ok(())
+            }
+            Err(SimhError::EmptyRecord) => {
+                self.warn("empty records dropped".to_string());
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn mark(&mut self, writer: &mut dyn TapeBlockWriter, mark: SimhTapeMark) -> SimhResult<()> {
+        if self.options.classic && mark != SimhTapeMark::EraseGap {
+            self.warn(format!(
+                "{} dropped (classic SIMH cannot hold them)",
+                describe_mark(mark)
+            ));
+            return Ok(());
+        }
+
+        match writer.write_mark(mark) {
+            Err(SimhError::Unsupported { format, .. }) => {
+                self.warn(format!(
+                    "{} dropped ({format} images cannot hold them)",
+                    describe_mark(mark)
+                ));
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        match self.warnings.iter_mut().find(|(seen, _)| *seen == message) {
+            Some((_, count)) => *count += 1,
+            None => self.warnings.push((message, 1)),
+        }
+    }
+}
+
+/// Counts the tape files in the source that hold at least one record.
+pub fn count_data_files(reader: &mut dyn TapeBlockReader) -> SimhResult<usize> {
+    let mut files = 0;
+    let mut records = 0;
+    loop {
+        match reader.next_block()? {
+            SimhTapeBlock::Record(_) => records += 1,
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                ..
+            } => {
+                files += usize::from(records > 0);
+                records = 0;
+            }
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::EndOfTape,
+                ..
+            }
+            | SimhTapeBlock::EndOfStream
+            | SimhTapeBlock::BeginningOfTape => break,
+            SimhTapeBlock::TapeMark { .. } | SimhTapeBlock::Resync(_) => {}
+        }
+    }
+    Ok(files + usize::from(records > 0))
+}
+
+fn describe_mark(mark: SimhTapeMark) -> &'static str {
+    match mark {
+        SimhTapeMark::EraseGap => "erase gaps",
+        SimhTapeMark::HalfGapForward | SimhTapeMark::HalfGapReverse { .. } => "half-gaps",
+        SimhTapeMark::Private { .. } => "private markers",
+        SimhTapeMark::Reserved { .. } => "reserved markers",
+        SimhTapeMark::Single | SimhTapeMark::Double => "tape marks",
+        SimhTapeMark::EndOfTape => "end-of-medium markers",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtsimh::{RawTapeWriter, SimhTapeReader, SimhTapeWriter, TpcTapeReader, TpcTapeWriter};
+    use std::io::Cursor;
+
+    fn source() -> Vec<u8> {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 6]).unwrap();
+        writer.write_record(&[2; 6]).unwrap();
+        writer.write_erase_gap_markers(1).unwrap();
+        writer.write_bad_record(&[3; 4]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_record_with_class(0x2, &[4; 2]).unwrap();
+        writer.write_private_marker(5).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_end_of_medium().unwrap();
+        writer.into_inner()
+    }
+
+    fn convert(
+        converter: &mut Converter,
+        reader: &mut dyn TapeBlockReader,
+        writer: &mut dyn TapeBlockWriter,
+    ) {
+        while converter.copy_file(reader, writer).unwrap().0 == FileEnd::TapeMark {
+            converter.end_file(writer).unwrap();
+        }
+        writer.write_end_of_medium().unwrap();
+    }
+
+    #[test]
+    fn reblocks_good_records_within_each_file() {
+        let mut converter = Converter::new(ConvertOptions {
+            block_size: Some(4),
+            ..Default::default()
+        });
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        convert(
+            &mut converter,
+            &mut SimhTapeReader::new(Cursor::new(source())),
+            &mut writer,
+        );
+
+        let mut reader = SimhTapeReader::new(Cursor::new(writer.into_inner()));
+        let mut records = Vec::new();
+        loop {
+            match reader.next_block().unwrap() {
+                SimhTapeBlock::Record(record) => {
+                    records.push((record.header.class, record.data.len()))
+                }
+                SimhTapeBlock::TapeMark {
+                    kind: SimhTapeMark::EndOfTape,
+                    ..
+                } => break,
+                SimhTapeBlock::TapeMark {
+                    kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                    ..
+                } => records.push((0xFF, 0)),
+                SimhTapeBlock::TapeMark { .. } => records.push((0xFE, 0)),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(
+            records,
+            vec![
+                (0, 4),
+                (0, 4),
+                (0, 4),
+                (0xFE, 0),
+                (8, 4),
+                (0xFF, 0),
+                (2, 2),
+                (0xFE, 0),
+                (0xFF, 0)
+            ]
+        );
+        assert_eq!(converter.stats.records_read, 4);
+        assert_eq!(converter.stats.files, 2);
+    }
+
+    #[test]
+    fn warns_about_what_the_target_cannot_hold() {
+        let mut converter = Converter::new(ConvertOptions::default());
+        let mut writer = TpcTapeWriter::new(Vec::new());
+        convert(
+            &mut converter,
+            &mut SimhTapeReader::new(Cursor::new(source())),
+            &mut writer,
+        );
+
+        let messages: Vec<_> = converter
+            .warnings()
+            .iter()
+            .map(|(message, count)| (message.as_str(), *count))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("erase gaps dropped (TPC images cannot hold them)", 1),
+                (
+                    "class 8 records written as good records (TPC images cannot hold record classes)",
+                    1
+                ),
+                (
+                    "class 2 records written as good records (TPC images cannot hold record classes)",
+                    1
+                ),
+                ("private markers dropped (TPC images cannot hold them)", 1),
+            ]
+        );
+
+        let mut reader = TpcTapeReader::new(Cursor::new(writer.into_inner()));
+        let mut count = 0;
+        while let SimhTapeBlock::Record(_) | SimhTapeBlock::TapeMark { .. } =
+            reader.next_block().unwrap()
+        {
+            count += 1;
+        }
+        assert_eq!(count, 6);
+
+        let mut classic = Converter::new(ConvertOptions {
+            classic: true,
+            ..Default::default()
+        });
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        convert(
+            &mut classic,
+            &mut SimhTapeReader::new(Cursor::new(source())),
+            &mut writer,
+        );
+        assert_eq!(classic.warnings().len(), 2);
+        assert_eq!(classic.stats.records_written, 4);
+    }
+
+    #[test]
+    fn warns_when_raw_output_joins_records_and_files() {
+        assert_eq!(
+            count_data_files(&mut SimhTapeReader::new(Cursor::new(source()))).unwrap(),
+            2
+        );
+
+        let mut tape = SimhTapeWriter::new(Vec::new());
+        tape.write_record(b"hello").unwrap();
+        tape.write_record(b"world").unwrap();
+        tape.write_tape_mark().unwrap();
+        tape.write_tape_mark().unwrap();
+        let tape = tape.into_inner();
+        assert_eq!(
+            count_data_files(&mut SimhTapeReader::new(Cursor::new(tape.clone()))).unwrap(),
+            1
+        );
+
+        let mut converter = Converter::new(ConvertOptions::default());
+        let mut writer = RawTapeWriter::new(Vec::new());
+        convert(
+            &mut converter,
+            &mut SimhTapeReader::new(Cursor::new(tape)),
+            &mut writer,
+        );
+        assert_eq!(writer.into_inner(), b"helloworld");
+        assert_eq!(
+            converter.warnings(),
+            [
+                (
+                    "record boundaries lost (records joined into one stream)".to_string(),
+                    1
+                ),
+                (
+                    "tape marks dropped (raw images cannot hold them)".to_string(),
+                    2
+                ),
+            ]
+        );
+    }
+}
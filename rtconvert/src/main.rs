@@ -0,0 +1,256 @@
+mod convert;
+
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use clap::Parser;
+use convert::{ConvertOptions, Converter, FileEnd};
+use rtsimh::{RawTapeReader, SimhError, TapeBlockReader, TapeImageFormat, VERSION};
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const GIT_HASH: &str = env!("GIT_HASH");
+
+#[derive(Debug, Parser)]
+#[command(
+    author,
+    version,
+    about = "ACMS rtconvert tape image format converter.",
+    after_help = "EXAMPLES:\n  \
+                  rtconvert mytape.aws mytape.tap\n  \
+                  rtconvert --to raw --split-files mytape.tap mytape/\n  \
+                  rtconvert --block-size 10240 mytape.tap reblocked.tap\n  \
+                  rtconvert --classic extended.tap classic.tap"
+)]
+struct Cli {
+    /// Path to the source image
+    #[arg(value_name = "INPUT")]
+    input: PathBuf,
+
+    /// Path to the converted image, or a directory with --split-files
+    #[arg(value_name = "OUTPUT")]
+    output: PathBuf,
+
+    /// Source format: simh, e11, tpc, aws, p7b or raw (detected when omitted)
+    #[arg(long, value_name = "FORMAT")]
+    from: Option<TapeImageFormat>,
+
+    /// Target format (taken from the output extension when omitted, else simh)
+    #[arg(long, value_name = "FORMAT")]
+    to: Option<TapeImageFormat>,
+
+    /// Write each tape file to its own numbered image in the OUTPUT directory
+    #[arg(long)]
+    split_files: bool,
+
+    /// Record size to cut raw input into [default: 10240]
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u32).range(1..))]
+    record_size: Option<u32>,
+
+    /// Reblock the good records of each tape file into records of this many bytes
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u32).range(1..))]
+    block_size: Option<u32>,
+
+    /// Restrict SIMH output to classic readers: no record classes besides bad
+    /// records, and no half-gaps, private or reserved markers
+    #[arg(long)]
+    classic: bool,
+
+    /// Overwrite OUTPUT if it already exists
+    #[arg(long)]
+    force: bool,
+}
+
+fn main() -> Result<()> {
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+    println!("========================");
+    println!("ACMS rtconvert v{} / {}", VERSION, GIT_HASH);
+    println!("Timestamp: {}", timestamp);
+
+    let cli = Cli::parse();
+
+    let file = File::open(&cli.input)
+        .with_context(|| format!("failed to open file {}", cli.input.display()))?;
+    let mut source = BufReader::new(file);
+    let from = match cli
+        .from
+        .or_else(|| TapeImageFormat::from_extension(&cli.input))
+    {
+        Some(format) => format,
+        None => TapeImageFormat::detect(&mut source)
+            .with_context(|| format!("failed to read {}", cli.input.display()))?,
+    };
+    let to = cli
+        .to
+        .or_else(|| TapeImageFormat::from_extension(&cli.output))
+        .unwrap_or(TapeImageFormat::Simh);
+    if cli.classic && to != TapeImageFormat::Simh {
+        bail!(
+            "--classic only applies to SIMH output, not {}",
+            to.description()
+        );
+    }
+
+    if cli.record_size.is_some() && from != TapeImageFormat::Raw {
+        bail!(
+            "--record-size only applies to raw input, not {}",
+            from.description()
+        );
+    }
+
+    if to == TapeImageFormat::Raw && !cli.split_files {
+        let file = File::open(&cli.input)
+            .with_context(|| format!("failed to open file {}", cli.input.display()))?;
+        let files =
+            convert::count_data_files(open_reader(&cli, from, BufReader::new(file)).as_mut())
+                .context("failed to scan the source image")?;
+        if files > 1 {
+            bail!(
+                "{} holds {files} tape files, which raw output would join; pass --split-files",
+                cli.input.display()
+            );
+        }
+    }
+
+    if fs::canonicalize(&cli.output)
+        .is_ok_and(|output| fs::canonicalize(&cli.input).is_ok_and(|input| input == output))
+    {
+        bail!("OUTPUT must not be the INPUT image");
+    }
+
+    println!("Input: {} ({})", cli.input.display(), from.description());
+    println!("Output: {} ({})", cli.output.display(), to.description());
+    println!("========================");
+
+    let options = ConvertOptions {
+        block_size: cli.block_size.map(|size| size as usize),
+        classic: cli.classic,
+    };
+    let mut converter = Converter::new(options);
+    let mut reader = open_reader(&cli, from, source);
+
+    if cli.split_files {
+        fs::create_dir_all(&cli.output)
+            .with_context(|| format!("failed to create directory {}", cli.output.display()))?;
+        // Images are numbered by tape file; empty tape files leave a gap.
+        for index in 1.. {
+            let path = cli
+                .output
+                .join(format!("file{index:04}.{}", to.extension()));
+            let created = Cell::new(false);
+            let mut output = LazyOutput::new(&path, cli.force, &created);
+            let mut writer = to.open_writer(&mut output);
+            let (end, records) = converter
+                .copy_file(reader.as_mut(), writer.as_mut())
+                .context("conversion failed")?;
+            if created.get() {
+                // Raw images have no tape marks; the file itself ends the tape file.
+                match writer.write_tape_mark() {
+                    Err(SimhError::Unsupported { .. }) => {}
+                    result => result?,
+                }
+                writer.write_end_of_medium()?;
+                drop(writer);
+                output.flush()?;
+                println!("{}: {} records", path.display(), records);
+            }
+            if end == FileEnd::EndOfMedium {
+                break;
+            }
+        }
+    } else {
+        let mut output = create_output(&cli.output, cli.force)?;
+        let mut writer = to.open_writer(&mut output);
+        while converter
+            .copy_file(reader.as_mut(), writer.as_mut())
+            .context("conversion failed")?
+            .0
+            == FileEnd::TapeMark
+        {
+            converter.end_file(writer.as_mut())?;
+        }
+        writer.write_end_of_medium()?;
+        drop(writer);
+        output.flush()?;
+    }
+
+    let stats = &converter.stats;
+    println!(
+        "Converted {} files, {} records ({} bytes); wrote {} records.",
+        stats.files, stats.records_read, stats.data_bytes, stats.records_written
+    );
+    for (message, count) in converter.warnings() {
+        eprintln!("[warning] {message}: {count}");
+    }
+
+    Ok(())
+}
+
+fn create_output(path: &Path, force: bool) -> Result<BufWriter<File>> {
+    if path.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+fn open_reader<'a>(
+    cli: &Cli,
+    format: TapeImageFormat,
+    source: BufReader<File>,
+) -> Box<dyn TapeBlockReader + 'a> {
+    match (format, cli.record_size) {
+        (TapeImageFormat::Raw, Some(size)) => {
+            Box::new(RawTapeReader::new(source).with_block_size(size as usize))
+        }
+        _ => format.open_reader(source),
+    }
+}
+
+/// An output file that is only created once something is written to it, so a
+/// tape file with nothing in it leaves no image behind.
+struct LazyOutput<'a> {
+    path: &'a Path,
+    force: bool,
+    created: &'a Cell<bool>,
+    file: Option<BufWriter<File>>,
+}
+
+impl<'a> LazyOutput<'a> {
+    fn new(path: &'a Path, force: bool, created: &'a Cell<bool>) -> Self {
+        Self {
+            path,
+            force,
+            created,
+            file: None,
+        }
+    }
+
+    fn file(&mut self) -> io::Result<&mut BufWriter<File>> {
+        if self.file.is_none() {
+            let file = create_output(self.path, self.force)
+                .map_err(|err| io::Error::other(format!("{err:#}")))?;
+            self.file = Some(file);
+            self.created.set(true);
+        }
+        Ok(self.file.as_mut().expect("file was just created"))
+    }
+}
+
+impl Write for LazyOutput<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+mod repair;
+
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use clap::{Parser, ValueEnum};
+use repair::{Diagnosis, EomMode, RepairOptions};
+use rtsimh::VERSION;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const GIT_HASH: &str = env!("GIT_HASH");
+
+#[derive(Debug, Parser)]
+#[command(
+    author,
+    version,
+    about = "ACMS rtfix SIMH tape image end-of-tape repair tool.",
+    after_help = "EXAMPLES:\n  \
+                  rtfix --check tape.tap         Check if file needs fixing\n  \
+                  rtfix tape.tap                 Fix file in place\n  \
+                  rtfix tape.tap -o fixed.tap    Fix to new file\n  \
+                  rtfix --check *.tap            Check multiple files\n  \
+                  rtfix --eom add *.tap          Fix files in place, ending each with an EOM marker\n  \
+                  rtfix --collapse-tape-marks tape.tap  Also collapse suspected retry runs of tape marks"
+)]
+struct Cli {
+    /// Tape image file(s) to check/fix
+    #[arg(value_name = "FILES", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Output file (only valid with a single input file)
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Check files only, do not modify
+    #[arg(short, long)]
+    check: bool,
+
+    /// What to do with the end-of-medium marker after the final double tape mark
+    #[arg(long, value_enum, default_value_t = EomArg::Keep)]
+    eom: EomArg,
+
+    /// Drop a truncated final record instead of keeping it as a bad record
+    #[arg(long)]
+    drop_truncated: bool,
+
+    /// Collapse runs of three or more tape marks inside the image, left by
+    /// rtimage retry loops, to a single mark. Such runs can also be empty files.
+    #[arg(long)]
+    collapse_tape_marks: bool,
+
+    /// Verbose output
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EomArg {
+    Keep,
+    Add,
+    Strip,
+}
+
+enum Outcome {
+    Ok,
+    NeedsFix,
+    Fixed,
+    Failed,
+}
+
+fn main() -> ExitCode {
+    // Display header before parsing args so it shows even on errors
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+    println!("========================");
+    println!("ACMS rtfix v{} / {}", VERSION, GIT_HASH);
+    println!("Timestamp: {}", timestamp);
+
+    let cli = Cli::parse();
+    println!("========================");
+    if cli.output.is_some() && cli.files.len() > 1 {
+        eprintln!("Error: --output can only be used with a single input file");
+        return ExitCode::FAILURE;
+    }
+
+    let options = RepairOptions {
+        eom: match cli.eom {
+            EomArg::Keep => EomMode::Keep,
+            EomArg::Add => EomMode::Add,
+            EomArg::Strip => EomMode::Strip,
+        },
+        salvage_truncated: !cli.drop_truncated,
+        collapse_tape_marks: cli.collapse_tape_marks,
+    };
+
+    let mut needing_fix = 0;
+    let mut fixed = 0;
+    let mut failed = 0;
+    for path in &cli.files {
+        let outcome = process(&cli, &options, path).unwrap_or_else(|err| {
+            eprintln!("Error: {}: {:#}", path.display(), err);
+            Outcome::Failed
+        });
+        match outcome {
+            Outcome::Ok => {}
+            Outcome::NeedsFix => needing_fix += 1,
+            Outcome::Fixed => {
+                needing_fix += 1;
+                fixed += 1;
+            }
+            Outcome::Failed => failed += 1,
+        }
+    }
+
+    if cli.files.len() > 1 {
+        println!();
+        if cli.check {
+            println!(
+                "Checked {} files: {} need fixing, {} failed",
+                cli.files.len(),
+                needing_fix,
+                failed
+            );
+        } else {
+            println!(
+                "Checked {} files: {} fixed, {} failed",
+                cli.files.len(),
+                fixed,
+                failed
+            );
+        }
+    }
+
+    if failed > 0 || (cli.check && needing_fix > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn process(cli: &Cli, options: &RepairOptions, path: &Path) -> Result<Outcome> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut source = BufReader::new(file);
+    let diagnosis = repair::diagnose(&mut source).context("failed to scan image")?;
+    let findings = diagnosis.findings(options);
+
+    if let Some(err) = &diagnosis.damage {
+        println!("{}: DAMAGED - {}", path.display(), err);
+        return Ok(Outcome::Failed);
+    }
+
+    let suspicions = diagnosis.suspicions(options);
+    if !suspicions.is_empty() {
+        println!("{}: SUSPECT - {}", path.display(), suspicions.join("; "));
+    }
+
+    if cli.check {
+        if findings.is_empty() {
+            if cli.verbose {
+                println!(
+                    "{}: OK - {} trailing tape marks",
+                    path.display(),
+                    diagnosis.trailing_tape_marks
+                );
+            }
+            return Ok(Outcome::Ok);
+        }
+        println!("{}: NEEDS FIX - {}", path.display(), findings.join("; "));
+        return Ok(Outcome::NeedsFix);
+    }
+
+    if let Some(output) = &cli.output {
+        if fs::canonicalize(output)
+            .is_ok_and(|output| fs::canonicalize(path).is_ok_and(|input| input == output))
+        {
+            bail!("--output must not be the input image; omit it to fix in place");
+        }
+        let length = rewrite(&diagnosis, options, &mut source, output)?;
+        if findings.is_empty() {
+            if cli.verbose {
+                println!(
+                    "{}: OK - copied unchanged -> {}",
+                    path.display(),
+                    output.display()
+                );
+            }
+            return Ok(Outcome::Ok);
+        }
+        report_fixed(cli, path, output, &findings, diagnosis.image_len, length);
+        return Ok(Outcome::Fixed);
+    }
+
+    if findings.is_empty() {
+        if cli.verbose {
+            println!("{}: OK - no fix needed", path.display());
+        }
+        return Ok(Outcome::Ok);
+    }
+
+    let mut image = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to open {} for writing", path.display()))?;
+    let length = match repair::repair_in_place(&diagnosis, options, &mut image, |file, length| {
+        file.set_len(length)
+    })
+    .context("failed to repair image in place")?
+    {
+        Some(length) => {
+            image.sync_all()?;
+            length
+        }
+        None => {
+            drop(image);
+            let temporary = temporary_path(path);
+            let permissions = fs::metadata(path)?.permissions();
+            let result = rewrite(&diagnosis, options, &mut source, &temporary).and_then(|length| {
+                fs::set_permissions(&temporary, permissions)?;
+                fs::rename(&temporary, path)
+                    .with_context(|| format!("failed to replace {}", path.display()))?;
+                Ok(length)
+            });
+            if result.is_err() {
+                let _ = fs::remove_file(&temporary);
+            }
+            result?
+        }
+    };
+    report_fixed(cli, path, path, &findings, diagnosis.image_len, length);
+    Ok(Outcome::Fixed)
+}
+
+fn rewrite(
+    diagnosis: &Diagnosis,
+    options: &RepairOptions,
+    source: &mut BufReader<File>,
+    output: &Path,
+) -> Result<u64> {
+    let file =
+        File::create(output).with_context(|| format!("failed to create {}", output.display()))?;
+    let mut writer = BufWriter::new(file);
+    let length = repair::write_repaired(diagnosis, options, source, &mut writer)
+        .with_context(|| format!("failed to write {}", output.display()))?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(length)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".rtfix");
+    path.with_file_name(name)
+}
+
+fn report_fixed(
+    cli: &Cli,
+    path: &Path,
+    output: &Path,
+    findings: &[String],
+    before: u64,
+    after: u64,
+) {
+    println!(
+        "{}: Fixed - {} -> {}",
+        path.display(),
+        findings.join("; "),
+        output.display()
+    );
+    if cli.verbose {
+        println!("  {} bytes -> {} bytes", before, after);
+    }
+}
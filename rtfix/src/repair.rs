@@ -0,0 +1,612 @@
+use rtsimh::{SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+const TAPE_MARK: [u8; 4] = [0; 4];
+const END_OF_MEDIUM: [u8; 4] = [0xFF; 4];
+const BAD_RECORD_CLASS: u32 = 0x8;
+const LENGTH_MASK: u32 = 0x0FFF_FFFF;
+
+/// Tape mark runs at least this long, followed by more data, may be rtimage
+/// retry artefacts rather than empty files.
+const SPURIOUS_RUN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EomMode {
+    /// Keep an end-of-medium marker only if the image already had one.
+    #[default]
+    Keep,
+    Add,
+    Strip,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RepairOptions {
+    pub eom: EomMode,
+    /// Keep what survives of a truncated final record as a bad (class 8) record.
+    pub salvage_truncated: bool,
+    /// Collapse suspected retry runs of tape marks to one mark. They can also
+    /// be legitimately empty files, so this is off by default.
+    pub collapse_tape_marks: bool,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            eom: EomMode::Keep,
+            salvage_truncated: true,
+            collapse_tape_marks: false,
+        }
+    }
+}
+
+/// A final record cut short by the end of the image.
+#[derive(Debug, Clone)]
+pub struct TruncatedRecord {
+    pub offset: u64,
+    pub word: u32,
+    /// Payload bytes present in the image.
+    pub present: u64,
+    /// Whether the whole payload is present and only the trailing length is lost.
+    pub complete: bool,
+}
+
+/// Everything the scan found about the end of an image and any retry artefacts.
+#[derive(Debug, Default)]
+pub struct Diagnosis {
+    pub image_len: u64,
+    /// Offset just past the last object that is kept as it is.
+    pub content_end: u64,
+    pub trailing_tape_marks: usize,
+    pub has_eom: bool,
+    pub bytes_after_eom: u64,
+    /// Runs of tape marks inside the image that look like retry artefacts,
+    /// as (offset, count).
+    pub spurious_runs: Vec<(u64, usize)>,
+    pub truncated: Option<TruncatedRecord>,
+    pub zero_fill: u64,
+    /// Erase gap and half-gap bytes among or after the trailing tape marks,
+    /// dropped like zero fill.
+    pub trailing_gaps: u64,
+    pub stray_bytes: u64,
+    /// Damage before the tail, which rtfix does not repair.
+    pub damage: Option<SimhError>,
+}
+
+enum Piece {
+    Copy(Range<u64>),
+    Bytes(Vec<u8>),
+}
+
+impl Piece {
+    fn len(&self) -> u64 {
+        match self {
+            Piece::Copy(range) => range.end - range.start,
+            Piece::Bytes(bytes) => bytes.len() as u64,
+        }
+    }
+}
+
+/// Walks the image once without reading record payloads.
+pub fn diagnose<R: Read + Seek>(source: &mut R) -> SimhResult<Diagnosis> {
+    let image_len = source.seek(SeekFrom::End(0))?;
+    source.seek(SeekFrom::Start(0))?;
+
+    let mut diagnosis = Diagnosis {
+        image_len,
+        ..Default::default()
+    };
+    let mut reader = SimhTapeReader::new(&mut *source);
+    let mut run_start = 0;
+    let mut run = 0;
+    // Tape mark runs after the last data that a gap cut short; they only
+    // become interior runs if more data follows.
+    let mut trailing_runs = Vec::new();
+    let mut failure = None;
+
+    loop {
+        let before = reader.position()?;
+        match reader.skip_block() {
+            Ok(SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                ..
+            }) => {
+                if run == 0 {
+                    run_start = before;
+                }
+                run += 1;
+            }
+            Ok(SimhTapeBlock::TapeMark {
+                kind:
+                    SimhTapeMark::EraseGap
+                    | SimhTapeMark::HalfGapForward
+                    | SimhTapeMark::HalfGapReverse { .. },
+                ..
+            }) => {
+                if run == 0 && trailing_runs.is_empty() {
+                    // A gap straight after data is part of the content.
+                    diagnosis.content_end = reader.position()?;
+                    continue;
+                }
+                if run > 0 {
+                    trailing_runs.push((run_start, run));
+                    run = 0;
+                }
+                diagnosis.trailing_gaps += reader.position()? - before;
+            }
+            Ok(SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::EndOfTape,
+                ..
+            }) => {
+                diagnosis.has_eom = true;
+                diagnosis.bytes_after_eom = image_len - (before + 4);
+                break;
+            }
+            Ok(SimhTapeBlock::EndOfStream | SimhTapeBlock::BeginningOfTape) => break,
+            Ok(_) => {
+                trailing_runs.push((run_start, run));
+                run = 0;
+                keep_trailing(&mut diagnosis, &mut trailing_runs);
+                diagnosis.content_end = reader.position()?;
+            }
+            Err(err) => {
+                failure = Some((before, err));
+                break;
+            }
+        }
+    }
+    diagnosis.trailing_tape_marks =
+        run + trailing_runs.iter().map(|(_, count)| count).sum::<usize>();
+
+    let Some((offset, err)) = failure else {
+        return Ok(diagnosis);
+    };
+
+    if is_zero_from(source, offset)? {
+        diagnosis.zero_fill = image_len - offset;
+        return Ok(diagnosis);
+    }
+
+    let word = read_word_at(source, offset)?;
+    let length = word & LENGTH_MASK;
+    let payload_at = offset + 4;
+    let trailing_at = payload_at + u64::from(length) + u64::from(length % 2);
+    let present = image_len.saturating_sub(payload_at).min(u64::from(length));
+    let truncated = match err {
+        SimhError::TruncatedWord { .. } => {
+            diagnosis.stray_bytes = image_len - offset;
+            return Ok(diagnosis);
+        }
+        SimhError::TruncatedRecord { .. } | SimhError::MissingTrailingLength { .. } => true,
+        SimhError::LengthMismatch { trailing: 0, .. } => is_zero_from(source, trailing_at)?,
+        _ => false,
+    };
+    if !truncated {
+        diagnosis.damage = Some(err);
+        return Ok(diagnosis);
+    }
+
+    trailing_runs.push((run_start, run));
+    keep_trailing(&mut diagnosis, &mut trailing_runs);
+    diagnosis.trailing_tape_marks = 0;
+    diagnosis.content_end = offset;
+    diagnosis.truncated = Some(TruncatedRecord {
+        offset,
+        word,
+        present,
+        complete: present == u64::from(length),
+    });
+    Ok(diagnosis)
+}
+
+/// Data follows the tape marks and gaps seen since the last data, so they are
+/// kept as they are.
+fn keep_trailing(diagnosis: &mut Diagnosis, trailing_runs: &mut Vec<(u64, usize)>) {
+    diagnosis.spurious_runs.extend(
+        trailing_runs
+            .drain(..)
+            .filter(|&(_, count)| count >= SPURIOUS_RUN),
+    );
+    diagnosis.trailing_gaps = 0;
+}
+
+impl Diagnosis {
+    /// One line per problem found; empty when the image needs no repair.
+    pub fn findings(&self, options: &RepairOptions) -> Vec<String> {
+        let mut findings = Vec::new();
+        if let Some(err) = &self.damage {
+            findings.push(format!("damaged before its tail, not repairable: {err}"));
+            return findings;
+        }
+        if self.image_len == 0 {
+            return findings;
+        }
+
+        if options.collapse_tape_marks {
+            for (offset, count) in &self.spurious_runs {
+                findings.push(format!(
+                    "run of {count} tape marks at offset {offset} (retry artefact)"
+                ));
+            }
+        }
+        if let Some(truncated) = &self.truncated {
+            let length = truncated.word & LENGTH_MASK;
+            findings.push(if truncated.complete {
+                format!(
+                    "final record at offset {} is missing its trailing length",
+                    truncated.offset
+                )
+            } else {
+                format!(
+                    "final record at offset {} is truncated ({} of {} bytes present){}",
+                    truncated.offset,
+                    truncated.present,
+                    length,
+                    if options.salvage_truncated && truncated.present > 0 {
+                        ""
+                    } else {
+                        " and will be dropped"
+                    }
+                )
+            });
+        }
+        if self.zero_fill > 0 {
+            findings.push(format!(
+                "{} bytes of zero fill after the last object",
+                self.zero_fill
+            ));
+        }
+        if self.trailing_gaps > 0 {
+            findings.push(format!(
+                "{} bytes of erase gap after the trailing tape marks",
+                self.trailing_gaps
+            ));
+        }
+        if self.stray_bytes > 0 {
+            findings.push(format!(
+                "{} stray bytes after the last object",
+                self.stray_bytes
+            ));
+        }
+        if self.trailing_tape_marks != 2 {
+            findings.push(format!(
+                "{} trailing tape marks (expected 2)",
+                self.trailing_tape_marks
+            ));
+        }
+        if self.bytes_after_eom > 0 {
+            findings.push(format!(
+                "{} bytes after the end-of-medium marker",
+                self.bytes_after_eom
+            ));
+        }
+        match (options.eom, self.has_eom) {
+            (EomMode::Add, false) => findings.push("no end-of-medium marker".to_string()),
+            (EomMode::Strip, true) => findings.push("end-of-medium marker present".to_string()),
+            _ => {}
+        }
+        findings
+    }
+
+    /// Things that may be wrong but are left alone unless asked for, such as
+    /// runs of tape marks that could be empty files.
+    pub fn suspicions(&self, options: &RepairOptions) -> Vec<String> {
+        if options.collapse_tape_marks || self.damage.is_some() {
+            return Vec::new();
+        }
+        self.spurious_runs
+            .iter()
+            .map(|(offset, count)| {
+                format!(
+                    "run of {count} tape marks at offset {offset} (empty files or a retry artefact; --collapse-tape-marks removes it)"
+                )
+            })
+            .collect()
+    }
+
+    fn plan(&self, options: &RepairOptions) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        let mut cursor = 0;
+        if options.collapse_tape_marks {
+            for &(offset, count) in &self.spurious_runs {
+                pieces.push(Piece::Copy(cursor..offset + 4));
+                cursor = offset + 4 * count as u64;
+            }
+        }
+        pieces.push(Piece::Copy(cursor..self.content_end));
+
+        if let Some(truncated) = &self.truncated
+            && (truncated.complete || (options.salvage_truncated && truncated.present > 0))
+        {
+            let word = if truncated.complete {
+                truncated.word
+            } else {
+                (BAD_RECORD_CLASS << 28) | truncated.present as u32
+            };
+            let payload_at = truncated.offset + 4;
+            pieces.push(Piece::Bytes(word.to_le_bytes().to_vec()));
+            pieces.push(Piece::Copy(payload_at..payload_at + truncated.present));
+            let mut tail = Vec::new();
+            if truncated.present % 2 == 1 {
+                tail.push(0);
+            }
+            tail.extend_from_slice(&word.to_le_bytes());
+            pieces.push(Piece::Bytes(tail));
+        }
+
+        let mut tail = [TAPE_MARK, TAPE_MARK].concat();
+        let eom = match options.eom {
+            EomMode::Keep => self.has_eom,
+            EomMode::Add => true,
+            EomMode::Strip => false,
+        };
+        if eom {
+            tail.extend_from_slice(&END_OF_MEDIUM);
+        }
+        pieces.push(Piece::Bytes(tail));
+        pieces
+    }
+}
+
+/// Writes the repaired image to `target`, which must be a different file.
+pub fn write_repaired<R: Read + Seek, W: Write>(
+    diagnosis: &Diagnosis,
+    options: &RepairOptions,
+    source: &mut R,
+    target: &mut W,
+) -> io::Result<u64> {
+    let mut written = 0;
+    for piece in diagnosis.plan(options) {
+        match &piece {
+            Piece::Copy(range) => {
+                source.seek(SeekFrom::Start(range.start))?;
+                let copied = io::copy(&mut source.take(range.end - range.start), target)?;
+                if copied != piece.len() {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+            Piece::Bytes(bytes) => target.write_all(bytes)?,
+        }
+        written += piece.len();
+    }
+    Ok(written)
+}
+
+/// Repairs `image` where it lies, provided every kept byte range stays at its
+/// offset; returns `None` when the image has to be rewritten instead.
+pub fn repair_in_place<F: Read + Write + Seek>(
+    diagnosis: &Diagnosis,
+    options: &RepairOptions,
+    image: &mut F,
+    set_len: impl FnOnce(&mut F, u64) -> io::Result<()>,
+) -> io::Result<Option<u64>> {
+    let pieces = diagnosis.plan(options);
+    let mut position = 0;
+    for piece in &pieces {
+        if let Piece::Copy(range) = piece
+            && range.start != position
+            && range.start != range.end
+        {
+            return Ok(None);
+        }
+        position += piece.len();
+    }
+
+    let mut position = 0;
+    for piece in &pieces {
+        if let Piece::Bytes(bytes) = piece {
+            image.seek(SeekFrom::Start(position))?;
+            image.write_all(bytes)?;
+        }
+        position += piece.len();
+    }
+    set_len(image, position)?;
+    Ok(Some(position))
+}
+
+fn is_zero_from<R: Read + Seek>(source: &mut R, offset: u64) -> io::Result<bool> {
+    source.seek(SeekFrom::Start(offset))?;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = source.read(&mut buf)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        if buf[..n].iter().any(|&byte| byte != 0) {
+            return Ok(false);
+        }
+    }
+}
+
+fn read_word_at<R: Read + Seek>(source: &mut R, offset: u64) -> io::Result<u32> {
+    source.seek(SeekFrom::Start(offset))?;
+    let mut word = [0u8; 4];
+    source.read_exact(&mut word)?;
+    Ok(u32::from_le_bytes(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtsimh::SimhTapeWriter;
+    use std::io::Cursor;
+
+    fn image(build: impl FnOnce(&mut SimhTapeWriter<&mut Vec<u8>>)) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        build(&mut SimhTapeWriter::new(&mut bytes));
+        bytes
+    }
+
+    fn repaired(bytes: &[u8], options: &RepairOptions) -> (Vec<String>, Vec<u8>) {
+        let mut source = Cursor::new(bytes.to_vec());
+        let diagnosis = diagnose(&mut source).unwrap();
+        let mut target = Vec::new();
+        write_repaired(&diagnosis, options, &mut source, &mut target).unwrap();
+        (diagnosis.findings(options), target)
+    }
+
+    #[test]
+    fn normalises_the_tail_and_collapses_retry_runs() {
+        let expected = image(|w| {
+            w.write_record(&[1; 5]).unwrap();
+            w.write_tape_mark().unwrap();
+            w.write_record(&[2; 4]).unwrap();
+            w.write_tape_mark().unwrap();
+            w.write_tape_mark().unwrap();
+        });
+
+        let damaged = image(|w| {
+            w.write_record(&[1; 5]).unwrap();
+            for _ in 0..4 {
+                w.write_tape_mark().unwrap();
+            }
+            w.write_record(&[2; 4]).unwrap();
+            for _ in 0..5 {
+                w.write_tape_mark().unwrap();
+            }
+        });
+        let mut damaged = damaged;
+        damaged.extend_from_slice(&[0, 0]);
+
+        let options = RepairOptions {
+            collapse_tape_marks: true,
+            ..RepairOptions::default()
+        };
+        let (findings, fixed) = repaired(&damaged, &options);
+        assert_eq!(findings.len(), 3, "{findings:?}");
+        assert_eq!(fixed, expected);
+
+        let (findings, again) = repaired(&fixed, &options);
+        assert!(findings.is_empty(), "{findings:?}");
+        assert_eq!(again, expected);
+
+        let eom = RepairOptions {
+            eom: EomMode::Add,
+            ..options
+        };
+        let (_, with_eom) = repaired(&fixed, &eom);
+        assert_eq!(&with_eom[expected.len()..], &END_OF_MEDIUM);
+
+        let mut in_place = Cursor::new(damaged.clone());
+        let diagnosis = diagnose(&mut in_place).unwrap();
+        assert!(
+            repair_in_place(&diagnosis, &options, &mut in_place, |_, _| Ok(()))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn keeps_runs_of_tape_marks_unless_asked_to_collapse_them() {
+        let bytes = image(|w| {
+            w.write_record(b"hello").unwrap();
+            for _ in 0..3 {
+                w.write_tape_mark().unwrap();
+            }
+            w.write_record(b"xxxxxxxx").unwrap();
+            w.write_tape_mark().unwrap();
+            w.write_tape_mark().unwrap();
+        });
+
+        let options = RepairOptions::default();
+        let mut source = Cursor::new(bytes.clone());
+        let diagnosis = diagnose(&mut source).unwrap();
+        assert!(diagnosis.findings(&options).is_empty());
+        assert_eq!(diagnosis.suspicions(&options).len(), 1);
+        let (_, fixed) = repaired(&bytes, &options);
+        assert_eq!(fixed, bytes);
+
+        let collapse = RepairOptions {
+            collapse_tape_marks: true,
+            ..options
+        };
+        assert!(diagnosis.suspicions(&collapse).is_empty());
+        let (findings, collapsed) = repaired(&bytes, &collapse);
+        assert_eq!(findings.len(), 1, "{findings:?}");
+        assert_eq!(collapsed.len(), bytes.len() - 8);
+    }
+
+    #[test]
+    fn drops_erase_gaps_after_the_trailing_tape_marks() {
+        let expected = image(|w| {
+            w.write_record(&[1; 4]).unwrap();
+            w.write_erase_gap_markers(1).unwrap();
+            w.write_tape_mark().unwrap();
+            w.write_tape_mark().unwrap();
+        });
+        let options = RepairOptions::default();
+        let (findings, fixed) = repaired(&expected, &options);
+        assert!(findings.is_empty(), "{findings:?}");
+        assert_eq!(fixed, expected);
+
+        let mut gapped = expected.clone();
+        gapped.extend_from_slice(&[0xFE, 0xFF, 0xFF, 0xFF, 0xFE, 0xFF, 0xFF, 0xFF]);
+        let (findings, fixed) = repaired(&gapped, &options);
+        assert_eq!(
+            findings,
+            vec!["8 bytes of erase gap after the trailing tape marks".to_string()]
+        );
+        assert_eq!(fixed, expected);
+
+        let mut in_place = Cursor::new(gapped);
+        let diagnosis = diagnose(&mut in_place).unwrap();
+        let length = repair_in_place(&diagnosis, &options, &mut in_place, |_, _| Ok(())).unwrap();
+        assert_eq!(length, Some(expected.len() as u64));
+    }
+
+    #[test]
+    fn salvages_truncated_final_records() {
+        let mut bytes = image(|w| {
+            w.write_record(&[1; 4]).unwrap();
+            w.write_tape_mark().unwrap();
+            w.write_record(&[2; 10]).unwrap();
+        });
+        bytes.truncate(bytes.len() - 7);
+
+        let options = RepairOptions::default();
+        let (findings, fixed) = repaired(&bytes, &options);
+        assert!(
+            findings[0].contains("7 of 10 bytes present"),
+            "{findings:?}"
+        );
+        let expected = image(|w| {
+            w.write_record(&[1; 4]).unwrap();
+            w.write_tape_mark().unwrap();
+            w.write_bad_record(&[2; 7]).unwrap();
+            w.write_tape_mark().unwrap();
+            w.write_tape_mark().unwrap();
+        });
+        assert_eq!(fixed, expected);
+
+        let mut in_place = Cursor::new(bytes.clone());
+        let diagnosis = diagnose(&mut in_place).unwrap();
+        let length = repair_in_place(&diagnosis, &options, &mut in_place, |image, length| {
+            image.get_mut().truncate(length as usize);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(length, Some(expected.len() as u64));
+        assert_eq!(in_place.into_inner(), expected);
+
+        let drop = RepairOptions {
+            salvage_truncated: false,
+            ..options
+        };
+        let (_, dropped) = repaired(&bytes, &drop);
+        assert_eq!(dropped.len(), 12 + 4 + 8);
+
+        let mut lost_trailer = image(|w| w.write_record(&[3; 6]).unwrap());
+        lost_trailer[10..14].fill(0);
+        lost_trailer.extend_from_slice(&[0; 9]);
+        let (findings, fixed) = repaired(&lost_trailer, &options);
+        assert!(findings[0].contains("missing its trailing length"));
+        assert_eq!(
+            fixed,
+            image(|w| {
+                w.write_record(&[3; 6]).unwrap();
+                w.write_tape_mark().unwrap();
+                w.write_tape_mark().unwrap();
+            })
+        );
+    }
+}
@@ -0,0 +1,426 @@
+use rtsimh::{
+    SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader, SimhTapeWriter, TAPE_DESCRIPTION_CLASS,
+};
+use std::fmt;
+use std::io::{self, Read, Seek, Write};
+
+const BAD_RECORD_CLASS: u8 = 0x8;
+
+/// A record where the passes did not simply agree. Files, records and passes
+/// are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// The passes hold different numbers of records for this file; `None`
+    /// where a pass ended before reaching it.
+    RecordCounts {
+        file: usize,
+        counts: Vec<Option<usize>>,
+    },
+    /// Clean reads of the record differ; the most common one was kept.
+    Disagree {
+        file: usize,
+        record: usize,
+        agreeing: usize,
+        reads: usize,
+        chosen: usize,
+    },
+    /// No pass read the record cleanly; the longest bad read was kept.
+    Bad {
+        file: usize,
+        record: usize,
+        chosen: usize,
+        length: usize,
+    },
+    /// A pass had to skip damaged data after `records` records of the file;
+    /// the rest of that file was not taken from this pass.
+    Damaged {
+        file: usize,
+        pass: usize,
+        records: usize,
+        offset: u64,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::RecordCounts { file, counts } => {
+                let counts: Vec<String> = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(pass, count)| match count {
+                        Some(count) => format!("pass {}: {}", pass + 1, count),
+                        None => format!("pass {}: missing", pass + 1),
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "File {file}: record counts differ ({})",
+                    counts.join(", ")
+                )
+            }
+            Divergence::Disagree {
+                file,
+                record,
+                agreeing,
+                reads,
+                chosen,
+            } => write!(
+                f,
+                "File {file} record {record}: passes disagree, {agreeing} of {reads} clean reads agree; kept pass {chosen}"
+            ),
+            Divergence::Bad {
+                file,
+                record,
+                chosen,
+                length,
+            } => write!(
+                f,
+                "File {file} record {record}: no clean read in any pass; kept {length} bytes from pass {chosen}"
+            ),
+            Divergence::Damaged {
+                file,
+                pass,
+                records,
+                offset,
+            } => write!(
+                f,
+                "File {file}: pass {pass} is damaged at offset {offset} after record {records}; the rest of the file was not merged from it"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConsensusReport {
+    pub records: usize,
+    /// Read cleanly and identically by every pass.
+    pub unanimous: usize,
+    /// Read cleanly by some passes, and bad or missing in the others.
+    pub recovered: usize,
+    pub divergent: usize,
+    pub bad: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl ConsensusReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} records: {} unanimous, {} recovered from other passes, {} divergent, {} bad in every pass",
+            self.records, self.unanimous, self.recovered, self.divergent, self.bad
+        )
+    }
+
+    /// Writes the divergence report for passes read from `names`.
+    pub fn write_to<W: Write>(&self, mut out: W, names: &[String]) -> io::Result<()> {
+        writeln!(out, "rtimage consensus report")?;
+        for (pass, name) in names.iter().enumerate() {
+            writeln!(out, "Pass {}: {}", pass + 1, name)?;
+        }
+        writeln!(out)?;
+        for divergence in &self.divergences {
+            writeln!(out, "{divergence}")?;
+        }
+        if self.divergences.is_empty() {
+            writeln!(out, "All passes agree.")?;
+        }
+        writeln!(out)?;
+        writeln!(out, "{}", self.summary())
+    }
+}
+
+/// One tape file as a pass read it.
+struct PassFile {
+    records: Vec<(u8, Vec<u8>)>,
+    ended_by_tape_mark: bool,
+    /// Where the reader first had to skip damaged data. Records after it are
+    /// not used, since they can no longer be matched up by position.
+    damaged_at: Option<u64>,
+}
+
+/// Reads the next tape file, or `None` once the image is exhausted. Gaps and
+/// markers carry no data and are passed over.
+fn read_file<R: Read + Seek>(reader: &mut SimhTapeReader<R>) -> SimhResult<Option<PassFile>> {
+    let mut file = PassFile {
+        records: Vec::new(),
+        ended_by_tape_mark: false,
+        damaged_at: None,
+    };
+    loop {
+        match reader.next_block()? {
+            // Description records say how each pass was made, not what is on the tape.
+            SimhTapeBlock::Record(record) if record.header.class == TAPE_DESCRIPTION_CLASS => {}
+            SimhTapeBlock::Record(_) if file.damaged_at.is_some() => {}
+            SimhTapeBlock::Record(record) => file.records.push((record.header.class, record.data)),
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                ..
+            } => {
+                file.ended_by_tape_mark = true;
+                return Ok(Some(file));
+            }
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::EndOfTape,
+                ..
+            }
+            | SimhTapeBlock::EndOfStream
+            | SimhTapeBlock::BeginningOfTape => {
+                return Ok((!file.records.is_empty() || file.damaged_at.is_some()).then_some(file));
+            }
+            SimhTapeBlock::Resync(resync) => {
+                file.damaged_at.get_or_insert(resync.start);
+            }
+            SimhTapeBlock::TapeMark { .. } => {}
+        }
+    }
+}
+
+/// Merges several reads of the same tape into one image, record by record.
+/// Records are matched by position within each tape file; clean reads beat
+/// bad (class 8) ones, and disagreeing clean reads are settled by majority,
+/// ties going to the earliest pass.
+pub fn merge<R: Read + Seek, W: Write>(
+    passes: Vec<R>,
+    writer: &mut SimhTapeWriter<W>,
+) -> SimhResult<ConsensusReport> {
+    let mut readers: Vec<_> = passes
+        .into_iter()
+        .map(|pass| SimhTapeReader::new(pass).with_recovery(true))
+        .collect();
+    let mut report = ConsensusReport::default();
+
+    for file in 1.. {
+        let files = readers
+            .iter_mut()
+            .map(read_file)
+            .collect::<SimhResult<Vec<_>>>()?;
+        if files.iter().all(Option::is_none) {
+            break;
+        }
+
+        for (pass, read) in files.iter().enumerate() {
+            if let Some(PassFile {
+                records,
+                damaged_at: Some(offset),
+                ..
+            }) = read
+            {
+                report.divergences.push(Divergence::Damaged {
+                    file,
+                    pass: pass + 1,
+                    records: records.len(),
+                    offset: *offset,
+                });
+            }
+        }
+
+        let counts: Vec<Option<usize>> = files
+            .iter()
+            .map(|pass| pass.as_ref().map(|pass| pass.records.len()))
+            .collect();
+        if counts.iter().any(|count| *count != counts[0]) {
+            report.divergences.push(Divergence::RecordCounts {
+                file,
+                counts: counts.clone(),
+            });
+        }
+
+        let longest = counts.iter().flatten().copied().max().unwrap_or(0);
+        for index in 0..longest {
+            let reads: Vec<(usize, &(u8, Vec<u8>))> = files
+                .iter()
+                .enumerate()
+                .filter_map(|(pass, file)| Some((pass + 1, file.as_ref()?.records.get(index)?)))
+                .collect();
+            let (class, data) = settle(&mut report, file, index + 1, &reads, files.len());
+            writer.write_record_with_class(class, data)?;
+        }
+
+        if files.iter().flatten().any(|pass| pass.ended_by_tape_mark) {
+            writer.write_tape_mark()?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Picks the record to keep from each pass's read of it.
+fn settle<'a>(
+    report: &mut ConsensusReport,
+    file: usize,
+    record: usize,
+    reads: &[(usize, &'a (u8, Vec<u8>))],
+    passes: usize,
+) -> (u8, &'a [u8]) {
+    report.records += 1;
+    let clean: Vec<_> = reads
+        .iter()
+        .filter(|(_, (class, _))| *class != BAD_RECORD_CLASS)
+        .collect();
+
+    let votes = |data: &Vec<u8>| clean.iter().filter(|(_, (_, other))| other == data).count();
+    let Some((chosen, (class, data))) = clean
+        .iter()
+        .rev()
+        .max_by_key(|(_, (_, data))| votes(data))
+        .copied()
+    else {
+        let (chosen, (class, data)) = reads
+            .iter()
+            .rev()
+            .max_by_key(|(_, (_, data))| data.len())
+            .expect("every record has at least one read");
+        report.bad += 1;
+        report.divergences.push(Divergence::Bad {
+            file,
+            record,
+            chosen: *chosen,
+            length: data.len(),
+        });
+        return (*class, data);
+    };
+    let agreeing = votes(data);
+    if agreeing < clean.len() {
+        report.divergent += 1;
+        report.divergences.push(Divergence::Disagree {
+            file,
+            record,
+            agreeing,
+            reads: clean.len(),
+            chosen: *chosen,
+        });
+    } else if clean.len() == passes {
+        report.unanimous += 1;
+    } else {
+        report.recovered += 1;
+    }
+    (*class, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn merged(passes: Vec<Vec<u8>>) -> (Vec<u8>, ConsensusReport) {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        let report = merge(passes.into_iter().map(Cursor::new).collect(), &mut writer).unwrap();
+        (writer.into_inner(), report)
+    }
+
+    #[test]
+    fn prefers_clean_reads_and_keeps_the_longest_bad_one() {
+        let mut first = SimhTapeWriter::new(Vec::new());
+        first.write_record(&[1; 4]).unwrap();
+        first.write_bad_record(&[0; 2]).unwrap();
+        first.write_bad_record(&[3; 1]).unwrap();
+        first.write_tape_mark().unwrap();
+        first.write_tape_mark().unwrap();
+
+        let mut second = SimhTapeWriter::new(Vec::new());
+        second.write_record(&[1; 4]).unwrap();
+        second.write_record(&[2; 4]).unwrap();
+        second.write_bad_record(&[3; 3]).unwrap();
+        second.write_tape_mark().unwrap();
+        second.write_tape_mark().unwrap();
+        let expected = second.into_inner();
+
+        let (bytes, report) = merged(vec![first.into_inner(), expected.clone()]);
+        assert_eq!(bytes, expected);
+        assert_eq!(
+            (
+                report.records,
+                report.unanimous,
+                report.recovered,
+                report.bad
+            ),
+            (3, 1, 1, 1)
+        );
+        assert_eq!(
+            report.divergences,
+            vec![Divergence::Bad {
+                file: 1,
+                record: 3,
+                chosen: 2,
+                length: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn settles_disagreements_by_majority_and_reports_them() {
+        let pass = |second: u8, extra: bool| {
+            let mut writer = SimhTapeWriter::new(Vec::new());
+            writer.write_record(&[1; 4]).unwrap();
+            writer.write_record(&[second; 4]).unwrap();
+            if extra {
+                writer.write_record(&[9; 2]).unwrap();
+            }
+            writer.write_tape_mark().unwrap();
+            writer.into_inner()
+        };
+        let (bytes, report) = merged(vec![pass(5, false), pass(6, true), pass(6, false)]);
+
+        assert_eq!(bytes, pass(6, true));
+        assert_eq!(report.divergent, 1);
+        assert_eq!(
+            report.divergences,
+            vec![
+                Divergence::RecordCounts {
+                    file: 1,
+                    counts: vec![Some(2), Some(3), Some(2)]
+                },
+                Divergence::Disagree {
+                    file: 1,
+                    record: 2,
+                    agreeing: 2,
+                    reads: 3,
+                    chosen: 2
+                },
+            ]
+        );
+        assert_eq!(
+            report.divergences[1].to_string(),
+            "File 1 record 2: passes disagree, 2 of 3 clean reads agree; kept pass 2"
+        );
+    }
+
+    #[test]
+    fn stops_merging_a_pass_file_at_damaged_data() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 4]).unwrap();
+        writer.write_record(&[2; 4]).unwrap();
+        writer.write_record(&[3; 4]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_record(&[4; 4]).unwrap();
+        writer.write_tape_mark().unwrap();
+        let clean = writer.into_inner();
+
+        // Break the trailing length of the second record, so the reader
+        // resynchronises onto the third.
+        let mut damaged = clean.clone();
+        damaged[20..24].fill(0x99);
+
+        let (bytes, report) = merged(vec![clean.clone(), damaged]);
+        assert_eq!(bytes, clean);
+        assert_eq!(
+            (report.records, report.unanimous, report.recovered),
+            (4, 2, 2)
+        );
+        assert_eq!(
+            report.divergences,
+            vec![
+                Divergence::Damaged {
+                    file: 1,
+                    pass: 2,
+                    records: 1,
+                    offset: 12
+                },
+                Divergence::RecordCounts {
+                    file: 1,
+                    counts: vec![Some(3), Some(1)]
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_LINES: AtomicBool = AtomicBool::new(false);
+
+/// Prints a line of human-readable output: to stdout normally, and to
+/// stderr once stdout is carrying the JSON event stream.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::events::json_enabled() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// What happened during a capture, for wrappers that drive rtimage. Files
+/// and blocks are numbered from 1; `block` counts within its file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Started {
+        version: &'static str,
+        git_hash: &'static str,
+        device: String,
+        output: String,
+        timestamp: String,
+    },
+    PassStarted {
+        pass: u32,
+        passes: u32,
+        image: String,
+    },
+    FileStarted {
+        file: u32,
+    },
+    RecordRead {
+        file: u32,
+        block: u32,
+        length: usize,
+    },
+    BadBlock {
+        file: u32,
+        block: u32,
+        length: usize,
+        reason: String,
+    },
+    Retry {
+        file: u32,
+        block: u32,
+        attempt: u32,
+        reason: String,
+    },
+    Recovered {
+        file: u32,
+        block: u32,
+        attempts: u32,
+    },
+    /// The drive returned nothing or was not ready, and is being asked again.
+    Reattempt {
+        attempt: u32,
+        limit: u32,
+        reason: String,
+    },
+    TapeMark {
+        file: u32,
+    },
+    FileFinished {
+        file: u32,
+        blocks: u32,
+        bytes: usize,
+    },
+    EndOfData,
+    Kernel {
+        source: &'static str,
+        line: String,
+    },
+    Info {
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+    Finished {
+        records: u64,
+        bytes: u64,
+        recovered_errors: usize,
+        bad_blocks: usize,
+        sha256: String,
+        manifest: String,
+    },
+}
+
+pub fn enable_json() {
+    JSON_LINES.store(true, Ordering::Relaxed);
+}
+
+pub fn json_enabled() -> bool {
+    JSON_LINES.load(Ordering::Relaxed)
+}
+
+/// Writes `event` to stdout as one JSON line, when the stream is enabled.
+pub fn emit(event: Event) {
+    if !json_enabled() {
+        return;
+    }
+    let mut stdout = io::stdout().lock();
+    if let Ok(line) = serde_json::to_string(&event) {
+        let _ = writeln!(stdout, "{line}");
+        let _ = stdout.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_events_by_name() {
+        let json = |event: &Event| serde_json::to_value(event).unwrap();
+        assert_eq!(
+            json(&Event::RecordRead {
+                file: 2,
+                block: 7,
+                length: 10240
+            }),
+            serde_json::json!({"event": "record_read", "file": 2, "block": 7, "length": 10240})
+        );
+        assert_eq!(
+            json(&Event::EndOfData),
+            serde_json::json!({"event": "end_of_data"})
+        );
+        assert_eq!(
+            json(&Event::Kernel {
+                source: "dmesg",
+                line: "st0: Medium Error".into()
+            })["event"],
+            "kernel"
+        );
+    }
+}
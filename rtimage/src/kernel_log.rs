@@ -1,6 +1,8 @@
+use crate::events::{self, Event};
 use anyhow::{Context, Result};
 use std::io::{BufRead, BufReader};
 use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -8,6 +10,7 @@ use std::time::Duration;
 pub struct KernelLogWatcher {
     child: Child,
     handle: Option<thread::JoinHandle<()>>,
+    lines: Arc<Mutex<Vec<String>>>,
 }
 
 impl KernelLogWatcher {
@@ -27,18 +30,29 @@ impl KernelLogWatcher {
             .take()
             .context("failed to capture kernel log stdout")?;
 
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let captured = Arc::clone(&lines);
         let handle = thread::Builder::new()
             .name("kernel-log".into())
             .spawn(move || {
-                pump_kernel_output(stdout, normalized_tokens, source_label);
+                pump_kernel_output(stdout, normalized_tokens, source_label, &captured);
             })
             .context("failed to start kernel log reader thread")?;
 
         Ok(Self {
             child,
             handle: Some(handle),
+            lines,
         })
     }
+
+    /// The matching lines seen so far.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines
+            .lock()
+            .map(|lines| lines.clone())
+            .unwrap_or_default()
+    }
 }
 
 impl Drop for KernelLogWatcher {
@@ -106,7 +120,12 @@ fn ensure_long_running(child: &mut Child, label: &str) -> Result<()> {
     Ok(())
 }
 
-fn pump_kernel_output(mut stdout: ChildStdout, tokens: Vec<String>, label: &'static str) {
+fn pump_kernel_output(
+    mut stdout: ChildStdout,
+    tokens: Vec<String>,
+    label: &'static str,
+    captured: &Mutex<Vec<String>>,
+) {
     let mut reader = BufReader::new(&mut stdout);
     let mut line = String::new();
 
@@ -118,6 +137,13 @@ fn pump_kernel_output(mut stdout: ChildStdout, tokens: Vec<String>, label: &'sta
                 let trimmed = line.trim();
                 if should_emit(trimmed, &tokens) {
                     eprintln!("[kernel:{label}] {trimmed}");
+                    events::emit(Event::Kernel {
+                        source: label,
+                        line: trimmed.to_owned(),
+                    });
+                    if let Ok(mut lines) = captured.lock() {
+                        lines.push(trimmed.to_owned());
+                    }
                 }
             }
             Err(err) => {
@@ -1,17 +1,35 @@
+mod consensus;
+#[macro_use]
+mod events;
 mod kernel_log;
+mod manifest;
+#[cfg(test)]
+mod mock;
+mod progress;
 mod reader;
+mod resume;
+mod sg;
+mod st;
 mod utils;
 
+use crate::events::Event;
 use crate::kernel_log::KernelLogWatcher;
-use crate::reader::{TapeEvent, start_reader_thread};
-use crate::utils::{device_token_candidates, make_input_name, make_output_name};
+use crate::manifest::Manifest;
+use crate::progress::{Progress, Reel};
+use crate::reader::{
+    DEFAULT_BLOCK_SIZE, PlainSource, ReadOptions, TapeEvent, TapeSource, start_reader_thread,
+};
+use crate::resume::ResumePoint;
+use crate::utils::{
+    device_token_candidates, is_sg_device, make_input_name, make_output_name, sibling_path,
+};
 use anyhow::{Context, Result, bail};
 use chrono::Local;
 use clap::Parser;
 use crossbeam_channel::bounded;
-use rtsimh::{SimhTapeWriter, VERSION};
+use rtsimh::{SimhTapeWriter, TapeDescription, VERSION};
 use std::fs::{File, OpenOptions};
-use std::io::{self, BufWriter, Read};
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::sync::{
     OnceLock,
@@ -33,7 +51,9 @@ static SUMMARY_PRINTED: AtomicBool = AtomicBool::new(false);
     after_help = "EXAMPLES:\n  \
                   rtimage /dev/nst0 mytape.tap\n  \
                   rtimage nst0 mytape.tap\n  \
-                  rtimage - mytape.tap < raw_tape_data.bin"
+                  rtimage sg1 mytape.tap\n  \
+                  rtimage - mytape.tap < raw_tape_data.bin\n  \
+                  rtimage --json nst0 mytape.tap"
 )]
 struct Args {
     /// Input device path (e.g., /dev/nst0, nst0) or "-" for stdin.
@@ -51,19 +71,121 @@ struct Args {
     /// Force overwrite if output file already exists.
     #[arg(long)]
     ignore_existing: bool,
+
+    /// Continue an interrupted capture: keep the complete part of the existing
+    /// output, position the tape after it and append (tape drives only).
+    #[arg(long, conflicts_with_all = ["ignore_existing", "passes", "extra_pass", "describe", "operator", "reel_label"])]
+    resume: bool,
+
+    /// Read the tape this many times, rewinding before each pass, and merge the passes
+    /// record by record into the output. Each pass is kept as OUTPUT.passN.tap.
+    #[arg(long, default_value_t = 1, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+    passes: u32,
+
+    /// An image of the same tape read on another drive, merged in as one more pass.
+    #[arg(long, value_name = "IMAGE")]
+    extra_pass: Vec<String>,
+
+    /// Start the image with a SIMH class E record describing the capture:
+    /// operator, reel label, density, drive and date.
+    #[arg(long)]
+    describe: bool,
+
+    /// Operator name for the description record (implies --describe).
+    #[arg(long, value_name = "NAME")]
+    operator: Option<String>,
+
+    /// Text from the reel's physical label for the description record (implies --describe).
+    #[arg(long, value_name = "TEXT")]
+    reel_label: Option<String>,
+
+    /// Rewind the tape before reading (tape drives only).
+    #[arg(long)]
+    rewind: bool,
+
+    /// Space forward over this many tape files before reading (tape drives only).
+    #[arg(long, default_value_t = 0, value_name = "COUNT")]
+    skip_files: u32,
+
+    /// Re-read attempts for a block the drive cannot read, spacing back over it before each.
+    #[arg(long, default_value_t = 3, value_name = "COUNT")]
+    retries: u32,
+
+    /// Stop at the first unreadable block instead of writing it as a bad (class 8) record.
+    #[arg(long)]
+    abort_on_error: bool,
+
+    /// Initial read buffer size in bytes [default: the drive's fixed block size, else 122880].
+    #[arg(long, value_name = "BYTES")]
+    block_size: Option<usize>,
+
+    /// Largest block in bytes the read buffer may grow to when a block does not fit.
+    #[arg(long, default_value_t = 0xFF_FFFF, value_name = "BYTES")]
+    max_block: usize,
+
+    /// Reel length in feet, for an estimate of the tape left (e.g. 2400).
+    #[arg(long, value_name = "FEET")]
+    tape_length: Option<u32>,
+
+    /// Recording density in bpi for the estimate [default: from the drive].
+    #[arg(long, value_name = "BPI")]
+    density: Option<u32>,
+
+    /// Don't show the live progress line.
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Write a JSON Lines event stream to stdout; other output goes to stderr.
+    #[arg(long)]
+    json: bool,
+
+    /// Issue SCSI commands through SG_IO instead of reading via the st driver,
+    /// for exact sense data. Implied for /dev/sg* devices.
+    #[arg(long)]
+    sg: bool,
+}
+
+/// Where to position the tape before reading.
+struct Positioning {
+    rewind: bool,
+    files: u32,
+    blocks: u32,
+}
+
+/// How a tape file ended.
+enum FileEnd {
+    TapeMark,
+    EndOfData,
+    NotReady,
 }
 
 fn main() -> Result<()> {
+    let result = run();
+    if let Err(err) = &result {
+        events::emit(Event::Error {
+            message: format!("{err:#}"),
+        });
+    }
+    result
+}
+
+fn run() -> Result<()> {
     record_run_start();
     install_ctrlc_handler()?;
     let _run_summary_guard = RunSummaryGuard;
 
-    // Display header before parsing args so it shows even on errors
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
-    println!("========================");
-    println!("ACMS rtimage v{} / {}", VERSION, GIT_HASH);
+    // Parse first so --json can keep stdout clean, but display the header
+    // before reporting a bad command line
+    let parsed = Args::try_parse();
+    if parsed.as_ref().is_ok_and(|args| args.json) {
+        events::enable_json();
+    }
+    let started = Local::now();
+    let timestamp = started.format("%Y-%m-%d %H:%M:%S");
+    say!("========================");
+    say!("ACMS rtimage v{} / {}", VERSION, GIT_HASH);
 
-    let args = Args::parse();
+    let args = parsed.unwrap_or_else(|err| err.exit());
 
     let out_path = make_output_name(&args.output);
     let input_name = make_input_name(&args.input);
@@ -85,16 +207,23 @@ fn main() -> Result<()> {
         .display()
         .to_string();
 
-    println!("Timestamp: {}", timestamp);
-    println!(
+    say!("Timestamp: {}", timestamp);
+    say!(
         "SCSI Device: {}",
         input_name.as_deref().unwrap_or("- (stdin)")
     );
-    println!("Destination: {}", full_output_path);
-    println!("========================");
-    println!();
+    say!("Destination: {}", full_output_path);
+    say!("========================");
+    say!();
+    events::emit(Event::Started {
+        version: VERSION,
+        git_hash: GIT_HASH,
+        device: input_name.clone().unwrap_or_else(|| "-".into()),
+        output: full_output_path.clone(),
+        timestamp: started.to_rfc3339(),
+    });
 
-    let _kernel_log_guard = if !device_tokens.is_empty() {
+    let kernel_log = if !device_tokens.is_empty() {
         match KernelLogWatcher::start(device_tokens.clone()) {
             Ok(watcher) => {
                 eprintln!(
@@ -114,67 +243,425 @@ fn main() -> Result<()> {
     };
 
     // Check if output exists
-    if std::path::Path::new(&out_path).exists() && !args.ignore_existing {
+    let output_exists = Path::new(&out_path).exists();
+    if output_exists && !args.ignore_existing && !args.resume {
         bail!("Output file '{}' already exists.", out_path);
     }
 
-    // Open Output
-    let output_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&out_path)
-        .context("Failed to open output file")?;
+    if args.block_size == Some(0) || args.max_block == 0 {
+        bail!("--block-size and --max-block must be at least 1 byte");
+    }
+
+    let resume_point = match (args.resume, output_exists) {
+        (true, true) => {
+            let image = File::open(&out_path).context("Failed to open output file")?;
+            let point = resume::scan(BufReader::new(image))
+                .with_context(|| format!("Failed to read partial image '{}'", out_path))?;
+            if point.is_complete() {
+                bail!(
+                    "'{}' already ends with a double tape mark; nothing to resume.",
+                    out_path
+                );
+            }
+            say!(
+                "[resume] {} tape files and {} records of file {} already captured ({} blocks, {} bytes)",
+                point.files,
+                point.file_records,
+                point.files + 1,
+                point.blocks,
+                point.bytes
+            );
+            if point.discarded > 0 {
+                say!(
+                    "[resume] Discarding {} bytes of an incomplete trailing record",
+                    point.discarded
+                );
+            }
+            Some(point)
+        }
+        (true, false) => {
+            say!(
+                "[resume] '{}' does not exist, starting a new capture",
+                out_path
+            );
+            None
+        }
+        (false, _) => None,
+    };
+    // Every pass must start from the same place, so the first rewinds too.
+    let positioning = Positioning {
+        rewind: args.rewind || resume_point.is_some() || args.passes > 1,
+        files: args.skip_files + resume_point.as_ref().map_or(0, |point| point.files),
+        blocks: resume_point.as_ref().map_or(0, |point| point.file_records),
+    };
+
+    let mut source: Box<dyn TapeSource> = match input_name.as_deref() {
+        Some(path) => open_input(path, &args, &positioning)?,
+        None if positioning.rewind || positioning.files > 0 => {
+            bail!("--rewind, --skip-files, --resume and --passes need a tape drive");
+        }
+        None => Box::new(PlainSource(io::stdin())),
+    };
+    let (block_size, origin) = match (args.block_size, source.fixed_block_size()) {
+        (Some(size), _) => (size, "--block-size"),
+        (None, Some(size)) => (size, "fixed block size"),
+        (None, None) => (DEFAULT_BLOCK_SIZE, "default"),
+    };
+    // The drive never returns more than its block limit, so growing past it
+    // would only waste memory.
+    let (max_block, max_origin) = match source.block_limit() {
+        Some(limit) if limit < args.max_block => (limit, "drive block limit"),
+        _ => (args.max_block, "--max-block"),
+    };
+    let mut options = ReadOptions {
+        retries: args.retries,
+        record_bad_blocks: !args.abort_on_error,
+        block_size: block_size.min(max_block),
+        max_block,
+    };
+    say!(
+        "Read Buffer: {} bytes ({}), growing to at most {} bytes ({})",
+        options.block_size,
+        origin,
+        options.max_block,
+        max_origin
+    );
+    let drive = source.drive_info();
+    if let Some(identity) = drive.identity() {
+        say!("Drive: {}", identity);
+    }
+    if let Some(density) = drive.density_name() {
+        say!("Density: {}", density);
+    }
+    let description =
+        (args.describe || args.operator.is_some() || args.reel_label.is_some()).then(|| {
+            TapeDescription {
+                operator: args.operator.clone(),
+                label: args.reel_label.clone(),
+                density: drive.density_name(),
+                drive: drive.identity(),
+                date: Some(started.to_rfc3339()),
+                other: Vec::new(),
+            }
+        });
+    if args.passes > 1 && !source.is_tape_drive() {
+        bail!("--passes needs a tape drive that can rewind");
+    }
+
+    let resumed = resume_point.is_some();
+    let (totals, passes) = if args.passes > 1 || !args.extra_pass.is_empty() {
+        run_passes(
+            source,
+            &args,
+            &out_path,
+            &mut options,
+            input_name.is_none(),
+            description.as_ref(),
+        )?
+    } else {
+        let totals = capture_image(
+            source,
+            &args,
+            &out_path,
+            &mut options,
+            input_name.is_none(),
+            resume_point,
+            description.as_ref(),
+        )?;
+        (totals, Vec::new())
+    };
+
+    let image = File::open(&out_path).context("Failed to reopen output file")?;
+    let summary = manifest::summarize(BufReader::new(image))
+        .with_context(|| format!("Failed to read back '{}'", out_path))?;
+    let manifest = Manifest {
+        tool: "rtimage",
+        version: VERSION,
+        git_hash: GIT_HASH,
+        device: input_name.clone().unwrap_or_else(|| "-".into()),
+        image: std::fs::canonicalize(&out_path)
+            .map_or(full_output_path, |path| path.display().to_string()),
+        drive,
+        started: started.to_rfc3339(),
+        finished: Local::now().to_rfc3339(),
+        resumed,
+        passes,
+        retries: totals.retries,
+        reattempts: totals.reattempts,
+        recovered_errors: totals.recovered_errors,
+        bad_blocks: totals.bad_blocks,
+        kernel_log: kernel_log
+            .as_ref()
+            .map(KernelLogWatcher::lines)
+            .unwrap_or_default(),
+        summary,
+    };
+    let manifest_path = sibling_path(&out_path, ".manifest.json");
+    let mut manifest_file =
+        BufWriter::new(File::create(&manifest_path).context("Failed to create manifest")?);
+    manifest.write_to(&mut manifest_file)?;
+    manifest_file.flush()?;
+    say!("SHA-256: {}", manifest.summary.sha256);
+    say!("Manifest: {}", manifest_path);
+    events::emit(Event::Finished {
+        records: manifest.summary.records,
+        bytes: manifest.summary.bytes,
+        recovered_errors: manifest.recovered_errors,
+        bad_blocks: manifest.bad_blocks,
+        sha256: manifest.summary.sha256.clone(),
+        manifest: manifest_path.clone(),
+    });
+
+    Ok(())
+}
+
+/// Captures the tape straight into the output image, appending to it when
+/// resuming. A new image starts with the description record, if any.
+fn capture_image(
+    source: Box<dyn TapeSource>,
+    args: &Args,
+    out_path: &str,
+    options: &mut ReadOptions,
+    stdin: bool,
+    resume_point: Option<ResumePoint>,
+    description: Option<&TapeDescription>,
+) -> Result<Totals> {
+    let output_file = match &resume_point {
+        Some(point) => {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .open(out_path)
+                .context("Failed to open output file")?;
+            file.set_len(point.valid_length)
+                .context("Failed to truncate output file")?;
+            file.seek(SeekFrom::End(0))?;
+            file
+        }
+        None => OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(out_path)
+            .context("Failed to open output file")?,
+    };
 
     let mut tape_writer = SimhTapeWriter::new(BufWriter::new(output_file));
+    if let (Some(description), None) = (description, &resume_point) {
+        tape_writer.write_tape_description(&description.to_bytes())?;
+    }
+    let resumed = resume_point.unwrap_or_default();
+    let (_, totals) = capture(source, &mut tape_writer, options, args, stdin, &resumed)?;
+    tape_writer.into_inner().flush()?;
+    totals.print("Session Complete");
+    Ok(totals)
+}
+
+/// What a capture read.
+#[derive(Default)]
+struct Totals {
+    records: u32,
+    blocks: usize,
+    bytes: usize,
+    retries: usize,
+    reattempts: usize,
+    recovered_errors: usize,
+    bad_blocks: usize,
+}
+
+impl Totals {
+    /// Adds the error counts of another pass.
+    fn add_errors(&mut self, other: &Totals) {
+        self.retries += other.retries;
+        self.reattempts += other.reattempts;
+        self.recovered_errors += other.recovered_errors;
+        self.bad_blocks += other.bad_blocks;
+    }
 
-    let mut count = 0;
-    let mut bytes = 0;
+    fn print(&self, title: &str) {
+        say!();
+        say!("========================");
+        say!("{}", title);
+        say!("========================");
+        say!("Total Records: {}", self.records);
+        say!("Total Blocks:  {}", self.blocks);
+        say!("Total Bytes:   {}", self.bytes);
+        say!(
+            "Recovered Errors: {} ({} re-read, {} bad blocks)",
+            self.recovered_errors,
+            self.recovered_errors - self.bad_blocks,
+            self.bad_blocks
+        );
+        say!("========================");
+    }
+}
+
+/// Reads tape files from `source` into `tape_writer` until the end of the
+/// tape, then hands the source back with what was read. A resumed capture
+/// carries on from the counts in `resumed`.
+fn capture(
+    mut source: Box<dyn TapeSource>,
+    tape_writer: &mut SimhTapeWriter<BufWriter<File>>,
+    options: &mut ReadOptions,
+    args: &Args,
+    stdin: bool,
+    resumed: &ResumePoint,
+) -> Result<(Box<dyn TapeSource>, Totals)> {
+    let tape_drive = source.is_tape_drive();
+    let reel = args.tape_length.and_then(|length_feet| {
+        let bpi = args.density.or_else(|| source.drive_info().bpi())?;
+        Some(Reel { length_feet, bpi })
+    });
+    if args.tape_length.is_some() && reel.is_none() {
+        eprintln!("[progress] Density unknown; pass --density for a tape length estimate");
+    }
+    let mut progress = Progress::new(!args.no_progress && !args.json, reel).with_position(
+        resumed.files,
+        resumed.blocks,
+        resumed.bytes,
+    );
+    let mut source = Some(source);
+    let mut count = resumed.blocks as usize;
+    let mut bytes = resumed.bytes as usize;
     let mut reattempts = 0;
     let mut consecutive_empty_files = 0; // Track consecutive tape marks with no data (double TM = EOT)
-    let mut tape_record_count = 0;
-    let mut prev_bytes: usize = 0;
+    let mut trailing_tape_marks = resumed.trailing_tape_marks;
+    let mut tape_record_count = resumed.data_files;
+    let mut prev_bytes = (resumed.bytes - resumed.file_bytes) as usize;
+    // Blocks of the file in progress that a resumed capture already holds.
+    let mut resumed_file_blocks = resumed.file_records;
+    let mut retries = 0;
+    let mut total_reattempts = 0;
+    let mut recovered_errors = 0;
+    let mut bad_blocks = 0;
 
     // Loop for reading tape files (separated by Tape Marks)
     loop {
-        // Open Input (Re-open for each file on tape)
-        let input: Box<dyn Read + Send> = if let Some(ref path) = input_name {
-            Box::new(File::open(path).context("Failed to open input device")?)
-        } else {
-            // Stdin can't be re-opened.
-            if count > 0 {
-                break; // We already read stdin once.
-            }
-            Box::new(io::stdin())
-        };
+        // Stdin can't be re-read.
+        if stdin && count > 0 {
+            break;
+        }
 
         let (sender, receiver) = bounded(2);
-        let reader_handle = start_reader_thread(input, sender);
+        let reader_handle = start_reader_thread(
+            source
+                .take()
+                .context("Tape source lost after a read error")?,
+            sender,
+            *options,
+        );
 
-        let mut file_block_count = 0;
-        let mut tape_mark_seen = false;
+        let mut file_block_count = std::mem::take(&mut resumed_file_blocks);
+        let mut file_end = None;
+        let file = tape_record_count + 1;
+        progress.start_file(file, file_block_count);
+        events::emit(Event::FileStarted { file });
 
         for event in receiver {
+            if !matches!(event, TapeEvent::Data(_)) {
+                progress.clear();
+            }
             match event {
                 TapeEvent::Data(data) => {
                     let record_size = data.len();
+                    progress.block(record_size);
                     bytes += record_size;
                     tape_writer.write_record(&data)?;
                     file_block_count += 1;
+                    events::emit(Event::RecordRead {
+                        file,
+                        block: file_block_count,
+                        length: record_size,
+                    });
                     count += 1;
                     // Reset reattempts on successful read
                     reattempts = 0;
                     consecutive_empty_files = 0;
+                    trailing_tape_marks = 0;
+                }
+                TapeEvent::Retry { attempt, reason } => {
+                    retries += 1;
+                    progress.retry();
+                    events::emit(Event::Retry {
+                        file,
+                        block: file_block_count + 1,
+                        attempt,
+                        reason: reason.clone(),
+                    });
+                    eprintln!(
+                        "[retry {}/{}] Block {}: {}",
+                        attempt,
+                        args.retries,
+                        count + 1,
+                        reason
+                    );
+                }
+                TapeEvent::Recovered { attempts } => {
+                    recovered_errors += 1;
+                    events::emit(Event::Recovered {
+                        file,
+                        block: file_block_count + 1,
+                        attempts,
+                    });
+                    eprintln!(
+                        "[recovered] Block {} read after {} retries",
+                        count + 1,
+                        attempts
+                    );
+                }
+                TapeEvent::BadBlock { data, reason } => {
+                    eprintln!(
+                        "[bad block] Block {}: {}; {} bytes kept as a bad record",
+                        count + 1,
+                        reason,
+                        data.len()
+                    );
+                    progress.block(data.len());
+                    bytes += data.len();
+                    tape_writer.write_bad_record(&data)?;
+                    file_block_count += 1;
+                    events::emit(Event::BadBlock {
+                        file,
+                        block: file_block_count,
+                        length: data.len(),
+                        reason,
+                    });
+                    count += 1;
+                    recovered_errors += 1;
+                    bad_blocks += 1;
+                    consecutive_empty_files = 0;
+                    trailing_tape_marks = 0;
+                }
+                TapeEvent::Resized { size } => {
+                    eprintln!(
+                        "[buffer] Block {} did not fit, re-reading with a {} byte buffer",
+                        count + 1,
+                        size
+                    );
+                    options.block_size = size;
                 }
                 TapeEvent::TapeMark => {
-                    tape_mark_seen = true;
+                    file_end = Some(FileEnd::TapeMark);
                     break; // End of this tape file
                 }
+                TapeEvent::EndOfData => {
+                    file_end = Some(FileEnd::EndOfData);
+                    break;
+                }
+                TapeEvent::NotReady => {
+                    file_end = Some(FileEnd::NotReady);
+                    break;
+                }
                 TapeEvent::Error(e) => {
-                    // EIO (os error 5) often occurs at end-of-tape after double tape mark
-                    // If we've already read data, treat I/O errors as end-of-tape
-                    if count > 0 && (e.contains("os error 5") || e.contains("Input/output error")) {
+                    // Without drive status, EIO (os error 5) after data is taken to be
+                    // the end of tape following a double tape mark.
+                    if !tape_drive
+                        && count > 0
+                        && (e.contains("os error 5") || e.contains("Input/output error"))
+                    {
                         eprintln!("[info] I/O error at end of tape (normal): {}", e);
+                        events::emit(Event::Info {
+                            message: format!("I/O error at end of tape (normal): {e}"),
+                        });
                         break;
                     }
                     eprintln!("Error reading tape: {}", e);
@@ -183,72 +670,313 @@ fn main() -> Result<()> {
             }
         }
 
-        // Wait for reader to finish
-        let _ = reader_handle.join();
+        progress.clear();
 
-        if !tape_mark_seen {
+        // Wait for reader to finish and take the source back for the next file
+        source = Some(
+            reader_handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("Tape reader thread panicked"))?,
+        );
+
+        let file_end = match file_end {
             // Reader exited without TM? (Error or Pipe closed)
-            break;
+            None => break,
+            Some(file_end) => file_end,
+        };
+
+        if file_block_count > 0 {
+            // We got data - write the tape mark that ends this file
+            tape_writer.write_tape_mark()?;
+            progress.tape_mark();
+            events::emit(Event::TapeMark { file });
+            trailing_tape_marks += 1;
+            tape_record_count += 1;
+            let record_bytes = bytes - prev_bytes;
+            events::emit(Event::FileFinished {
+                file,
+                blocks: file_block_count,
+                bytes: record_bytes,
+            });
+            say!(
+                "Record {}: {} blocks, {} bytes ({})",
+                tape_record_count,
+                file_block_count,
+                record_bytes,
+                progress.file_summary()
+            );
+            prev_bytes = bytes;
+            // Reset counters
+            consecutive_empty_files = 0;
         }
 
-        if file_block_count == 0 {
-            // We read 0 blocks and hit a TM - this could be:
-            // 1. Part of a double tape mark (EOT)
-            // 2. Drive not ready (retry situation)
-            
-            // First check if we should retry (drive not ready scenario)
-            if reattempts < args.max_reattempts {
+        match file_end {
+            FileEnd::EndOfData => {
+                // The drive reported the end of recorded data; close the image
+                // with a double tape mark.
+                while trailing_tape_marks < 2 {
+                    tape_writer.write_tape_mark()?;
+                    trailing_tape_marks += 1;
+                }
+                say!("[End of Data]");
+                events::emit(Event::EndOfData);
+                break;
+            }
+            FileEnd::NotReady if file_block_count > 0 => {
+                bail!("Drive went offline after {} blocks", count);
+            }
+            FileEnd::NotReady => {
+                if reattempts >= args.max_reattempts {
+                    bail!("Drive not ready after {} attempts", args.max_reattempts);
+                }
                 eprintln!(
-                    "\n[Attempt {}/{}] Not receiving any data from drive, retrying...",
+                    "\n[Attempt {}/{}] Drive not ready, retrying...",
                     reattempts + 1,
                     args.max_reattempts
                 );
+                events::emit(Event::Reattempt {
+                    attempt: reattempts + 1,
+                    limit: args.max_reattempts,
+                    reason: "drive not ready".into(),
+                });
                 thread::sleep(std::time::Duration::from_millis(500));
                 reattempts += 1;
-                // Don't increment consecutive_empty_files or write tape mark during retries
+                total_reattempts += 1;
                 continue;
             }
-            
-            // Exhausted retries, now treat as actual tape mark
-            consecutive_empty_files += 1;
-            
-            if consecutive_empty_files >= 2 {
-                // Second consecutive empty file = double tape mark (EOT).
-                // Don't write another tape mark, we already wrote the first one.
-                break;
+            FileEnd::TapeMark if file_block_count > 0 => {}
+            FileEnd::TapeMark => {
+                // We read 0 blocks and hit a TM - this could be:
+                // 1. Part of a double tape mark (EOT)
+                // 2. Drive not ready (retry situation), which only plain reads
+                //    can't tell apart; st drives report readiness separately
+
+                // First check if we should retry (drive not ready scenario)
+                if !tape_drive && reattempts < args.max_reattempts {
+                    eprintln!(
+                        "\n[Attempt {}/{}] Not receiving any data from drive, retrying...",
+                        reattempts + 1,
+                        args.max_reattempts
+                    );
+                    events::emit(Event::Reattempt {
+                        attempt: reattempts + 1,
+                        limit: args.max_reattempts,
+                        reason: "no data from drive".into(),
+                    });
+                    thread::sleep(std::time::Duration::from_millis(500));
+                    reattempts += 1;
+                    total_reattempts += 1;
+                    // Don't increment consecutive_empty_files or write tape mark during retries
+                    continue;
+                }
+
+                // Exhausted retries, now treat as actual tape mark
+                consecutive_empty_files += 1;
+
+                if consecutive_empty_files >= 2 {
+                    // Second consecutive empty file = double tape mark (EOT).
+                    // Don't write another tape mark, we already wrote the first one.
+                    break;
+                }
+
+                // First empty file after exhausting retries - write the tape mark
+                tape_writer.write_tape_mark()?;
+                progress.tape_mark();
+                trailing_tape_marks += 1;
+                say!("[Tape Mark]");
+                events::emit(Event::TapeMark { file });
+
+                // Reset reattempts for next potential retry cycle
+                reattempts = 0;
             }
-            
-            // First empty file after exhausting retries - write the tape mark
-            tape_writer.write_tape_mark()?;
-            println!("[Tape Mark]");
-            
-            // Reset reattempts for next potential retry cycle
-            reattempts = 0;
-        } else {
-            // We got data - write the tape mark that ends this file
-            tape_writer.write_tape_mark()?;
-            tape_record_count += 1;
-            let record_bytes = bytes - prev_bytes;
-            println!("Record {}: {} blocks, {} bytes", 
-                tape_record_count, 
-                file_block_count, 
-                record_bytes);
-            prev_bytes = bytes;
-            // Reset counters
-            consecutive_empty_files = 0;
         }
     }
 
-    println!();
-    println!("========================");
-    println!("Session Complete");
-    println!("========================");
-    println!("Total Records: {}", tape_record_count);
-    println!("Total Blocks:  {}", count);
-    println!("Total Bytes:   {}", bytes);
-    println!("========================");
+    let totals = Totals {
+        records: tape_record_count,
+        blocks: count,
+        bytes,
+        retries,
+        reattempts: total_reattempts,
+        recovered_errors,
+        bad_blocks,
+    };
+    let source = source.context("Tape source lost after a read error")?;
+    Ok((source, totals))
+}
 
-    Ok(())
+/// Reads the tape once per pass, rewinding in between, then merges those
+/// passes and any extra images into the output record by record.
+fn run_passes(
+    mut source: Box<dyn TapeSource>,
+    args: &Args,
+    out_path: &str,
+    options: &mut ReadOptions,
+    stdin: bool,
+    description: Option<&TapeDescription>,
+) -> Result<(Totals, Vec<String>)> {
+    let mut images = Vec::new();
+    let mut all = Totals::default();
+    for pass in 1..=args.passes {
+        let path = sibling_path(out_path, &format!(".pass{}.tap", pass));
+        if Path::new(&path).exists() && !args.ignore_existing {
+            bail!("Pass image '{}' already exists.", path);
+        }
+        if pass > 1 {
+            say!("Rewinding for pass {}...", pass);
+            source.rewind().context("Failed to rewind tape")?;
+            if args.skip_files > 0 {
+                source
+                    .space_files(args.skip_files as i32)
+                    .context("Failed to space forward over tape files")?;
+            }
+        }
+        say!("Pass {}/{}: {}", pass, args.passes, path);
+        events::emit(Event::PassStarted {
+            pass,
+            passes: args.passes,
+            image: path.clone(),
+        });
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .context("Failed to open pass image")?;
+        let mut writer = SimhTapeWriter::new(BufWriter::new(file));
+        let (returned, totals) = capture(
+            source,
+            &mut writer,
+            options,
+            args,
+            stdin,
+            &ResumePoint::default(),
+        )?;
+        source = returned;
+        writer.into_inner().flush()?;
+        totals.print(&format!("Pass {} Complete", pass));
+        all.add_errors(&totals);
+        images.push(path);
+    }
+    images.extend(args.extra_pass.iter().cloned());
+
+    let passes = images
+        .iter()
+        .map(|path| {
+            File::open(path)
+                .map(BufReader::new)
+                .with_context(|| format!("Failed to open pass image '{}'", path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let output_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(out_path)
+        .context("Failed to open output file")?;
+    let mut writer = SimhTapeWriter::new(BufWriter::new(output_file));
+    if let Some(description) = description {
+        writer.write_tape_description(&description.to_bytes())?;
+    }
+    let report = consensus::merge(passes, &mut writer).context("Failed to merge passes")?;
+    writer.into_inner().flush()?;
+
+    let report_path = sibling_path(out_path, ".divergence.txt");
+    let mut report_file =
+        BufWriter::new(File::create(&report_path).context("Failed to create divergence report")?);
+    report.write_to(&mut report_file, &images)?;
+    report_file.flush()?;
+
+    say!();
+    say!("Consensus: {}", report.summary());
+    say!(
+        "Divergence Report: {} ({} entries)",
+        report_path,
+        report.divergences.len()
+    );
+    Ok((all, images))
+}
+
+/// Opens the input device, going through SG_IO for SCSI generic devices and
+/// through the st driver for other tape drives, so tape marks and end of data
+/// come from the drive rather than from 0-byte reads.
+fn open_input(path: &str, args: &Args, positioning: &Positioning) -> Result<Box<dyn TapeSource>> {
+    #[cfg(target_os = "linux")]
+    if args.sg || is_sg_device(path) {
+        let device = sg::SgDevice::open(Path::new(path))
+            .context("Failed to open input device")?
+            .with_context(|| format!("{path} does not accept SCSI generic commands"))?;
+        let mut drive = sg::SgDrive::new(device);
+        if positioning.rewind {
+            say!("Rewinding tape...");
+            drive.rewind().context("Failed to rewind tape")?;
+        }
+        if positioning.files > 0 {
+            say!("Skipping {} tape files...", positioning.files);
+            drive
+                .space_files(positioning.files as i32)
+                .context("Failed to space forward over tape files")?;
+        }
+        if positioning.blocks > 0 {
+            say!("Skipping {} blocks...", positioning.blocks);
+            drive
+                .space_blocks(positioning.blocks as i32)
+                .context("Failed to space forward over blocks")?;
+        }
+        match drive.test_unit_ready().context("Failed to query drive")? {
+            None => say!("Drive Status: READY"),
+            Some(sense) => say!("Drive Status: {}", sense),
+        }
+        if let Ok(position) = drive.position() {
+            say!(
+                "Block Position: {}{}",
+                position.block,
+                if position.bop { " (BOP)" } else { "" }
+            );
+        }
+        say!();
+        return Ok(Box::new(drive));
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(device) =
+        st::StDevice::open(Path::new(path)).context("Failed to open input device")?
+    {
+        let mut drive = st::StDrive::new(device);
+        if positioning.rewind {
+            say!("Rewinding tape...");
+            drive.rewind().context("Failed to rewind tape")?;
+        }
+        if positioning.files > 0 {
+            say!("Skipping {} tape files...", positioning.files);
+            drive
+                .space_files(positioning.files as i32)
+                .context("Failed to space forward over tape files")?;
+        }
+        if positioning.blocks > 0 {
+            say!("Skipping {} blocks...", positioning.blocks);
+            drive
+                .space_blocks(positioning.blocks as i32)
+                .context("Failed to space forward over blocks")?;
+        }
+        let status = drive.status().context("Failed to read drive status")?;
+        say!("Drive Status: {}", status.describe());
+        if let Ok(block) = drive.position() {
+            say!("Block Position: {}", block);
+        }
+        say!();
+        return Ok(Box::new(drive));
+    }
+
+    if args.sg || is_sg_device(path) {
+        bail!("SCSI generic pass-through needs Linux");
+    }
+    if positioning.rewind || positioning.files > 0 {
+        bail!("--rewind, --skip-files and --resume need a tape drive on Linux");
+    }
+    let file = File::open(path).context("Failed to open input device")?;
+    Ok(Box::new(PlainSource(file)))
 }
 
 fn record_run_start() {
@@ -259,6 +987,11 @@ fn record_run_start() {
 fn install_ctrlc_handler() -> Result<()> {
     ctrlc::set_handler(|| {
         print_run_summary();
+        // Consumers of --json would otherwise see the stream stop without a
+        // final event.
+        events::emit(Event::Error {
+            message: "interrupted".to_string(),
+        });
         std::process::exit(130);
     })
     .context("Failed to install Ctrl+C handler")
@@ -282,9 +1015,9 @@ fn print_run_summary() {
         .map(|start| start.elapsed().as_secs())
         .unwrap_or(0);
 
-    println!("========================");
-    println!("Timestamp: {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
-    println!(
+    say!("========================");
+    say!("Timestamp: {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+    say!(
         "Run ended after {} seconds.",
         format_seconds_with_commas(elapsed_secs)
     );
@@ -0,0 +1,192 @@
+use crate::reader::DriveInfo;
+use rtsimh::{SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader, TAPE_DESCRIPTION_CLASS};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+const BAD_RECORD_CLASS: u8 = 0x8;
+
+/// One tape file of the finished image. Files are numbered from 1.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct FileStats {
+    pub file: u32,
+    pub records: u64,
+    pub bytes: u64,
+    pub bad_records: u64,
+}
+
+/// What the finished image holds, read back from disk.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ImageSummary {
+    pub image_length: u64,
+    pub sha256: String,
+    pub records: u64,
+    pub bytes: u64,
+    pub files: Vec<FileStats>,
+}
+
+/// Provenance written next to the image, for archive ingestion.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    /// Input device, or "-" for stdin.
+    pub device: String,
+    pub image: String,
+    pub drive: DriveInfo,
+    pub started: String,
+    pub finished: String,
+    pub resumed: bool,
+    /// Pass images merged into the output; empty for a single read.
+    pub passes: Vec<String>,
+    /// Block re-reads after a read error.
+    pub retries: usize,
+    /// Re-reads after the drive was not ready or returned nothing.
+    pub reattempts: usize,
+    pub recovered_errors: usize,
+    pub bad_blocks: usize,
+    pub kernel_log: Vec<String>,
+    #[serde(flatten)]
+    pub summary: ImageSummary,
+}
+
+impl Manifest {
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)
+    }
+}
+
+/// Hashes the image and counts the records in each of its tape files.
+/// Empty files after the last one with data (the end-of-tape marks) are
+/// left out.
+pub fn summarize<R: Read + Seek>(mut image: R) -> SimhResult<ImageSummary> {
+    let mut hasher = Sha256::new();
+    let image_length = io::copy(&mut image, &mut hasher)?;
+    image.seek(SeekFrom::Start(0))?;
+
+    let mut reader = SimhTapeReader::new(image);
+    let mut files = Vec::new();
+    let mut file = FileStats {
+        file: 1,
+        ..FileStats::default()
+    };
+    loop {
+        match reader.skip_block()? {
+            SimhTapeBlock::Record(record) if record.header.class == TAPE_DESCRIPTION_CLASS => {}
+            SimhTapeBlock::Record(record) => {
+                file.records += 1;
+                file.bytes += u64::from(record.header.length);
+                if record.header.class == BAD_RECORD_CLASS {
+                    file.bad_records += 1;
+                }
+            }
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                ..
+            } => {
+                let next = FileStats {
+                    file: file.file + 1,
+                    ..FileStats::default()
+                };
+                files.push(std::mem::replace(&mut file, next));
+            }
+            SimhTapeBlock::TapeMark { .. } => {}
+            _ => break,
+        }
+    }
+    files.push(file);
+    while files.last().is_some_and(|file| file.records == 0) {
+        files.pop();
+    }
+
+    Ok(ImageSummary {
+        image_length,
+        sha256: format!("{:x}", hasher.finalize()),
+        records: files.iter().map(|file| file.records).sum(),
+        bytes: files.iter().map(|file| file.bytes).sum(),
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtsimh::SimhTapeWriter;
+    use std::io::Cursor;
+
+    #[test]
+    fn counts_tape_files_and_hashes_the_image() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 80]).unwrap();
+        writer.write_bad_record(&[2; 7]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_record(&[3; 10]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        let bytes = writer.into_inner();
+
+        let summary = summarize(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(summary.image_length, bytes.len() as u64);
+        assert_eq!(summary.sha256, format!("{:x}", Sha256::digest(&bytes)));
+        assert_eq!((summary.records, summary.bytes), (3, 97));
+        assert_eq!(
+            summary.files,
+            vec![
+                FileStats {
+                    file: 1,
+                    records: 2,
+                    bytes: 87,
+                    bad_records: 1
+                },
+                FileStats {
+                    file: 2,
+                    ..FileStats::default()
+                },
+                FileStats {
+                    file: 3,
+                    records: 1,
+                    bytes: 10,
+                    bad_records: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_the_summary_alongside_the_capture_details() {
+        let summary = summarize(Cursor::new(Vec::new())).unwrap();
+        assert_eq!(
+            summary.sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let manifest = Manifest {
+            tool: "rtimage",
+            version: "1.0",
+            git_hash: "abc1234",
+            device: "/dev/nst0".into(),
+            image: "tape.tap".into(),
+            drive: DriveInfo::default(),
+            started: "2026-01-01T00:00:00+00:00".into(),
+            finished: "2026-01-01T01:00:00+00:00".into(),
+            resumed: false,
+            passes: Vec::new(),
+            retries: 2,
+            reattempts: 0,
+            recovered_errors: 1,
+            bad_blocks: 0,
+            kernel_log: vec!["st 0:0:0:0: [st0] Sense Key : Medium Error".into()],
+            summary,
+        };
+        let mut out = Vec::new();
+        manifest.write_to(&mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["device"], "/dev/nst0");
+        assert_eq!(json["drive"]["density"], serde_json::Value::Null);
+        assert_eq!(json["sha256"], manifest.summary.sha256);
+        assert_eq!(json["files"], serde_json::json!([]));
+        assert_eq!(json["kernel_log"][0], manifest.kernel_log[0]);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::sg::{self, CommandResult, SgBackend};
+use crate::st::{MtGet, MtOp, StBackend};
+use std::io;
+use std::os::raw::c_long;
+
+/// What a simulated tape holds at each block position.
+#[derive(Debug, Clone)]
+pub enum MockObject {
+    Record(Vec<u8>),
+    TapeMark,
+    /// A block that fails to read with this errno.
+    Error(i32),
+    /// A block that fails to read this many more times before reading cleanly.
+    Unreliable(u32, Vec<u8>),
+}
+
+/// A tape drive simulated in memory, answering both as an st device and as
+/// an sg device closely enough to exercise the code above them.
+pub struct MockTape {
+    objects: Vec<MockObject>,
+    position: usize,
+    eod_reads: u32,
+    block_size: u32,
+    pub online: bool,
+    pub write_protected: bool,
+    /// Past the early-warning marker near the end of the reel.
+    pub past_early_warning: bool,
+    pub density: u8,
+    /// Every MTIOCTOP issued, in order.
+    pub operations: Vec<(MtOp, i32)>,
+}
+
+impl MockTape {
+    pub fn new(objects: Vec<MockObject>) -> Self {
+        Self {
+            objects,
+            position: 0,
+            eod_reads: 0,
+            block_size: 0,
+            online: true,
+            write_protected: false,
+            past_early_warning: false,
+            density: 0,
+            operations: Vec::new(),
+        }
+    }
+
+    fn is_tape_mark(&self, index: usize) -> bool {
+        matches!(self.objects.get(index), Some(MockObject::TapeMark))
+    }
+
+    fn io_error() -> io::Error {
+        io::Error::from_raw_os_error(libc::EIO)
+    }
+
+    /// The object under the head as a read sees it, advancing past it.
+    fn next_object(&mut self) -> Option<MockObject> {
+        let object = self.objects.get_mut(self.position)?;
+        self.position += 1;
+        Some(match object {
+            MockObject::Unreliable(0, data) => MockObject::Record(data.clone()),
+            MockObject::Unreliable(failures, _) => {
+                *failures -= 1;
+                MockObject::Error(libc::EIO)
+            }
+            other => other.clone(),
+        })
+    }
+
+    /// Moves past the next tape mark; false if the end of data comes first.
+    fn forward_file(&mut self) -> bool {
+        loop {
+            if self.position >= self.objects.len() {
+                return false;
+            }
+            self.position += 1;
+            if self.is_tape_mark(self.position - 1) {
+                return true;
+            }
+        }
+    }
+
+    /// Moves back to just before the previous tape mark; false at BOT.
+    fn backward_file(&mut self) -> bool {
+        loop {
+            if self.position == 0 {
+                return false;
+            }
+            self.position -= 1;
+            if self.is_tape_mark(self.position) {
+                return true;
+            }
+        }
+    }
+}
+
+/// Fills `sense` with fixed format sense data and reports CHECK CONDITION.
+fn check_condition(
+    sense: &mut [u8],
+    key: u8,
+    (asc, ascq): (u8, u8),
+    flags: u8,
+    information: Option<i32>,
+    residual: usize,
+) -> io::Result<CommandResult> {
+    let mut fixed = [0u8; 18];
+    fixed[0] = 0x70 | if information.is_some() { 0x80 } else { 0 };
+    fixed[2] = flags | key;
+    fixed[3..7].copy_from_slice(&information.unwrap_or(0).to_be_bytes());
+    fixed[7] = 10;
+    fixed[12] = asc;
+    fixed[13] = ascq;
+    let length = fixed.len().min(sense.len());
+    sense[..length].copy_from_slice(&fixed[..length]);
+    Ok(CommandResult {
+        status: 0x02,
+        residual,
+        sense_length: length,
+    })
+}
+
+const ILLEGAL_REQUEST: u8 = 0x5;
+const FILEMARK: u8 = 0x80;
+const EOM: u8 = 0x40;
+const ILI: u8 = 0x20;
+const FILEMARK_DETECTED: (u8, u8) = (0x00, 0x01);
+const BEGINNING_OF_MEDIUM: (u8, u8) = (0x00, 0x04);
+const END_OF_DATA: (u8, u8) = (0x00, 0x05);
+
+impl SgBackend for MockTape {
+    fn execute(
+        &mut self,
+        cdb: &[u8],
+        data: &mut [u8],
+        sense: &mut [u8],
+    ) -> io::Result<CommandResult> {
+        let good = |residual| {
+            Ok(CommandResult {
+                status: 0x00,
+                residual,
+                sense_length: 0,
+            })
+        };
+        let requested = data.len();
+        if !self.online && ![sg::OP_READ_POSITION, sg::OP_INQUIRY].contains(&cdb[0]) {
+            return check_condition(sense, sg::KEY_NOT_READY, (0x3A, 0x00), 0, None, requested);
+        }
+        // 24-bit two's complement count in bytes 2-4 of six-byte CDBs.
+        let count = i32::from_be_bytes([cdb[2], cdb[3], cdb[4], 0]) >> 8;
+
+        match cdb[0] {
+            sg::OP_TEST_UNIT_READY => good(0),
+            sg::OP_REWIND => {
+                self.position = 0;
+                good(0)
+            }
+            sg::OP_READ_BLOCK_LIMITS => {
+                let mut response = [0u8; sg::BLOCK_LIMITS_LENGTH];
+                response[1..4].copy_from_slice(&[0xFF, 0xFF, 0xFF]);
+                response[5] = 1;
+                let length = response.len().min(requested);
+                data[..length].copy_from_slice(&response[..length]);
+                good(requested - length)
+            }
+            sg::OP_INQUIRY => {
+                let mut response = [b' '; sg::INQUIRY_LENGTH];
+                response[..8].copy_from_slice(&[0x01, 0, 0x05, 0x02, 31, 0, 0, 0]);
+                response[8..12].copy_from_slice(b"MOCK");
+                response[16..28].copy_from_slice(b"VIRTUAL TAPE");
+                response[32..35].copy_from_slice(b"1.0");
+                let length = response.len().min(requested);
+                data[..length].copy_from_slice(&response[..length]);
+                good(requested - length)
+            }
+            sg::OP_MODE_SENSE_6 => {
+                let mut response = [0u8; sg::MODE_SENSE_LENGTH];
+                response[0] = sg::MODE_SENSE_LENGTH as u8 - 1;
+                response[3] = 8;
+                response[4] = self.density;
+                let length = response.len().min(requested);
+                data[..length].copy_from_slice(&response[..length]);
+                good(requested - length)
+            }
+            sg::OP_READ_POSITION => {
+                let block = (self.position as u32).to_be_bytes();
+                let mut response = [0u8; sg::POSITION_LENGTH];
+                response[0] = if self.position == 0 { 0x80 } else { 0 };
+                response[4..8].copy_from_slice(&block);
+                response[8..12].copy_from_slice(&block);
+                let length = response.len().min(requested);
+                data[..length].copy_from_slice(&response[..length]);
+                good(requested - length)
+            }
+            sg::OP_READ_6 => {
+                let Some(object) = self.next_object() else {
+                    return check_condition(
+                        sense,
+                        sg::KEY_BLANK_CHECK,
+                        END_OF_DATA,
+                        0,
+                        Some(requested as i32),
+                        requested,
+                    );
+                };
+                match object {
+                    MockObject::Record(record) if record.len() == requested => {
+                        data.copy_from_slice(&record);
+                        good(0)
+                    }
+                    MockObject::Record(record) => {
+                        // Drives transfer what fits and report the difference.
+                        let length = record.len().min(requested);
+                        data[..length].copy_from_slice(&record[..length]);
+                        let difference = requested as i32 - record.len() as i32;
+                        check_condition(
+                            sense,
+                            sg::KEY_NO_SENSE,
+                            (0x00, 0x00),
+                            ILI,
+                            Some(difference),
+                            requested - length,
+                        )
+                    }
+                    MockObject::TapeMark => check_condition(
+                        sense,
+                        sg::KEY_NO_SENSE,
+                        FILEMARK_DETECTED,
+                        FILEMARK,
+                        Some(requested as i32),
+                        requested,
+                    ),
+                    MockObject::Error(_) => check_condition(
+                        sense,
+                        sg::KEY_MEDIUM_ERROR,
+                        (0x11, 0x00),
+                        0,
+                        Some(requested as i32),
+                        requested,
+                    ),
+                    MockObject::Unreliable(..) => unreachable!(),
+                }
+            }
+            sg::OP_SPACE_6 => match cdb[1] & 0x07 {
+                sg::SPACE_FILEMARKS => {
+                    for done in 0..count.abs() {
+                        let moved = if count > 0 {
+                            self.forward_file()
+                        } else {
+                            self.backward_file()
+                        };
+                        if !moved {
+                            let remaining = Some(count.abs() - done);
+                            return if count > 0 {
+                                check_condition(
+                                    sense,
+                                    sg::KEY_BLANK_CHECK,
+                                    END_OF_DATA,
+                                    0,
+                                    remaining,
+                                    0,
+                                )
+                            } else {
+                                check_condition(
+                                    sense,
+                                    sg::KEY_NO_SENSE,
+                                    BEGINNING_OF_MEDIUM,
+                                    EOM,
+                                    remaining,
+                                    0,
+                                )
+                            };
+                        }
+                    }
+                    good(0)
+                }
+                sg::SPACE_BLOCKS => {
+                    for done in 0..count.abs() {
+                        let remaining = Some(count.abs() - done);
+                        let (key, asc, flags) = if count > 0 {
+                            if self.position >= self.objects.len() {
+                                (sg::KEY_BLANK_CHECK, END_OF_DATA, 0)
+                            } else {
+                                self.position += 1;
+                                if !self.is_tape_mark(self.position - 1) {
+                                    continue;
+                                }
+                                (sg::KEY_NO_SENSE, FILEMARK_DETECTED, FILEMARK)
+                            }
+                        } else if self.position == 0 {
+                            (sg::KEY_NO_SENSE, BEGINNING_OF_MEDIUM, EOM)
+                        } else {
+                            // Stops on the BOT side of a filemark.
+                            self.position -= 1;
+                            if !self.is_tape_mark(self.position) {
+                                continue;
+                            }
+                            (sg::KEY_NO_SENSE, FILEMARK_DETECTED, FILEMARK)
+                        };
+                        return check_condition(sense, key, asc, flags, remaining, 0);
+                    }
+                    good(0)
+                }
+                _ => check_condition(sense, ILLEGAL_REQUEST, (0x24, 0x00), 0, None, 0),
+            },
+            _ => check_condition(sense, ILLEGAL_REQUEST, (0x20, 0x00), 0, None, 0),
+        }
+    }
+}
+
+impl StBackend for MockTape {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.online {
+            return Ok(0);
+        }
+        let Some(object) = self.next_object() else {
+            // Like st, the first read at end of data returns 0 and later ones fail.
+            self.eod_reads += 1;
+            return if self.eod_reads == 1 {
+                Ok(0)
+            } else {
+                Err(Self::io_error())
+            };
+        };
+
+        match object {
+            MockObject::Record(data) if data.len() > buf.len() => {
+                Err(io::Error::from_raw_os_error(libc::ENOMEM))
+            }
+            MockObject::Record(data) => {
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(data.len())
+            }
+            MockObject::TapeMark => Ok(0),
+            MockObject::Error(errno) => Err(io::Error::from_raw_os_error(errno)),
+            MockObject::Unreliable(..) => unreachable!(),
+        }
+    }
+
+    fn get_status(&mut self) -> io::Result<MtGet> {
+        let before = &self.objects[..self.position.min(self.objects.len())];
+        let file_start = before
+            .iter()
+            .rposition(|object| matches!(object, MockObject::TapeMark))
+            .map_or(0, |index| index + 1);
+        let flags = [
+            (self.online, 0x0100_0000),
+            (self.write_protected, 0x0400_0000),
+            (self.past_early_warning, 0x2000_0000),
+            (self.position == 0, 0x4000_0000),
+            (
+                self.position > 0 && self.is_tape_mark(self.position - 1),
+                0x8000_0000,
+            ),
+            (self.eod_reads > 0, 0x0800_0000),
+        ];
+        Ok(MtGet {
+            mt_dsreg: (c_long::from(self.density) << 24) | c_long::from(self.block_size),
+            mt_gstat: flags
+                .into_iter()
+                .filter(|(set, _)| *set)
+                .fold(0, |gstat, (_, bit)| gstat | bit),
+            mt_fileno: before
+                .iter()
+                .filter(|object| matches!(object, MockObject::TapeMark))
+                .count() as i32,
+            mt_blkno: (self.position - file_start) as i32,
+            ..Default::default()
+        })
+    }
+
+    fn get_position(&mut self) -> io::Result<u64> {
+        Ok(self.position as u64)
+    }
+
+    fn operation(&mut self, op: MtOp, count: i32) -> io::Result<()> {
+        self.operations.push((op, count));
+        self.eod_reads = 0;
+        match op {
+            MtOp::Rewind => self.position = 0,
+            MtOp::ForwardFiles => {
+                for _ in 0..count {
+                    if !self.forward_file() {
+                        return Err(Self::io_error());
+                    }
+                }
+            }
+            MtOp::BackwardFiles => {
+                for _ in 0..count {
+                    if !self.backward_file() {
+                        return Err(Self::io_error());
+                    }
+                }
+            }
+            MtOp::ForwardRecords => {
+                for _ in 0..count {
+                    if self.position >= self.objects.len() {
+                        return Err(Self::io_error());
+                    }
+                    self.position += 1;
+                    if self.is_tape_mark(self.position - 1) {
+                        return Err(Self::io_error());
+                    }
+                }
+            }
+            MtOp::BackwardRecords => {
+                for _ in 0..count {
+                    if self.position == 0 || self.is_tape_mark(self.position - 1) {
+                        return Err(Self::io_error());
+                    }
+                    self.position -= 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
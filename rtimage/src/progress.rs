@@ -0,0 +1,238 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// How often the status line is redrawn on a terminal.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+/// How often a plain progress line is logged when stderr is not a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(60);
+/// Tape taken by a tape mark and the gap in front of it.
+const TAPE_MARK_INCHES: f64 = 3.75;
+const BYTES_PER_MB: f64 = 1_000_000.0;
+
+/// Reel length and recording density, for estimating the tape left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reel {
+    pub length_feet: u32,
+    pub bpi: u32,
+}
+
+impl Reel {
+    /// Half-inch inter-record gap: 0.6" at 800 and 1600 bpi, 0.3" for GCR.
+    fn gap_inches(&self) -> f64 {
+        if self.bpi >= 6250 { 0.3 } else { 0.6 }
+    }
+
+    fn block_inches(&self, bytes: u64) -> f64 {
+        bytes as f64 / f64::from(self.bpi) + self.gap_inches()
+    }
+}
+
+/// Live status of a capture: a redrawn line on a terminal, an occasional
+/// plain line otherwise.
+pub struct Progress {
+    enabled: bool,
+    terminal: bool,
+    reel: Option<Reel>,
+    started: Instant,
+    last_shown: Instant,
+    drawn: bool,
+    file: u32,
+    block: u32,
+    bytes: u64,
+    /// Bytes already captured before this run, left out of the rate.
+    resumed_bytes: u64,
+    retries: usize,
+    used_inches: f64,
+    /// Tape passed over by this run, for the ETA.
+    run_inches: f64,
+    file_started: Instant,
+    file_bytes: u64,
+}
+
+impl Progress {
+    pub fn new(enabled: bool, reel: Option<Reel>) -> Self {
+        let now = Instant::now();
+        Self {
+            enabled,
+            terminal: io::stderr().is_terminal(),
+            reel,
+            started: now,
+            last_shown: now,
+            drawn: false,
+            file: 0,
+            block: 0,
+            bytes: 0,
+            resumed_bytes: 0,
+            retries: 0,
+            used_inches: 0.0,
+            run_inches: 0.0,
+            file_started: now,
+            file_bytes: 0,
+        }
+    }
+
+    /// Counts tape a resumed capture has already been over.
+    pub fn with_position(mut self, files: u32, blocks: u64, bytes: u64) -> Self {
+        self.bytes = bytes;
+        self.resumed_bytes = bytes;
+        if let Some(reel) = self.reel {
+            self.used_inches = bytes as f64 / f64::from(reel.bpi)
+                + blocks as f64 * reel.gap_inches()
+                + f64::from(files) * TAPE_MARK_INCHES;
+        }
+        self
+    }
+
+    /// Starts tape file `file`, of which `blocks` were already captured.
+    pub fn start_file(&mut self, file: u32, blocks: u32) {
+        self.file = file;
+        self.block = blocks;
+        self.file_started = Instant::now();
+        self.file_bytes = 0;
+    }
+
+    pub fn block(&mut self, bytes: usize) {
+        self.block += 1;
+        self.bytes += bytes as u64;
+        self.file_bytes += bytes as u64;
+        if let Some(reel) = self.reel {
+            self.advance(reel.block_inches(bytes as u64));
+        }
+        self.show();
+    }
+
+    pub fn tape_mark(&mut self) {
+        self.advance(TAPE_MARK_INCHES);
+    }
+
+    pub fn retry(&mut self) {
+        self.retries += 1;
+    }
+
+    fn advance(&mut self, inches: f64) {
+        self.used_inches += inches;
+        self.run_inches += inches;
+    }
+
+    /// Time spent on the current file and its rate, for the per-file line.
+    pub fn file_summary(&self) -> String {
+        let elapsed = self.file_started.elapsed();
+        format!(
+            "{}, {:.2} MB/s",
+            format_duration(elapsed),
+            rate(self.file_bytes, elapsed)
+        )
+    }
+
+    /// Removes the status line so other output starts on a clean line.
+    pub fn clear(&mut self) {
+        if self.drawn {
+            eprint!("\r\x1b[K");
+            self.drawn = false;
+        }
+    }
+
+    fn show(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let interval = if self.terminal {
+            REDRAW_INTERVAL
+        } else {
+            LOG_INTERVAL
+        };
+        if now.duration_since(self.last_shown) < interval {
+            return;
+        }
+        self.last_shown = now;
+
+        let status = self.status(now.duration_since(self.started));
+        if self.terminal {
+            eprint!("\r\x1b[K{status}");
+            let _ = io::stderr().flush();
+            self.drawn = true;
+        } else {
+            eprintln!("[progress] {status}");
+        }
+    }
+
+    fn status(&self, elapsed: Duration) -> String {
+        let mut status = format!(
+            "Record {}, block {} | {:.1} MB | {:.2} MB/s | {} retries | {}",
+            self.file,
+            self.block,
+            self.bytes as f64 / BYTES_PER_MB,
+            rate(self.bytes - self.resumed_bytes, elapsed),
+            self.retries,
+            format_duration(elapsed)
+        );
+        if let Some(reel) = self.reel {
+            let left_inches = (f64::from(reel.length_feet) * 12.0 - self.used_inches).max(0.0);
+            status.push_str(&format!(" | ~{:.0} ft left", left_inches / 12.0));
+            if self.run_inches > 0.0 {
+                let eta = elapsed.as_secs_f64() * left_inches / self.run_inches;
+                status.push_str(&format!(
+                    ", ETA {}",
+                    format_duration(Duration::from_secs_f64(eta))
+                ));
+            }
+        }
+        status
+    }
+}
+
+fn rate(bytes: u64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        bytes as f64 / BYTES_PER_MB / seconds
+    } else {
+        0.0
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_position_rate_and_elapsed_time() {
+        let mut progress = Progress::new(false, None);
+        progress.start_file(2, 0);
+        progress.block(1_000_000);
+        progress.block(500_000);
+        progress.retry();
+        assert_eq!(
+            progress.status(Duration::from_secs(3)),
+            "Record 2, block 2 | 1.5 MB | 0.50 MB/s | 1 retries | 00:00:03"
+        );
+    }
+
+    #[test]
+    fn estimates_the_tape_left_from_the_reel() {
+        let reel = Reel {
+            length_feet: 100,
+            bpi: 1600,
+        };
+        // 10 blocks of 16,000 bytes take 100" of data and 6" of gaps.
+        let mut progress = Progress::new(false, Some(reel)).with_position(0, 10, 160_000);
+        progress.start_file(1, 10);
+        for _ in 0..10 {
+            progress.block(16_000);
+        }
+        assert_eq!(
+            progress.status(Duration::from_secs(60)),
+            "Record 1, block 20 | 0.3 MB | 0.00 MB/s | 0 retries | 00:01:00 | ~82 ft left, ETA 00:09:19"
+        );
+    }
+}
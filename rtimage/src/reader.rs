@@ -1,26 +1,192 @@
 use crossbeam_channel::Sender;
-use std::io::Read;
+use serde::Serialize;
+use std::io::{self, Read};
 use std::thread;
 
 // Default buffer size from timage.c (120KB)
-const MAXSIZE: usize = 120 * 1024;
+pub const DEFAULT_BLOCK_SIZE: usize = 120 * 1024;
+
+/// Outcome of reading one block from a tape source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapeRead {
+    Data(usize),
+    TapeMark,
+    EndOfData,
+    NotReady,
+    /// A block the drive could not read; the first `length` bytes of the
+    /// buffer hold whatever partial data came back.
+    BadBlock {
+        length: usize,
+        reason: String,
+    },
+    /// A block longer than the buffer, of `length` bytes when the drive says.
+    Overlength {
+        length: Option<usize>,
+    },
+}
+
+/// What a drive says about itself, for the capture manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DriveInfo {
+    pub vendor: Option<String>,
+    pub product: Option<String>,
+    pub revision: Option<String>,
+    /// SCSI density code of the loaded tape.
+    pub density: Option<u8>,
+}
+
+impl DriveInfo {
+    /// Vendor, product and revision, as far as the drive told us.
+    pub fn identity(&self) -> Option<String> {
+        let parts: Vec<&str> = [&self.vendor, &self.product, &self.revision]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Recording density in bits per inch, for the half-inch density codes.
+    pub fn bpi(&self) -> Option<u32> {
+        match self.density? {
+            0x01 => Some(800),
+            0x02 => Some(1600),
+            0x03 => Some(6250),
+            _ => None,
+        }
+    }
+
+    /// The density code, named for the common half-inch densities.
+    pub fn density_name(&self) -> Option<String> {
+        let code = self.density?;
+        Some(match code {
+            0x00 => "drive default (0x00)".to_string(),
+            0x01 => "800 bpi NRZI (0x01)".to_string(),
+            0x02 => "1600 bpi PE (0x02)".to_string(),
+            0x03 => "6250 bpi GCR (0x03)".to_string(),
+            code => format!("0x{code:02X}"),
+        })
+    }
+}
+
+/// Something rtimage can read tape blocks from.
+pub trait TapeSource: Send {
+    fn read_block(&mut self, buf: &mut [u8]) -> io::Result<TapeRead>;
+
+    /// Whether tape marks and end of data are reported by the drive rather
+    /// than inferred from 0-byte reads.
+    fn is_tape_drive(&self) -> bool {
+        false
+    }
+
+    fn rewind(&mut self) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Spaces over `count` tape marks, backwards if negative.
+    fn space_files(&mut self, _count: i32) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Spaces over `count` blocks, backwards if negative.
+    fn space_blocks(&mut self, _count: i32) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// The largest block the drive will return, when it can say.
+    fn block_limit(&mut self) -> Option<usize> {
+        None
+    }
+
+    /// The drive's block size in fixed block mode, which every read must use.
+    fn fixed_block_size(&mut self) -> Option<usize> {
+        None
+    }
+
+    fn drive_info(&mut self) -> DriveInfo {
+        DriveInfo::default()
+    }
+}
+
+/// How the reader thread sizes its buffer and handles blocks the drive
+/// cannot read.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOptions {
+    /// Re-reads to attempt, spacing back over the block before each one.
+    pub retries: u32,
+    /// Write unreadable blocks as bad records and carry on, rather than abort.
+    pub record_bad_blocks: bool,
+    pub block_size: usize,
+    /// Largest size the buffer grows to for blocks that do not fit.
+    pub max_block: usize,
+}
+
+/// A device, file or pipe read with plain `read(2)`, where every 0-byte
+/// read counts as a tape mark.
+pub struct PlainSource<R>(pub R);
+
+impl<R: Read + Send> TapeSource for PlainSource<R> {
+    fn read_block(&mut self, buf: &mut [u8]) -> io::Result<TapeRead> {
+        match self.0.read(buf)? {
+            0 => Ok(TapeRead::TapeMark),
+            n => Ok(TapeRead::Data(n)),
+        }
+    }
+}
 
 pub enum TapeEvent {
     Data(Vec<u8>),
-    TapeMark, // 0-byte read
+    TapeMark,
+    EndOfData,
+    NotReady,
+    /// A failed read is being retried.
+    Retry {
+        attempt: u32,
+        reason: String,
+    },
+    /// The next block read cleanly after this many retries.
+    Recovered {
+        attempts: u32,
+    },
+    /// A block that could not be read, with any partial data.
+    BadBlock {
+        data: Vec<u8>,
+        reason: String,
+    },
+    /// The buffer grew to this size to re-read a block that did not fit.
+    Resized {
+        size: usize,
+    },
     Error(String),
 }
 
+/// Reads one tape file on a background thread, handing the source back when
+/// the file ends so the next one can be read from the same position.
 pub fn start_reader_thread(
-    mut reader: Box<dyn Read + Send>,
+    mut reader: Box<dyn TapeSource>,
     sender: Sender<TapeEvent>,
-) -> thread::JoinHandle<()> {
+    options: ReadOptions,
+) -> thread::JoinHandle<Box<dyn TapeSource>> {
     thread::spawn(move || {
-        let mut buffer = vec![0u8; MAXSIZE];
+        let mut buffer = vec![0u8; options.block_size];
+        // A read already made while retrying a bad block, handled next.
+        let mut pending = None;
 
         loop {
-            match reader.read(&mut buffer) {
-                Ok(0) => {
+            let result = match pending.take() {
+                Some(result) => result,
+                None => reader.read_block(&mut buffer),
+            };
+            match result {
+                Ok(TapeRead::EndOfData) => {
+                    let _ = sender.send(TapeEvent::EndOfData);
+                    break;
+                }
+                Ok(TapeRead::NotReady) => {
+                    let _ = sender.send(TapeEvent::NotReady);
+                    break;
+                }
+                Ok(TapeRead::TapeMark) => {
                     // Tape Mark or EOF
                     // In timage.c, if count == 0 (no data read at all), it retries.
                     // "Drive reported 0 bytes on last read, we will try again."
@@ -61,13 +227,46 @@ pub fn start_reader_thread(
                     // The Main Thread will then decide whether to spawn a NEW Reader Thread.
                     break;
                 }
-                Ok(n) => {
+                Ok(TapeRead::Data(n)) => {
                     // Send data
                     let data = buffer[0..n].to_vec();
                     if sender.send(TapeEvent::Data(data)).is_err() {
                         break;
                     }
                 }
+                Ok(TapeRead::BadBlock { length, reason }) => {
+                    let data = buffer[..length].to_vec();
+                    match retry_bad_block(&mut *reader, &mut buffer, options, &sender, data, reason)
+                    {
+                        Retried::Recovered { attempts, length } => {
+                            if sender.send(TapeEvent::Recovered { attempts }).is_err() {
+                                break;
+                            }
+                            pending = Some(Ok(TapeRead::Data(length)));
+                        }
+                        Retried::Failed { data, reason, next } => {
+                            let event = if options.record_bad_blocks {
+                                TapeEvent::BadBlock { data, reason }
+                            } else {
+                                TapeEvent::Error(reason)
+                            };
+                            if sender.send(event).is_err() || !options.record_bad_blocks {
+                                break;
+                            }
+                            pending = next;
+                        }
+                    }
+                }
+                Ok(TapeRead::Overlength { length }) => {
+                    let event = match grow_buffer(&mut *reader, &mut buffer, options, length) {
+                        Ok(size) => TapeEvent::Resized { size },
+                        Err(message) => TapeEvent::Error(message),
+                    };
+                    let failed = matches!(event, TapeEvent::Error(_));
+                    if sender.send(event).is_err() || failed {
+                        break;
+                    }
+                }
                 Err(e) => {
                     // Check for retryable errors?
                     // timage.c checks ENOENT, ENXIO, ENODEV, EIO and exits with error.
@@ -76,5 +275,209 @@ pub fn start_reader_thread(
                 }
             }
         }
+        reader
     })
 }
+
+/// Grows the buffer to fit a block that was too long for it and spaces back
+/// so it is read again. Fails, rather than risk a truncated record, when the
+/// block cannot fit in `options.max_block` or cannot be re-read.
+fn grow_buffer(
+    reader: &mut dyn TapeSource,
+    buffer: &mut Vec<u8>,
+    options: ReadOptions,
+    length: Option<usize>,
+) -> Result<usize, String> {
+    let size = match length {
+        Some(length) if length > options.max_block => {
+            return Err(format!(
+                "Block of {length} bytes is longer than the {}-byte maximum (see --max-block)",
+                options.max_block
+            ));
+        }
+        Some(length) => length,
+        None if buffer.len() >= options.max_block => {
+            return Err(format!(
+                "Block is longer than the {}-byte maximum and would be truncated (see --max-block)",
+                options.max_block
+            ));
+        }
+        None => (buffer.len() * 2).min(options.max_block),
+    };
+    reader.space_blocks(-1).map_err(|err| {
+        format!(
+            "Block is longer than the {}-byte read buffer and cannot be re-read: {err}",
+            buffer.len()
+        )
+    })?;
+    buffer.resize(size, 0);
+    Ok(size)
+}
+
+enum Retried {
+    /// A retry read the block cleanly into the buffer.
+    Recovered { attempts: u32, length: usize },
+    /// Every retry failed. `data` is the longest partial block seen, and
+    /// `next` any read that came back as something other than the block.
+    Failed {
+        data: Vec<u8>,
+        reason: String,
+        next: Option<io::Result<TapeRead>>,
+    },
+}
+
+/// Re-reads a bad block up to `options.retries` times. Drives leave the tape
+/// past a block that failed to read, so each retry first spaces back one block.
+fn retry_bad_block(
+    reader: &mut dyn TapeSource,
+    buffer: &mut [u8],
+    options: ReadOptions,
+    sender: &Sender<TapeEvent>,
+    mut data: Vec<u8>,
+    mut reason: String,
+) -> Retried {
+    for attempt in 1..=options.retries {
+        let _ = sender.send(TapeEvent::Retry {
+            attempt,
+            reason: reason.clone(),
+        });
+        if let Err(err) = reader.space_blocks(-1) {
+            reason = format!("{reason} (cannot reposition to retry: {err})");
+            break;
+        }
+        match reader.read_block(buffer) {
+            Ok(TapeRead::BadBlock {
+                length,
+                reason: why,
+            }) => {
+                if length > data.len() {
+                    data = buffer[..length].to_vec();
+                }
+                reason = why;
+            }
+            Ok(TapeRead::Data(length)) => {
+                return Retried::Recovered {
+                    attempts: attempt,
+                    length,
+                };
+            }
+            other => {
+                return Retried::Failed {
+                    data,
+                    reason,
+                    next: Some(other),
+                };
+            }
+        }
+    }
+    Retried::Failed {
+        data,
+        reason,
+        next: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockObject, MockTape};
+    use crate::st::StDrive;
+    use crossbeam_channel::unbounded;
+
+    const OPTIONS: ReadOptions = ReadOptions {
+        retries: 0,
+        record_bad_blocks: false,
+        block_size: 64,
+        max_block: 256,
+    };
+
+    fn read_file(objects: Vec<MockObject>, options: ReadOptions) -> Vec<TapeEvent> {
+        let (sender, receiver) = unbounded();
+        let source = Box::new(StDrive::new(MockTape::new(objects)));
+        start_reader_thread(source, sender, options).join().unwrap();
+        receiver.into_iter().collect()
+    }
+
+    #[test]
+    fn recovers_blocks_that_read_on_retry() {
+        let events = read_file(
+            vec![
+                MockObject::Record(vec![1; 4]),
+                MockObject::Unreliable(2, vec![2; 6]),
+                MockObject::TapeMark,
+            ],
+            ReadOptions {
+                retries: 3,
+                record_bad_blocks: false,
+                ..OPTIONS
+            },
+        );
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TapeEvent::Data(first),
+                TapeEvent::Retry { attempt: 1, .. },
+                TapeEvent::Retry { attempt: 2, .. },
+                TapeEvent::Recovered { attempts: 2 },
+                TapeEvent::Data(second),
+                TapeEvent::TapeMark,
+            ] if first == &[1; 4] && second == &[2; 6]
+        ));
+    }
+
+    #[test]
+    fn records_or_aborts_on_unreadable_blocks() {
+        let objects = vec![
+            MockObject::Error(libc::EIO),
+            MockObject::Record(vec![3; 2]),
+            MockObject::TapeMark,
+        ];
+        let events = read_file(
+            objects.clone(),
+            ReadOptions {
+                retries: 1,
+                record_bad_blocks: true,
+                ..OPTIONS
+            },
+        );
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TapeEvent::Retry { attempt: 1, .. },
+                TapeEvent::BadBlock { data, .. },
+                TapeEvent::Data(_),
+                TapeEvent::TapeMark,
+            ] if data.is_empty()
+        ));
+
+        let events = read_file(
+            objects,
+            ReadOptions {
+                retries: 0,
+                record_bad_blocks: false,
+                ..OPTIONS
+            },
+        );
+        assert!(matches!(events.as_slice(), [TapeEvent::Error(_)]));
+    }
+
+    #[test]
+    fn grows_the_buffer_for_long_blocks() {
+        let events = read_file(
+            vec![
+                MockObject::Record(vec![1; 200]),
+                MockObject::Record(vec![2; 300]),
+            ],
+            OPTIONS,
+        );
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TapeEvent::Resized { size: 128 },
+                TapeEvent::Resized { size: 256 },
+                TapeEvent::Data(data),
+                TapeEvent::Error(message),
+            ] if data.len() == 200 && message.contains("256-byte maximum")
+        ));
+    }
+}
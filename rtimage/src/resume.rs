@@ -0,0 +1,143 @@
+use rtsimh::{
+    SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader, TAPE_DESCRIPTION_CLASS,
+};
+use std::io::{Read, Seek, SeekFrom};
+
+/// Where a partial capture left off, found by walking the image it wrote.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ResumePoint {
+    /// Tape files completed, each ended by a tape mark.
+    pub files: u32,
+    /// How many of those held data.
+    pub data_files: u32,
+    /// Records already captured from the file in progress.
+    pub file_records: u32,
+    pub blocks: u64,
+    pub bytes: u64,
+    /// Bytes already captured from the file in progress.
+    pub file_bytes: u64,
+    pub trailing_tape_marks: u32,
+    /// Image length up to the end of the last complete object.
+    pub valid_length: u64,
+    /// Bytes of an incomplete trailing record after `valid_length`.
+    pub discarded: u64,
+}
+
+impl ResumePoint {
+    /// Whether the capture already reached the end of the tape.
+    pub fn is_complete(&self) -> bool {
+        self.trailing_tape_marks >= 2
+    }
+}
+
+pub fn scan<R: Read + Seek>(mut image: R) -> SimhResult<ResumePoint> {
+    let size = image.seek(SeekFrom::End(0))?;
+    image.seek(SeekFrom::Start(0))?;
+    let mut reader = SimhTapeReader::new(image);
+    let mut point = ResumePoint::default();
+
+    loop {
+        match reader.skip_block() {
+            // A description record is not tape data.
+            Ok(SimhTapeBlock::Record(record)) if record.header.class == TAPE_DESCRIPTION_CLASS => {}
+            Ok(SimhTapeBlock::Record(record)) => {
+                point.file_records += 1;
+                point.blocks += 1;
+                point.bytes += u64::from(record.header.length);
+                point.file_bytes += u64::from(record.header.length);
+                point.trailing_tape_marks = 0;
+            }
+            Ok(SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                ..
+            }) => {
+                point.files += 1;
+                if point.file_records > 0 {
+                    point.data_files += 1;
+                }
+                point.file_records = 0;
+                point.file_bytes = 0;
+                point.trailing_tape_marks += 1;
+            }
+            // rtimage writes no other markers; keep any that are there.
+            Ok(SimhTapeBlock::TapeMark { .. }) => {}
+            Ok(_) => break,
+            // A capture cut short leaves a partial record at the end.
+            Err(
+                SimhError::TruncatedWord { .. }
+                | SimhError::TruncatedRecord { .. }
+                | SimhError::MissingTrailingLength { .. },
+            ) => break,
+            Err(err) => return Err(err),
+        }
+        point.valid_length = reader.position()?;
+    }
+
+    point.discarded = size - point.valid_length;
+    Ok(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtsimh::SimhTapeWriter;
+    use std::io::Cursor;
+
+    #[test]
+    fn finds_where_an_interrupted_capture_stopped() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_tape_description(b"Operator: test").unwrap();
+        writer.write_record(&[1; 10]).unwrap();
+        writer.write_bad_record(&[2; 3]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_record(&[3; 6]).unwrap();
+        let mut bytes = writer.into_inner();
+        let valid_length = bytes.len() as u64;
+        bytes.extend_from_slice(&[0x00, 0x02, 0x00, 0x00, 4, 4, 4]);
+
+        let point = scan(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            point,
+            ResumePoint {
+                files: 2,
+                data_files: 1,
+                file_records: 1,
+                blocks: 3,
+                bytes: 19,
+                file_bytes: 6,
+                trailing_tape_marks: 0,
+                valid_length,
+                discarded: 7,
+            }
+        );
+        assert!(!point.is_complete());
+    }
+
+    #[test]
+    fn recognises_finished_and_damaged_images() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 4]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        assert!(
+            scan(Cursor::new(writer.into_inner()))
+                .unwrap()
+                .is_complete()
+        );
+        assert_eq!(
+            scan(Cursor::new(Vec::new())).unwrap(),
+            ResumePoint::default()
+        );
+
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 4]).unwrap();
+        writer.write_record(&[2; 4]).unwrap();
+        let mut bytes = writer.into_inner();
+        bytes[8] = 5;
+        assert!(matches!(
+            scan(Cursor::new(bytes)),
+            Err(SimhError::LengthMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,716 @@
+use crate::reader::{DriveInfo, TapeRead, TapeSource};
+use std::fmt;
+use std::io;
+
+// SCSI status bytes.
+const STATUS_GOOD: u8 = 0x00;
+const STATUS_CHECK_CONDITION: u8 = 0x02;
+
+// Sense keys (SPC-4 table 48).
+pub const KEY_NO_SENSE: u8 = 0x0;
+pub const KEY_RECOVERED_ERROR: u8 = 0x1;
+pub const KEY_NOT_READY: u8 = 0x2;
+pub const KEY_MEDIUM_ERROR: u8 = 0x3;
+pub const KEY_UNIT_ATTENTION: u8 = 0x6;
+pub const KEY_BLANK_CHECK: u8 = 0x8;
+pub const KEY_VOLUME_OVERFLOW: u8 = 0xD;
+
+// Command operation codes (SSC-3).
+pub const OP_TEST_UNIT_READY: u8 = 0x00;
+pub const OP_REWIND: u8 = 0x01;
+pub const OP_READ_BLOCK_LIMITS: u8 = 0x05;
+pub const OP_READ_6: u8 = 0x08;
+pub const OP_SPACE_6: u8 = 0x11;
+pub const OP_INQUIRY: u8 = 0x12;
+pub const OP_MODE_SENSE_6: u8 = 0x1A;
+pub const OP_READ_POSITION: u8 = 0x34;
+
+// SPACE(6) codes.
+pub const SPACE_BLOCKS: u8 = 0x0;
+pub const SPACE_FILEMARKS: u8 = 0x1;
+
+/// READ BLOCK LIMITS response length.
+pub const BLOCK_LIMITS_LENGTH: usize = 6;
+/// READ POSITION short form response length.
+pub const POSITION_LENGTH: usize = 20;
+/// Standard INQUIRY data, up to the product revision.
+pub const INQUIRY_LENGTH: usize = 36;
+/// MODE SENSE(6) header plus one block descriptor.
+pub const MODE_SENSE_LENGTH: usize = 12;
+const SENSE_LENGTH: usize = 64;
+/// Largest transfer a 24-bit READ(6) length can ask for.
+const MAX_TRANSFER: usize = 0xFF_FFFF;
+
+/// How a command completed at the SCSI level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandResult {
+    pub status: u8,
+    /// Bytes of the data buffer that were not transferred.
+    pub residual: usize,
+    /// Bytes of sense data written.
+    pub sense_length: usize,
+}
+
+/// Issues a CDB and collects the data and sense it returns. Implemented by
+/// `SgDevice` through the `SG_IO` ioctl and by a simulated device in tests.
+pub trait SgBackend {
+    fn execute(
+        &mut self,
+        cdb: &[u8],
+        data: &mut [u8],
+        sense: &mut [u8],
+    ) -> io::Result<CommandResult>;
+}
+
+/// Sense data decoded from either the fixed (0x70/0x71) or descriptor
+/// (0x72/0x73) format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SenseData {
+    pub key: u8,
+    pub asc: u8,
+    pub ascq: u8,
+    pub filemark: bool,
+    pub eom: bool,
+    /// Incorrect length indicator: the block was not the size asked for.
+    pub ili: bool,
+    /// The INFORMATION field when valid; for reads, the requested length
+    /// minus the actual block length.
+    pub information: Option<i64>,
+    pub deferred: bool,
+}
+
+impl SenseData {
+    pub fn parse(raw: &[u8]) -> Option<Self> {
+        let byte = |index: usize| raw.get(index).copied().unwrap_or(0);
+        let code = *raw.first()? & 0x7F;
+        match code {
+            0x70 | 0x71 if raw.len() >= 3 => {
+                let flags = raw[2];
+                let information = (raw[0] & 0x80 != 0 && raw.len() >= 7)
+                    .then(|| i64::from(i32::from_be_bytes([raw[3], raw[4], raw[5], raw[6]])));
+                Some(Self {
+                    key: flags & 0x0F,
+                    asc: byte(12),
+                    ascq: byte(13),
+                    filemark: flags & 0x80 != 0,
+                    eom: flags & 0x40 != 0,
+                    ili: flags & 0x20 != 0,
+                    information,
+                    deferred: code == 0x71,
+                })
+            }
+            0x72 | 0x73 if raw.len() >= 4 => {
+                let mut sense = Self {
+                    key: raw[1] & 0x0F,
+                    asc: raw[2],
+                    ascq: raw[3],
+                    deferred: code == 0x73,
+                    ..Default::default()
+                };
+                let end = raw.len().min(8 + usize::from(byte(7)));
+                let mut descriptors = raw.get(8..end).unwrap_or_default();
+                while let [kind, length, rest @ ..] = descriptors {
+                    let length = usize::from(*length).min(rest.len());
+                    let body = &rest[..length];
+                    match (kind, body) {
+                        (0x00, [valid, _, a, b, c, d, e, f, g, h, ..]) if valid & 0x80 != 0 => {
+                            sense.information =
+                                Some(i64::from_be_bytes([*a, *b, *c, *d, *e, *f, *g, *h]));
+                        }
+                        (0x04, [_, flags, ..]) => {
+                            sense.filemark = flags & 0x80 != 0;
+                            sense.eom = flags & 0x40 != 0;
+                            sense.ili = flags & 0x20 != 0;
+                        }
+                        _ => {}
+                    }
+                    descriptors = &rest[length..];
+                }
+                Some(sense)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn key_name(&self) -> &'static str {
+        match self.key {
+            0x0 => "NO SENSE",
+            0x1 => "RECOVERED ERROR",
+            0x2 => "NOT READY",
+            0x3 => "MEDIUM ERROR",
+            0x4 => "HARDWARE ERROR",
+            0x5 => "ILLEGAL REQUEST",
+            0x6 => "UNIT ATTENTION",
+            0x7 => "DATA PROTECT",
+            0x8 => "BLANK CHECK",
+            0x9 => "VENDOR SPECIFIC",
+            0xA => "COPY ABORTED",
+            0xB => "ABORTED COMMAND",
+            0xD => "VOLUME OVERFLOW",
+            0xE => "MISCOMPARE",
+            _ => "RESERVED",
+        }
+    }
+
+    /// Description of the additional sense code, for the codes tape drives
+    /// commonly report.
+    pub fn asc_description(&self) -> Option<&'static str> {
+        Some(match (self.asc, self.ascq) {
+            (0x00, 0x00) => "no additional sense information",
+            (0x00, 0x01) => "filemark detected",
+            (0x00, 0x02) => "end-of-partition/medium detected",
+            (0x00, 0x04) => "beginning-of-partition/medium detected",
+            (0x00, 0x05) => "end-of-data detected",
+            (0x04, 0x00) => "logical unit not ready, cause not reportable",
+            (0x04, 0x01) => "logical unit is in process of becoming ready",
+            (0x04, 0x02) => "logical unit not ready, initializing command required",
+            (0x11, 0x00) => "unrecovered read error",
+            (0x11, 0x01) => "read retries exhausted",
+            (0x11, 0x08) => "incomplete block read",
+            (0x14, 0x00) => "recorded entity not found",
+            (0x14, 0x01) => "record not found",
+            (0x14, 0x03) => "end-of-data not found",
+            (0x15, 0x01) => "mechanical positioning error",
+            (0x20, 0x00) => "invalid command operation code",
+            (0x24, 0x00) => "invalid field in CDB",
+            (0x28, 0x00) => "not ready to ready change, medium may have changed",
+            (0x29, 0x00) => "power on, reset, or bus device reset occurred",
+            (0x30, 0x00) => "incompatible medium installed",
+            (0x30, 0x01) => "cannot read medium, unknown format",
+            (0x30, 0x02) => "cannot read medium, incompatible format",
+            (0x31, 0x00) => "medium format corrupted",
+            (0x3A, 0x00) => "medium not present",
+            (0x3B, 0x00) => "sequential positioning error",
+            (0x44, 0x00) => "internal target failure",
+            (0x52, 0x00) => "cartridge fault",
+            (0x53, 0x00) => "media load or eject failed",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for SenseData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, ASC/ASCQ {:02X}/{:02X}",
+            self.key_name(),
+            self.asc,
+            self.ascq
+        )?;
+        if let Some(description) = self.asc_description() {
+            write!(f, " ({description})")?;
+        }
+        for (set, name) in [
+            (self.filemark, "FILEMARK"),
+            (self.eom, "EOM"),
+            (self.ili, "ILI"),
+        ] {
+            if set {
+                write!(f, " {name}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SenseData {}
+
+/// A READ(6) outcome, classified from the SCSI status and sense data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SgEvent {
+    /// A block of this length; shorter blocks than the request are normal in
+    /// variable block mode.
+    Data(usize),
+    /// A block longer than the request; only `transferred` bytes were read.
+    Overlength {
+        transferred: usize,
+        length: usize,
+    },
+    Filemark,
+    /// Blank tape, the end of recorded data.
+    BlankCheck,
+    /// The physical end of medium, with nothing read.
+    EndOfMedium,
+    NotReady(SenseData),
+    /// An unreadable block; `transferred` bytes of it came back.
+    MediumError {
+        transferred: usize,
+        sense: SenseData,
+    },
+    Failed(SenseData),
+}
+
+impl SgEvent {
+    pub fn classify(requested: usize, transferred: usize, sense: Option<&SenseData>) -> Self {
+        let Some(sense) = sense else {
+            return SgEvent::Data(transferred);
+        };
+        match sense.key {
+            KEY_MEDIUM_ERROR => SgEvent::MediumError {
+                transferred,
+                sense: *sense,
+            },
+            KEY_NOT_READY | KEY_UNIT_ATTENTION => SgEvent::NotReady(*sense),
+            KEY_BLANK_CHECK => SgEvent::BlankCheck,
+            KEY_NO_SENSE | KEY_RECOVERED_ERROR | KEY_VOLUME_OVERFLOW => {
+                let residual = sense.information.unwrap_or(0);
+                if sense.filemark {
+                    SgEvent::Filemark
+                } else if sense.ili && residual < 0 {
+                    SgEvent::Overlength {
+                        transferred: requested,
+                        length: requested + residual.unsigned_abs() as usize,
+                    }
+                } else if sense.ili {
+                    SgEvent::Data(requested.saturating_sub(residual as usize))
+                } else if sense.eom && transferred == 0 {
+                    SgEvent::EndOfMedium
+                } else {
+                    SgEvent::Data(transferred)
+                }
+            }
+            _ => SgEvent::Failed(*sense),
+        }
+    }
+}
+
+/// Logical position from READ POSITION.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub block: u64,
+    /// Beginning of partition.
+    pub bop: bool,
+    /// Past the early warning point before the end of partition.
+    pub eop: bool,
+}
+
+/// A tape drive driven with SCSI commands through the sg driver, so every
+/// read comes back with its sense data rather than a bare errno.
+pub struct SgDrive<B> {
+    backend: B,
+}
+
+impl<B: SgBackend> SgDrive<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    /// Issues `cdb`, returning the bytes transferred and, on CHECK
+    /// CONDITION, the decoded sense data.
+    fn command(&mut self, cdb: &[u8], data: &mut [u8]) -> io::Result<(usize, Option<SenseData>)> {
+        let mut sense = [0u8; SENSE_LENGTH];
+        let result = self.backend.execute(cdb, data, &mut sense)?;
+        let transferred = data.len().saturating_sub(result.residual);
+        match result.status {
+            STATUS_GOOD => Ok((transferred, None)),
+            STATUS_CHECK_CONDITION => {
+                let sense = SenseData::parse(&sense[..result.sense_length.min(SENSE_LENGTH)])
+                    .ok_or_else(|| io::Error::other("CHECK CONDITION without valid sense data"))?;
+                Ok((transferred, Some(sense)))
+            }
+            status => Err(io::Error::other(format!("SCSI status 0x{status:02X}"))),
+        }
+    }
+
+    /// Issues a command without data, failing on anything but a clean
+    /// completion.
+    fn control(&mut self, cdb: &[u8]) -> io::Result<()> {
+        match self.command(cdb, &mut [])? {
+            (_, None) => Ok(()),
+            (_, Some(sense))
+                if sense.key == KEY_RECOVERED_ERROR
+                    || (sense.key == KEY_NO_SENSE && !sense.filemark && !sense.eom) =>
+            {
+                Ok(())
+            }
+            (_, Some(sense)) => Err(io::Error::other(sense)),
+        }
+    }
+
+    /// Returns `None` when the drive is ready, else the sense data saying why not.
+    pub fn test_unit_ready(&mut self) -> io::Result<Option<SenseData>> {
+        Ok(self
+            .command(&[OP_TEST_UNIT_READY, 0, 0, 0, 0, 0], &mut [])?
+            .1)
+    }
+
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.control(&[OP_REWIND, 0, 0, 0, 0, 0])
+    }
+
+    /// Spaces over `count` filemarks, backwards if negative.
+    pub fn space_files(&mut self, count: i32) -> io::Result<()> {
+        self.space(SPACE_FILEMARKS, count)
+    }
+
+    /// Spaces over `count` blocks, backwards if negative.
+    pub fn space_blocks(&mut self, count: i32) -> io::Result<()> {
+        self.space(SPACE_BLOCKS, count)
+    }
+
+    fn space(&mut self, code: u8, count: i32) -> io::Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        let [_, high, mid, low] = count.to_be_bytes();
+        self.control(&[OP_SPACE_6, code, high, mid, low, 0])
+    }
+
+    /// The largest block the drive supports, from READ BLOCK LIMITS.
+    pub fn max_block_length(&mut self) -> io::Result<usize> {
+        let mut data = [0u8; BLOCK_LIMITS_LENGTH];
+        match self.command(&[OP_READ_BLOCK_LIMITS, 0, 0, 0, 0, 0], &mut data)? {
+            (BLOCK_LIMITS_LENGTH, None) => {
+                Ok(u32::from_be_bytes([0, data[1], data[2], data[3]]) as usize)
+            }
+            (_, Some(sense)) => Err(io::Error::other(sense)),
+            (length, None) => Err(io::Error::other(format!(
+                "READ BLOCK LIMITS returned {length} bytes"
+            ))),
+        }
+    }
+
+    pub fn position(&mut self) -> io::Result<Position> {
+        let mut data = [0u8; POSITION_LENGTH];
+        match self.command(&[OP_READ_POSITION, 0, 0, 0, 0, 0, 0, 0, 0, 0], &mut data)? {
+            (POSITION_LENGTH, None) => Ok(Position {
+                block: u64::from(u32::from_be_bytes([data[4], data[5], data[6], data[7]])),
+                bop: data[0] & 0x80 != 0,
+                eop: data[0] & 0x40 != 0,
+            }),
+            (_, Some(sense)) => Err(io::Error::other(sense)),
+            (length, None) => Err(io::Error::other(format!(
+                "READ POSITION returned {length} bytes"
+            ))),
+        }
+    }
+
+    /// Vendor, product and revision from standard INQUIRY data.
+    pub fn inquiry(&mut self) -> io::Result<DriveInfo> {
+        let mut data = [0u8; INQUIRY_LENGTH];
+        match self.command(&[OP_INQUIRY, 0, 0, 0, INQUIRY_LENGTH as u8, 0], &mut data)? {
+            (INQUIRY_LENGTH, None) => {
+                let text = |range: std::ops::Range<usize>| {
+                    let text = String::from_utf8_lossy(&data[range]).trim().to_owned();
+                    Some(text).filter(|text| !text.is_empty())
+                };
+                Ok(DriveInfo {
+                    vendor: text(8..16),
+                    product: text(16..32),
+                    revision: text(32..36),
+                    density: None,
+                })
+            }
+            (_, Some(sense)) => Err(io::Error::other(sense)),
+            (length, None) => Err(io::Error::other(format!("INQUIRY returned {length} bytes"))),
+        }
+    }
+
+    /// Density code from the MODE SENSE block descriptor, if the drive sends one.
+    pub fn density(&mut self) -> io::Result<Option<u8>> {
+        let mut data = [0u8; MODE_SENSE_LENGTH];
+        match self.command(
+            &[OP_MODE_SENSE_6, 0, 0, 0, MODE_SENSE_LENGTH as u8, 0],
+            &mut data,
+        )? {
+            (length, None) if length >= MODE_SENSE_LENGTH && data[3] >= 8 => Ok(Some(data[4])),
+            (_, None) => Ok(None),
+            (_, Some(sense)) => Err(io::Error::other(sense)),
+        }
+    }
+
+    /// Reads one variable-length block with READ(6).
+    pub fn read_event(&mut self, buf: &mut [u8]) -> io::Result<SgEvent> {
+        let requested = buf.len().min(MAX_TRANSFER);
+        let [_, high, mid, low] = (requested as u32).to_be_bytes();
+        let (transferred, sense) =
+            self.command(&[OP_READ_6, 0, high, mid, low, 0], &mut buf[..requested])?;
+        Ok(SgEvent::classify(requested, transferred, sense.as_ref()))
+    }
+
+    pub fn read_block(&mut self, buf: &mut [u8]) -> io::Result<TapeRead> {
+        match self.read_event(buf)? {
+            SgEvent::Data(n) => Ok(TapeRead::Data(n)),
+            SgEvent::Filemark => Ok(TapeRead::TapeMark),
+            SgEvent::BlankCheck | SgEvent::EndOfMedium => Ok(TapeRead::EndOfData),
+            SgEvent::NotReady(_) => Ok(TapeRead::NotReady),
+            SgEvent::Overlength { length, .. } => Ok(TapeRead::Overlength {
+                length: Some(length),
+            }),
+            SgEvent::MediumError { transferred, sense } => Ok(TapeRead::BadBlock {
+                length: transferred,
+                reason: sense.to_string(),
+            }),
+            SgEvent::Failed(sense) => Err(io::Error::other(sense)),
+        }
+    }
+}
+
+impl<B: SgBackend + Send> TapeSource for SgDrive<B> {
+    fn read_block(&mut self, buf: &mut [u8]) -> io::Result<TapeRead> {
+        SgDrive::read_block(self, buf)
+    }
+
+    fn is_tape_drive(&self) -> bool {
+        true
+    }
+
+    fn rewind(&mut self) -> io::Result<()> {
+        SgDrive::rewind(self)
+    }
+
+    fn space_files(&mut self, count: i32) -> io::Result<()> {
+        SgDrive::space_files(self, count)
+    }
+
+    fn space_blocks(&mut self, count: i32) -> io::Result<()> {
+        SgDrive::space_blocks(self, count)
+    }
+
+    fn block_limit(&mut self) -> Option<usize> {
+        self.max_block_length().ok().filter(|&length| length > 0)
+    }
+
+    fn drive_info(&mut self) -> DriveInfo {
+        DriveInfo {
+            density: self.density().ok().flatten(),
+            ..self.inquiry().unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod device {
+    use super::{CommandResult, SgBackend};
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::os::raw::{c_int, c_uchar, c_uint, c_ushort, c_void};
+    use std::path::Path;
+
+    const SG_DXFER_NONE: c_int = -1;
+    const SG_DXFER_FROM_DEV: c_int = -3;
+    /// Long enough for a rewind or a space over a full reel.
+    const TIMEOUT_MS: c_uint = 30 * 60 * 1000;
+    const DRIVER_SENSE: c_ushort = 0x08;
+
+    /// `struct sg_io_hdr` from scsi/sg.h.
+    #[repr(C)]
+    struct SgIoHdr {
+        interface_id: c_int,
+        dxfer_direction: c_int,
+        cmd_len: c_uchar,
+        mx_sb_len: c_uchar,
+        iovec_count: c_ushort,
+        dxfer_len: c_uint,
+        dxferp: *mut c_void,
+        cmdp: *const c_uchar,
+        sbp: *mut c_uchar,
+        timeout: c_uint,
+        flags: c_uint,
+        pack_id: c_int,
+        usr_ptr: *mut c_void,
+        status: c_uchar,
+        masked_status: c_uchar,
+        msg_status: c_uchar,
+        sb_len_wr: c_uchar,
+        host_status: c_ushort,
+        driver_status: c_ushort,
+        resid: c_int,
+        duration: c_uint,
+        info: c_uint,
+    }
+
+    nix::ioctl_readwrite_bad!(sg_io, 0x2285, SgIoHdr);
+    nix::ioctl_read_bad!(sg_get_version_num, 0x2282, c_int);
+
+    /// An open SCSI generic device such as `/dev/sg1`.
+    pub struct SgDevice {
+        file: File,
+    }
+
+    impl SgDevice {
+        /// Opens `path` and checks it takes SG_IO; returns `None` for anything
+        /// that is not a SCSI device.
+        pub fn open(path: &Path) -> io::Result<Option<Self>> {
+            let file = OpenOptions::new().read(true).write(true).open(path)?;
+            let mut version: c_int = 0;
+            // SAFETY: the fd is open and SG_GET_VERSION_NUM writes a single int.
+            match unsafe { sg_get_version_num(file.as_raw_fd(), &mut version) } {
+                Ok(_) => Ok(Some(Self { file })),
+                Err(nix::errno::Errno::ENOTTY) => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        }
+    }
+
+    impl SgBackend for SgDevice {
+        fn execute(
+            &mut self,
+            cdb: &[u8],
+            data: &mut [u8],
+            sense: &mut [u8],
+        ) -> io::Result<CommandResult> {
+            let mut header = SgIoHdr {
+                interface_id: c_int::from(b'S'),
+                dxfer_direction: if data.is_empty() {
+                    SG_DXFER_NONE
+                } else {
+                    SG_DXFER_FROM_DEV
+                },
+                cmd_len: cdb.len() as c_uchar,
+                mx_sb_len: sense.len().min(255) as c_uchar,
+                iovec_count: 0,
+                dxfer_len: data.len() as c_uint,
+                dxferp: data.as_mut_ptr().cast(),
+                cmdp: cdb.as_ptr(),
+                sbp: sense.as_mut_ptr(),
+                timeout: TIMEOUT_MS,
+                flags: 0,
+                pack_id: 0,
+                usr_ptr: std::ptr::null_mut(),
+                status: 0,
+                masked_status: 0,
+                msg_status: 0,
+                sb_len_wr: 0,
+                host_status: 0,
+                driver_status: 0,
+                resid: 0,
+                duration: 0,
+                info: 0,
+            };
+            // SAFETY: the fd is open, `header` matches the kernel's struct
+            // sg_io_hdr and its buffers outlive the synchronous call.
+            unsafe { sg_io(self.file.as_raw_fd(), &mut header) }?;
+
+            if header.host_status != 0 {
+                return Err(io::Error::other(format!(
+                    "SCSI host status 0x{:02X}",
+                    header.host_status
+                )));
+            }
+            if header.driver_status & 0x0F & !DRIVER_SENSE != 0 {
+                return Err(io::Error::other(format!(
+                    "SCSI driver status 0x{:02X}",
+                    header.driver_status
+                )));
+            }
+            Ok(CommandResult {
+                status: header.status,
+                residual: header.resid.max(0) as usize,
+                sense_length: usize::from(header.sb_len_wr),
+            })
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use device::SgDevice;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockObject, MockTape};
+
+    #[test]
+    fn decodes_fixed_and_descriptor_sense() {
+        let mut fixed = [0u8; 18];
+        fixed[0] = 0xF0;
+        fixed[2] = 0x20 | KEY_NO_SENSE;
+        fixed[3..7].copy_from_slice(&(-48i32).to_be_bytes());
+        fixed[7] = 10;
+        let sense = SenseData::parse(&fixed).unwrap();
+        assert!(sense.ili && !sense.filemark);
+        assert_eq!(sense.information, Some(-48));
+        assert_eq!(
+            SgEvent::classify(80, 80, Some(&sense)),
+            SgEvent::Overlength {
+                transferred: 80,
+                length: 128
+            }
+        );
+
+        let descriptor = [
+            0x72, 0x03, 0x11, 0x00, 0, 0, 0, 16, // header
+            0x00, 0x0A, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0x07, // information
+            0x04, 0x02, 0x00, 0x40, // stream commands
+        ];
+        let sense = SenseData::parse(&descriptor).unwrap();
+        assert_eq!(
+            (sense.key, sense.information, sense.eom),
+            (3, Some(7), true)
+        );
+        assert_eq!(
+            sense.to_string(),
+            "MEDIUM ERROR, ASC/ASCQ 11/00 (unrecovered read error) EOM"
+        );
+        assert_eq!(
+            SgEvent::classify(80, 12, Some(&sense)),
+            SgEvent::MediumError {
+                transferred: 12,
+                sense
+            }
+        );
+        assert_eq!(SenseData::parse(&[0x7F, 0, 0]), None);
+    }
+
+    #[test]
+    fn reads_through_a_simulated_drive() {
+        let mut drive = SgDrive::new(MockTape::new(vec![
+            MockObject::Record(vec![1; 80]),
+            MockObject::Record(vec![2; 200]),
+            MockObject::TapeMark,
+            MockObject::Error(libc::EIO),
+            MockObject::Record(vec![3; 4]),
+            MockObject::TapeMark,
+        ]));
+        let mut buf = [0u8; 128];
+
+        assert_eq!(drive.test_unit_ready().unwrap(), None);
+        assert_eq!(drive.max_block_length().unwrap(), MAX_TRANSFER);
+        assert_eq!(
+            TapeSource::drive_info(&mut drive),
+            DriveInfo {
+                vendor: Some("MOCK".into()),
+                product: Some("VIRTUAL TAPE".into()),
+                revision: Some("1.0".into()),
+                density: Some(0),
+            }
+        );
+        assert!(drive.position().unwrap().bop);
+        assert_eq!(drive.read_event(&mut buf).unwrap(), SgEvent::Data(80));
+        assert_eq!(
+            drive.read_event(&mut buf).unwrap(),
+            SgEvent::Overlength {
+                transferred: 128,
+                length: 200
+            }
+        );
+        assert_eq!(buf[127], 2);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::TapeMark);
+        assert!(matches!(
+            drive.read_block(&mut buf).unwrap(),
+            TapeRead::BadBlock { length: 0, reason } if reason.starts_with("MEDIUM ERROR, ASC/ASCQ 11/00")
+        ));
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::Data(4));
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::TapeMark);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::EndOfData);
+
+        drive.space_files(-2).unwrap();
+        assert_eq!(drive.position().unwrap().block, 2);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::TapeMark);
+        drive.space_blocks(1).unwrap();
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::Data(4));
+        assert!(drive.space_blocks(-3).is_err());
+        assert_eq!(drive.position().unwrap().block, 2);
+        drive.rewind().unwrap();
+        let err = drive.space_files(3).unwrap_err();
+        assert!(err.to_string().starts_with("BLANK CHECK"));
+        assert_eq!(drive.position().unwrap().block, 6);
+
+        drive.backend.online = false;
+        assert!(drive.test_unit_ready().unwrap().is_some());
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::NotReady);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::reader::{DriveInfo, TapeRead, TapeSource};
+use std::io;
+use std::os::raw::{c_int, c_long, c_short};
+
+// Generic status bits in mtget.mt_gstat (linux/mtio.h).
+const GMT_EOF: c_long = 0x8000_0000;
+const GMT_BOT: c_long = 0x4000_0000;
+const GMT_EOT: c_long = 0x2000_0000;
+const GMT_EOD: c_long = 0x0800_0000;
+const GMT_WR_PROT: c_long = 0x0400_0000;
+const GMT_ONLINE: c_long = 0x0100_0000;
+const GMT_DR_OPEN: c_long = 0x0004_0000;
+
+// Fields packed into mtget.mt_dsreg and mtget.mt_erreg by the st driver.
+const MT_ST_BLKSIZE_MASK: c_long = 0x00FF_FFFF;
+const MT_ST_DENSITY_SHIFT: u32 = 24;
+const MT_ST_SOFTERR_MASK: c_long = 0xFFFF;
+
+/// `struct mtget` from linux/mtio.h.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MtGet {
+    pub mt_type: c_long,
+    pub mt_resid: c_long,
+    pub mt_dsreg: c_long,
+    pub mt_gstat: c_long,
+    pub mt_erreg: c_long,
+    pub mt_fileno: c_int,
+    pub mt_blkno: c_int,
+}
+
+/// `struct mtop` from linux/mtio.h.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MtOpRequest {
+    pub mt_op: c_short,
+    pub mt_count: c_int,
+}
+
+/// The MTIOCTOP operations rtimage issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtOp {
+    ForwardFiles,
+    BackwardFiles,
+    ForwardRecords,
+    BackwardRecords,
+    Rewind,
+}
+
+impl MtOp {
+    pub fn code(self) -> c_short {
+        match self {
+            MtOp::ForwardFiles => 1,
+            MtOp::BackwardFiles => 2,
+            MtOp::ForwardRecords => 3,
+            MtOp::BackwardRecords => 4,
+            MtOp::Rewind => 6,
+        }
+    }
+}
+
+/// The system calls the st layer is built on: `read(2)` and the three MTIO
+/// ioctls. Implemented by `StDevice` for real drives and by a mock in tests.
+pub trait StBackend {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+    fn get_status(&mut self) -> io::Result<MtGet>;
+    fn get_position(&mut self) -> io::Result<u64>;
+    fn operation(&mut self, op: MtOp, count: i32) -> io::Result<()>;
+
+    /// Vendor, product and revision, where the backend can find them.
+    fn identify(&self) -> DriveInfo {
+        DriveInfo::default()
+    }
+}
+
+/// Drive state decoded from `MTIOCGET`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriveStatus {
+    pub file_number: i32,
+    pub block_number: i32,
+    pub density: u8,
+    /// Fixed block size, or 0 in variable block mode.
+    pub block_size: u32,
+    pub soft_errors: u16,
+    pub bot: bool,
+    pub eof: bool,
+    pub eot: bool,
+    pub eod: bool,
+    pub write_protected: bool,
+    pub online: bool,
+    pub door_open: bool,
+}
+
+impl DriveStatus {
+    pub fn from_raw(raw: &MtGet) -> Self {
+        let flag = |bit: c_long| raw.mt_gstat & bit != 0;
+        Self {
+            file_number: raw.mt_fileno,
+            block_number: raw.mt_blkno,
+            density: ((raw.mt_dsreg as u64 >> MT_ST_DENSITY_SHIFT) & 0xFF) as u8,
+            block_size: (raw.mt_dsreg & MT_ST_BLKSIZE_MASK) as u32,
+            soft_errors: (raw.mt_erreg & MT_ST_SOFTERR_MASK) as u16,
+            bot: flag(GMT_BOT),
+            eof: flag(GMT_EOF),
+            eot: flag(GMT_EOT),
+            eod: flag(GMT_EOD),
+            write_protected: flag(GMT_WR_PROT),
+            online: flag(GMT_ONLINE),
+            door_open: flag(GMT_DR_OPEN),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let flags: Vec<&str> = [
+            (self.online, "ONLINE"),
+            (!self.online, "OFFLINE"),
+            (self.door_open, "DR_OPEN"),
+            (self.bot, "BOT"),
+            (self.eof, "EOF"),
+            (self.eot, "EOT"),
+            (self.eod, "EOD"),
+            (self.write_protected, "WR_PROT"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect();
+        let block_size = if self.block_size == 0 {
+            "variable".to_string()
+        } else {
+            format!("{} bytes", self.block_size)
+        };
+        format!(
+            "{}, file {}, block {}, density 0x{:02X}, block size {}",
+            flags.join(" "),
+            self.file_number,
+            self.block_number,
+            self.density,
+            block_size
+        )
+    }
+}
+
+/// A tape drive behind the Linux st driver. Status queries settle what a bare
+/// `read(2)` leaves ambiguous: whether a 0-byte read was a tape mark, the end
+/// of recorded data or a drive that is not ready.
+pub struct StDrive<B> {
+    backend: B,
+}
+
+impl<B: StBackend> StDrive<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    pub fn status(&mut self) -> io::Result<DriveStatus> {
+        Ok(DriveStatus::from_raw(&self.backend.get_status()?))
+    }
+
+    /// Logical block address reported by `MTIOCPOS`.
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.backend.get_position()
+    }
+
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.backend.operation(MtOp::Rewind, 1)
+    }
+
+    /// Spaces over `count` tape marks, backwards if negative, leaving the tape
+    /// on the far side of the last one.
+    pub fn space_files(&mut self, count: i32) -> io::Result<()> {
+        match count {
+            0 => Ok(()),
+            1.. => self.backend.operation(MtOp::ForwardFiles, count),
+            _ => self.backend.operation(MtOp::BackwardFiles, -count),
+        }
+    }
+
+    /// Spaces over `count` blocks, backwards if negative.
+    pub fn space_blocks(&mut self, count: i32) -> io::Result<()> {
+        match count {
+            0 => Ok(()),
+            1.. => self.backend.operation(MtOp::ForwardRecords, count),
+            _ => self.backend.operation(MtOp::BackwardRecords, -count),
+        }
+    }
+
+    pub fn read_block(&mut self, buf: &mut [u8]) -> io::Result<TapeRead> {
+        match self.backend.read(buf) {
+            Ok(0) => {
+                let status = self.status()?;
+                Ok(if !status.online {
+                    TapeRead::NotReady
+                } else if status.eod {
+                    TapeRead::EndOfData
+                } else {
+                    TapeRead::TapeMark
+                })
+            }
+            Ok(n) => Ok(TapeRead::Data(n)),
+            Err(err) => {
+                // The st driver reports reading past the end of data as EIO.
+                let Ok(status) = self.status() else {
+                    return Err(err);
+                };
+                if !status.online {
+                    Ok(TapeRead::NotReady)
+                } else if err.raw_os_error() == Some(libc::ENOMEM) {
+                    // st skips a block too long for the buffer and says ENOMEM.
+                    Ok(TapeRead::Overlength { length: None })
+                } else if err.raw_os_error() == Some(libc::EIO) && status.eod {
+                    Ok(TapeRead::EndOfData)
+                } else if err.raw_os_error() == Some(libc::EIO) {
+                    // Anything else st reports as EIO is a medium error, including
+                    // past the early-warning marker, where a reel still holds data.
+                    Ok(TapeRead::BadBlock {
+                        length: 0,
+                        reason: err.to_string(),
+                    })
+                } else {
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+impl<B: StBackend + Send> TapeSource for StDrive<B> {
+    fn read_block(&mut self, buf: &mut [u8]) -> io::Result<TapeRead> {
+        StDrive::read_block(self, buf)
+    }
+
+    fn is_tape_drive(&self) -> bool {
+        true
+    }
+
+    fn rewind(&mut self) -> io::Result<()> {
+        StDrive::rewind(self)
+    }
+
+    fn space_files(&mut self, count: i32) -> io::Result<()> {
+        StDrive::space_files(self, count)
+    }
+
+    fn space_blocks(&mut self, count: i32) -> io::Result<()> {
+        StDrive::space_blocks(self, count)
+    }
+
+    /// MTIOCGET only knows the block size in fixed block mode.
+    fn block_limit(&mut self) -> Option<usize> {
+        self.fixed_block_size()
+    }
+
+    fn fixed_block_size(&mut self) -> Option<usize> {
+        let status = self.status().ok()?;
+        (status.block_size > 0).then_some(status.block_size as usize)
+    }
+
+    fn drive_info(&mut self) -> DriveInfo {
+        DriveInfo {
+            density: self.status().ok().map(|status| status.density),
+            ..self.backend.identify()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod device {
+    use super::{MtGet, MtOp, MtOpRequest, StBackend};
+    use crate::reader::DriveInfo;
+    use std::fs::{self, File};
+    use std::io::{self, Read};
+    use std::os::fd::AsRawFd;
+    use std::os::raw::c_long;
+    use std::path::{Path, PathBuf};
+
+    nix::ioctl_write_ptr!(mtioctop, b'm', 1, MtOpRequest);
+    nix::ioctl_read!(mtiocget, b'm', 2, MtGet);
+    nix::ioctl_read!(mtiocpos, b'm', 3, c_long);
+
+    /// An open st character device such as `/dev/nst0`.
+    pub struct StDevice {
+        file: File,
+        /// The drive's SCSI device directory in sysfs.
+        sysfs: Option<PathBuf>,
+    }
+
+    impl StDevice {
+        /// Opens `path` and checks it answers `MTIOCGET`; returns `None` for
+        /// plain files, pipes and anything else that is not an st device.
+        pub fn open(path: &Path) -> io::Result<Option<Self>> {
+            let sysfs = fs::canonicalize(path).ok().and_then(|path| {
+                let name = path.file_name()?.to_str()?.to_owned();
+                Some(Path::new("/sys/class/scsi_tape").join(name).join("device"))
+            });
+            let mut device = Self {
+                file: File::open(path)?,
+                sysfs,
+            };
+            match device.get_status() {
+                Ok(_) => Ok(Some(device)),
+                Err(err) if err.raw_os_error() == Some(libc::ENOTTY) => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+    }
+
+    impl StBackend for StDevice {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.file.read(buf)
+        }
+
+        fn get_status(&mut self) -> io::Result<MtGet> {
+            let mut status = MtGet::default();
+            // SAFETY: the fd is open and `status` matches the kernel's struct mtget.
+            unsafe { mtiocget(self.file.as_raw_fd(), &mut status) }?;
+            Ok(status)
+        }
+
+        fn get_position(&mut self) -> io::Result<u64> {
+            let mut block: c_long = 0;
+            // SAFETY: the fd is open and struct mtpos is a single long.
+            unsafe { mtiocpos(self.file.as_raw_fd(), &mut block) }?;
+            Ok(block as u64)
+        }
+
+        fn operation(&mut self, op: MtOp, count: i32) -> io::Result<()> {
+            let request = MtOpRequest {
+                mt_op: op.code(),
+                mt_count: count,
+            };
+            // SAFETY: the fd is open and `request` matches the kernel's struct mtop.
+            unsafe { mtioctop(self.file.as_raw_fd(), &request) }?;
+            Ok(())
+        }
+
+        /// The st driver has no inquiry ioctl, but sysfs keeps the strings.
+        fn identify(&self) -> DriveInfo {
+            let read = |attribute: &str| {
+                let value = fs::read_to_string(self.sysfs.as_ref()?.join(attribute)).ok()?;
+                Some(value.trim().to_owned()).filter(|value| !value.is_empty())
+            };
+            DriveInfo {
+                vendor: read("vendor"),
+                product: read("model"),
+                revision: read("rev"),
+                density: None,
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use device::StDevice;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockObject, MockTape};
+
+    fn drive(objects: Vec<MockObject>) -> StDrive<MockTape> {
+        StDrive::new(MockTape::new(objects))
+    }
+
+    #[test]
+    fn distinguishes_tape_marks_end_of_data_and_not_ready() {
+        let mut drive = drive(vec![
+            MockObject::Record(vec![1; 80]),
+            MockObject::TapeMark,
+            MockObject::TapeMark,
+        ]);
+        let mut buf = [0u8; 128];
+
+        assert!(drive.status().unwrap().bot);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::Data(80));
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::TapeMark);
+        let status = drive.status().unwrap();
+        assert!(status.eof && !status.bot);
+        assert_eq!((status.file_number, status.block_number), (1, 0));
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::TapeMark);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::EndOfData);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::EndOfData);
+        assert_eq!(drive.position().unwrap(), 3);
+
+        drive.backend.online = false;
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::NotReady);
+
+        let mut near_end = StDrive::new(MockTape::new(vec![
+            MockObject::Error(libc::EIO),
+            MockObject::Record(vec![1; 80]),
+        ]));
+        near_end.backend.past_early_warning = true;
+        assert!(matches!(
+            near_end.read_block(&mut buf).unwrap(),
+            TapeRead::BadBlock { .. }
+        ));
+        assert_eq!(near_end.read_block(&mut buf).unwrap(), TapeRead::Data(80));
+    }
+
+    #[test]
+    fn positions_the_tape() {
+        let mut drive = drive(vec![
+            MockObject::Record(vec![1; 4]),
+            MockObject::TapeMark,
+            MockObject::Record(vec![2; 4]),
+            MockObject::Record(vec![3; 4]),
+            MockObject::TapeMark,
+            MockObject::Error(libc::EIO),
+            MockObject::Record(vec![4; 4]),
+        ]);
+        let mut buf = [0u8; 16];
+
+        drive.space_files(2).unwrap();
+        let status = drive.status().unwrap();
+        assert_eq!((status.file_number, status.block_number), (2, 0));
+        assert!(matches!(
+            drive.read_block(&mut buf).unwrap(),
+            TapeRead::BadBlock { length: 0, .. }
+        ));
+        drive.space_blocks(-1).unwrap();
+        assert_eq!(drive.position().unwrap(), 5);
+
+        drive.space_files(-2).unwrap();
+        assert_eq!(drive.position().unwrap(), 1);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::TapeMark);
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::Data(4));
+        assert_eq!(buf[0], 2);
+
+        drive.rewind().unwrap();
+        assert_eq!(drive.read_block(&mut buf).unwrap(), TapeRead::Data(4));
+        assert_eq!(buf[0], 1);
+        assert!(
+            drive
+                .status()
+                .unwrap()
+                .describe()
+                .starts_with("ONLINE, file 0, block 1")
+        );
+        assert_eq!(
+            drive.backend.operations,
+            vec![
+                (MtOp::ForwardFiles, 2),
+                (MtOp::BackwardRecords, 1),
+                (MtOp::BackwardFiles, 2),
+                (MtOp::Rewind, 1)
+            ]
+        );
+    }
+}
@@ -8,6 +8,15 @@ pub fn make_output_name(src: &str) -> String {
     }
 }
 
+/// Path next to the output image, with `suffix` in place of its `.tap`.
+pub fn sibling_path(output: &str, suffix: &str) -> String {
+    format!(
+        "{}{}",
+        output.strip_suffix(".tap").unwrap_or(output),
+        suffix
+    )
+}
+
 pub fn make_input_name(src: &str) -> Option<String> {
     if src == "-" {
         return None;
@@ -38,15 +47,24 @@ pub fn device_token_candidates(input: &Option<String>) -> Vec<String> {
     }
 
     let mut tokens = vec![lower.clone()];
-    if let Some(stripped) = lower.strip_prefix('n') {
-        if !stripped.is_empty() {
-            tokens.push(stripped.to_string());
-        }
+    if let Some(stripped) = lower.strip_prefix('n')
+        && !stripped.is_empty()
+    {
+        tokens.push(stripped.to_string());
     }
 
     tokens
 }
 
+/// Whether `path` names a SCSI generic device such as `/dev/sg1`.
+pub fn is_sg_device(path: &str) -> bool {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("sg"))
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,6 +73,7 @@ mod tests {
     fn test_make_output_name() {
         assert_eq!(make_output_name("test"), "test.tap");
         assert_eq!(make_output_name("test.tap"), "test.tap");
+        assert_eq!(sibling_path("test.tap", ".pass2.tap"), "test.pass2.tap");
     }
 
     #[test]
@@ -78,4 +97,12 @@ mod tests {
         );
         assert!(device_token_candidates(&Some("/dev/".to_string())).is_empty());
     }
+
+    #[test]
+    fn test_is_sg_device() {
+        assert!(is_sg_device("/dev/sg1"));
+        assert!(!is_sg_device("/dev/nst0"));
+        assert!(!is_sg_device("/dev/sg"));
+        assert!(!is_sg_device("./sgtape.bin"));
+    }
 }
@@ -201,10 +201,10 @@ pub fn summarize_file_records(records: &[AnalyzedRecord]) -> Option<TapeSummary>
         add_signatures_to_summary(&mut summary, &record.signatures);
     }
 
-    if !data_lengths.is_empty() {
-        if let Some(common) = common_block_size(&data_lengths) {
-            summary.add_detail(format!("Predominant data block size: {common} bytes"));
-        }
+    if !data_lengths.is_empty()
+        && let Some(common) = common_block_size(&data_lengths)
+    {
+        summary.add_detail(format!("Predominant data block size: {common} bytes"));
     }
 
     if summary.is_empty() {
@@ -237,19 +237,18 @@ pub fn summarize_tape(files: &[TapeFile]) -> Option<TapeSummary> {
         summary.add_platform("ANSI/ISO Standard Labeled Tape");
     }
 
-    if summary.formats.is_empty() {
-        if let Some(record) = files
+    if summary.formats.is_empty()
+        && let Some(record) = files
             .iter()
             .flat_map(|file| file.records.iter())
             .find(|record| record.label.is_none())
-        {
-            add_signatures_to_summary(&mut summary, &record.signatures);
-            if summary.formats.is_empty() {
-                summary.add_format(format!(
-                    "Content appears {}",
-                    encoding_label(record.encoding)
-                ));
-            }
+    {
+        add_signatures_to_summary(&mut summary, &record.signatures);
+        if summary.formats.is_empty() {
+            summary.add_format(format!(
+                "Content appears {}",
+                encoding_label(record.encoding)
+            ));
         }
     }
 
@@ -262,10 +261,10 @@ pub fn summarize_tape(files: &[TapeFile]) -> Option<TapeSummary> {
 
 pub fn extract_backup_command(records: &[Vec<u8>]) -> Option<String> {
     for record in records {
-        if record.len() == LABEL_LENGTH {
-            if let Some(command) = parse_label_for_command(record) {
-                return Some(command);
-            }
+        if record.len() == LABEL_LENGTH
+            && let Some(command) = parse_label_for_command(record)
+        {
+            return Some(command);
         }
     }
 
@@ -452,7 +451,7 @@ mod tests {
         let cmd = b"BACKUP/IMAGE DUA0: DUA1:/SAVE";
         uhl[4..4 + cmd.len()].copy_from_slice(cmd);
 
-        let command = extract_backup_command(&vec![uhl]).expect("command found");
+        let command = extract_backup_command(&[uhl]).expect("command found");
         assert!(command.starts_with("BACKUP/IMAGE"));
         assert!(command.contains("DUA1"));
     }
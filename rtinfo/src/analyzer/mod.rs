@@ -5,8 +5,13 @@ pub mod reader;
 pub mod signature;
 
 use indexmap::IndexSet;
-use reader::{SimhTapeBlock, SimhTapeMark, SimhTapeReader, SimhTapeRecord};
-use std::io::{self, Cursor};
+use reader::{
+    E11TapeReader, P7bParity, P7bTapeReader, SimhError, SimhResync, SimhTapeBlock,
+    SimhTapeBlockRef, SimhTapeMark, SimhTapeRecordHeader, SimhTapeSliceReader,
+    SimhTapeStreamReader, TAPE_DESCRIPTION_CLASS, TapeBlockReader, TapeDescription,
+    TapeImageFormat,
+};
+use std::io::{self, Cursor, Read};
 
 pub use formats::{
     AnsiLabel, TapeSummary, decode_ansi_label, extract_backup_command, summarize_file_records,
@@ -16,22 +21,17 @@ pub use signature::{RecordSignature, SignatureDetector};
 
 const MAX_COMMAND_RECORDS: usize = 20;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum RecordEncoding {
     Empty,
     Ascii,
     MostlyAscii,
     Ansi,
     MostlyAnsi,
+    #[default]
     Binary,
 }
 
-impl Default for RecordEncoding {
-    fn default() -> Self {
-        RecordEncoding::Binary
-    }
-}
-
 #[derive(Debug, Default, Clone)]
 pub struct RecordPreview {
     pub hex_lines: Vec<String>,
@@ -73,6 +73,8 @@ pub struct TapeTotals {
 pub struct TapeAnalysis {
     pub filesize: Option<u64>,
     pub files: Vec<TapeFile>,
+    /// Capture metadata decoded from class E records, kept out of the files.
+    pub descriptions: Vec<TapeDescription>,
     pub totals: TapeTotals,
     pub warnings: Vec<String>,
     pub tape_summary: Option<TapeSummary>,
@@ -92,140 +94,290 @@ impl TapeAnalysis {
     }
 }
 
-pub fn analyze_bytes(bytes: &[u8]) -> TapeAnalysis {
-    let mut analysis = TapeAnalysis::default();
-    analysis.filesize = Some(bytes.len() as u64);
-    let mut reader = SimhTapeReader::new(Cursor::new(bytes));
-    let detector = SignatureDetector::default();
-    let mut current_file: Option<TapeFile> = None;
-    let mut command_records: Vec<Vec<u8>> = Vec::new();
+/// With `recover`, damaged spans are skipped and reported instead of ending
+/// the analysis.
+pub fn analyze_bytes(bytes: &[u8], recover: bool) -> TapeAnalysis {
+    let mut reader = SimhTapeSliceReader::new(bytes).with_recovery(recover);
+    let mut analyzer = TapeAnalyzer::default();
 
     loop {
         match reader.next_block() {
-            Ok(SimhTapeBlock::Record(record)) => {
-                let SimhTapeRecord { header, data } = record;
-                analysis.totals.records += 1;
-                analysis.totals.data_bytes += header.length as u64;
-
-                let file = current_file.get_or_insert_with(|| {
-                    analysis.totals.files += 1;
-                    TapeFile {
-                        file_index: analysis.totals.files,
-                        ..Default::default()
-                    }
-                });
-
-                if command_records.len() < MAX_COMMAND_RECORDS {
-                    command_records.push(data.clone());
+            Ok(SimhTapeBlockRef::Record(record)) => analyzer.record(&record.header, record.data),
+            Ok(SimhTapeBlockRef::TapeMark { offset, kind }) => {
+                if !analyzer.tape_mark(offset, kind) {
+                    break;
                 }
+            }
+            Ok(SimhTapeBlockRef::Resync(resync)) => analyzer.resync(&resync),
+            Ok(SimhTapeBlockRef::EndOfStream) => break,
+            Err(err) => {
+                analyzer.reader_error(&err);
+                break;
+            }
+        }
+    }
 
-                let encoding = classify_encoding(&data);
-                let preview = build_preview(&data, encoding);
-                let label = decode_ansi_label(&data);
-                let signatures = detector.detect(&data, header.length);
-
-                let mut analyzed = AnalyzedRecord {
-                    record_index: file.records.len() + 1,
-                    offset: header.offset,
-                    length: header.length,
-                    class: header.class,
-                    encoding,
-                    label,
-                    signatures,
-                    warnings: Vec::new(),
-                    preview,
-                    trailing_length: header.trailing_length,
-                };
-
-                if header.trailing_length != Some(header.length) {
-                    analyzed
-                        .warnings
-                        .push("Trailing length mismatch".to_string());
+    let mut analysis = analyzer.finish();
+    analysis.filesize = Some(bytes.len() as u64);
+    analysis
+}
+
+/// Analyses a non-seekable SIMH source such as stdin without buffering it in memory.
+/// Damaged data cannot be skipped here, so the first reader error ends the analysis.
+pub fn analyze_stream<R: Read>(source: R) -> TapeAnalysis {
+    let mut reader = SimhTapeStreamReader::new(source);
+    let mut analysis = analyze_blocks(&mut reader);
+    analysis.filesize = Some(reader.position());
+    analysis
+}
+
+/// Analyses an in-memory image of any supported format. `recover` applies to
+/// the SIMH and E11 readers, which can resynchronise past damaged data.
+pub fn analyze_image(bytes: &[u8], format: TapeImageFormat, recover: bool) -> TapeAnalysis {
+    let mut analysis = match format {
+        TapeImageFormat::Simh => return analyze_bytes(bytes, recover),
+        TapeImageFormat::E11 => {
+            analyze_blocks(&mut E11TapeReader::new(Cursor::new(bytes)).with_recovery(recover))
+        }
+        TapeImageFormat::P7b => analyze_p7b(bytes),
+        _ => analyze_blocks(format.open_reader(Cursor::new(bytes)).as_mut()),
+    };
+    analysis.filesize = Some(bytes.len() as u64);
+    analysis
+}
+
+/// Analyses the blocks of any reader; the first reader error ends the analysis.
+pub fn analyze_blocks(reader: &mut dyn TapeBlockReader) -> TapeAnalysis {
+    let mut analyzer = TapeAnalyzer::default();
+
+    loop {
+        match reader.next_block() {
+            Ok(SimhTapeBlock::Record(record)) => analyzer.record(&record.header, &record.data),
+            Ok(SimhTapeBlock::TapeMark { offset, kind }) => {
+                if !analyzer.tape_mark(offset, kind) {
+                    break;
                 }
+            }
+            Ok(SimhTapeBlock::Resync(resync)) => analyzer.resync(&resync),
+            Ok(SimhTapeBlock::EndOfStream | SimhTapeBlock::BeginningOfTape) => break,
+            Err(err) => {
+                analyzer.reader_error(&err);
+                break;
+            }
+        }
+    }
 
-                match header.class {
-                    0 => {}
-                    0x1..=0x6 => analyzed
-                        .warnings
-                        .push(format!("SIMH private data class 0x{:X}", header.class)),
-                    0x8 => analyzed
-                        .warnings
-                        .push("SIMH class 8 (bad data record)".to_string()),
-                    0x9..=0xD => analyzed
-                        .warnings
-                        .push(format!("SIMH reserved data class 0x{:X}", header.class)),
-                    0xE => analyzed
-                        .warnings
-                        .push("SIMH tape description record (class E)".to_string()),
-                    _ => analyzed
-                        .warnings
-                        .push(format!("SIMH unknown data class 0x{:X}", header.class)),
+    analyzer.finish()
+}
+
+/// Analyses a SIMH P7B 7-track image, flagging characters whose parity bit is wrong.
+pub fn analyze_p7b<R: Read>(source: R) -> TapeAnalysis {
+    let mut reader = P7bTapeReader::new(source);
+    let mut analyzer = TapeAnalyzer::default();
+
+    loop {
+        match reader.next_block() {
+            Ok(SimhTapeBlock::Record(record)) => {
+                analyzer.record(&record.header, &record.data);
+                if let Some(parity) = reader.record_parity() {
+                    analyzer.parity_errors(parity, reader.parity_errors());
+                }
+            }
+            Ok(SimhTapeBlock::TapeMark { offset, kind }) => {
+                if !analyzer.tape_mark(offset, kind) {
+                    break;
                 }
+            }
+            Ok(SimhTapeBlock::Resync(resync)) => analyzer.resync(&resync),
+            Ok(SimhTapeBlock::EndOfStream | SimhTapeBlock::BeginningOfTape) => break,
+            Err(err) => {
+                analyzer.reader_error(&err);
+                break;
+            }
+        }
+    }
+
+    let mut analysis = analyzer.finish();
+    analysis.filesize = Some(reader.position());
+    analysis
+}
 
-                file.data_bytes += header.length as u64;
-                file.records.push(analyzed);
+#[derive(Default)]
+struct TapeAnalyzer {
+    analysis: TapeAnalysis,
+    detector: SignatureDetector,
+    current_file: Option<TapeFile>,
+    command_records: Vec<Vec<u8>>,
+}
+
+impl TapeAnalyzer {
+    fn record(&mut self, header: &SimhTapeRecordHeader, data: &[u8]) {
+        if header.class == TAPE_DESCRIPTION_CLASS
+            && let Some(description) = TapeDescription::parse(data)
+        {
+            self.analysis.descriptions.push(description);
+            return;
+        }
+
+        let analysis = &mut self.analysis;
+        analysis.totals.records += 1;
+        analysis.totals.data_bytes += header.length as u64;
+
+        let file = self.current_file.get_or_insert_with(|| {
+            analysis.totals.files += 1;
+            TapeFile {
+                file_index: analysis.totals.files,
+                ..Default::default()
             }
-            Ok(SimhTapeBlock::TapeMark { kind, offset }) => {
-                match kind {
-                    SimhTapeMark::Single | SimhTapeMark::Double => {
-                        if let Some(mut file) = current_file.take() {
-                            if matches!(kind, SimhTapeMark::Double) {
-                                file.tape_mark_warning =
-                                    Some("Double tape mark encountered".to_string());
-                            }
-                            push_completed_file(&mut analysis, file);
-                        } else if matches!(kind, SimhTapeMark::Double) {
-                            analysis
-                                .warnings
-                                .push("Double tape mark observed outside of a file".to_string());
-                        }
-                    }
-                    SimhTapeMark::EndOfTape => {
-                        if let Some(file) = current_file.take() {
-                            push_completed_file(&mut analysis, file);
-                        }
-                        analysis.end_of_tape_offset = Some(offset);
-                        break;
+        });
+
+        if self.command_records.len() < MAX_COMMAND_RECORDS {
+            self.command_records.push(data.to_vec());
+        }
+
+        let encoding = classify_encoding(data);
+        let preview = build_preview(data, encoding);
+        let label = decode_ansi_label(data);
+        let signatures = self.detector.detect(data, header.length);
+
+        let mut analyzed = AnalyzedRecord {
+            record_index: file.records.len() + 1,
+            offset: header.offset,
+            length: header.length,
+            class: header.class,
+            encoding,
+            label,
+            signatures,
+            warnings: Vec::new(),
+            preview,
+            trailing_length: header.trailing_length,
+        };
+
+        if header
+            .trailing_length
+            .is_some_and(|trailing| trailing != header.length)
+        {
+            analyzed
+                .warnings
+                .push("Trailing length mismatch".to_string());
+        }
+
+        match header.class {
+            0 => {}
+            0x1..=0x6 => analyzed
+                .warnings
+                .push(format!("SIMH private data class 0x{:X}", header.class)),
+            0x8 => analyzed
+                .warnings
+                .push("SIMH class 8 (bad data record)".to_string()),
+            0x9..=0xD => analyzed
+                .warnings
+                .push(format!("SIMH reserved data class 0x{:X}", header.class)),
+            0xE => analyzed
+                .warnings
+                .push("SIMH tape description record (class E)".to_string()),
+            _ => analyzed
+                .warnings
+                .push(format!("SIMH unknown data class 0x{:X}", header.class)),
+        }
+
+        file.data_bytes += header.length as u64;
+        file.records.push(analyzed);
+    }
+
+    /// Returns `false` once the end-of-medium marker has been reached.
+    fn tape_mark(&mut self, offset: u64, kind: SimhTapeMark) -> bool {
+        let analysis = &mut self.analysis;
+        match kind {
+            SimhTapeMark::Single | SimhTapeMark::Double => {
+                if let Some(mut file) = self.current_file.take() {
+                    if matches!(kind, SimhTapeMark::Double) {
+                        file.tape_mark_warning = Some("Double tape mark encountered".to_string());
                     }
-                    SimhTapeMark::EraseGap => analysis
+                    push_completed_file(analysis, file);
+                } else if matches!(kind, SimhTapeMark::Double) {
+                    analysis
                         .warnings
-                        .push(format!("Erase gap marker at offset 0x{offset:08X}")),
-                    SimhTapeMark::HalfGapForward | SimhTapeMark::HalfGapReverse => analysis
-                        .warnings
-                        .push(format!("Half-gap marker at offset 0x{offset:08X}")),
-                    SimhTapeMark::Private { class, value } => analysis.warnings.push(format!(
-                        "SIMH private marker class 0x{class:X} value 0x{value:08X} at offset 0x{offset:08X}"
-                    )),
-                    SimhTapeMark::Reserved { class, value } => analysis.warnings.push(format!(
-                        "SIMH reserved marker class 0x{class:X} value 0x{value:08X} at offset 0x{offset:08X}"
-                    )),
-                }
-            }
-            Ok(SimhTapeBlock::EndOfStream) => {
-                if let Some(file) = current_file.take() {
-                    push_completed_file(&mut analysis, file);
+                        .push("Double tape mark observed outside of a file".to_string());
                 }
-                break;
             }
-            Err(err) => {
-                analysis.warnings.push(describe_reader_error(&err));
-                if let Some(file) = current_file.take() {
-                    push_completed_file(&mut analysis, file);
+            SimhTapeMark::EndOfTape => {
+                if let Some(file) = self.current_file.take() {
+                    push_completed_file(analysis, file);
                 }
-                break;
+                analysis.end_of_tape_offset = Some(offset);
+                return false;
             }
+            SimhTapeMark::EraseGap => analysis
+                .warnings
+                .push(format!("Erase gap marker at offset 0x{offset:08X}")),
+            SimhTapeMark::HalfGapForward | SimhTapeMark::HalfGapReverse { .. } => analysis
+                .warnings
+                .push(format!("Half-gap marker at offset 0x{offset:08X}")),
+            SimhTapeMark::Private { class, value } => analysis.warnings.push(format!(
+                "SIMH private marker class 0x{class:X} value 0x{value:08X} at offset 0x{offset:08X}"
+            )),
+            SimhTapeMark::Reserved { class, value } => analysis.warnings.push(format!(
+                "SIMH reserved marker class 0x{class:X} value 0x{value:08X} at offset 0x{offset:08X}"
+            )),
         }
+        true
     }
 
-    analysis.tape_summary = summarize_tape(&analysis.files);
+    /// Annotates the record just added with the characters that failed `parity`.
+    fn parity_errors(&mut self, parity: P7bParity, errors: &[usize]) {
+        let Some(&first) = errors.first() else {
+            return;
+        };
+        let Some(record) = self
+            .current_file
+            .as_mut()
+            .and_then(|file| file.records.last_mut())
+        else {
+            return;
+        };
+        let parity = match parity {
+            P7bParity::Odd => "odd",
+            P7bParity::Even => "even",
+        };
+        record.warnings.push(format!(
+            "7-track parity errors in {} of {} characters ({parity} parity), first at character {}",
+            errors.len(),
+            record.length,
+            first
+        ));
+    }
+
+    fn resync(&mut self, resync: &SimhResync) {
+        self.analysis.warnings.push(format!(
+            "{}; skipped {} bytes at offsets 0x{:08X}..0x{:08X} and resynchronised",
+            describe_reader_error(&resync.error),
+            resync.end - resync.start,
+            resync.start,
+            resync.end
+        ));
+    }
 
-    if analysis.backup_command.is_none() {
-        if let Some(command) = extract_backup_command(&command_records) {
+    fn reader_error(&mut self, err: &SimhError) {
+        self.analysis.warnings.push(describe_reader_error(err));
+    }
+
+    fn finish(mut self) -> TapeAnalysis {
+        if let Some(file) = self.current_file.take() {
+            push_completed_file(&mut self.analysis, file);
+        }
+
+        let mut analysis = self.analysis;
+        analysis.tape_summary = summarize_tape(&analysis.files);
+
+        if analysis.backup_command.is_none()
+            && let Some(command) = extract_backup_command(&self.command_records)
+        {
             analysis.backup_command = Some(command);
         }
-    }
 
-    analysis
+        analysis
+    }
 }
 
 fn classify_encoding(data: &[u8]) -> RecordEncoding {
@@ -328,7 +480,7 @@ fn push_completed_file(analysis: &mut TapeAnalysis, mut file: TapeFile) {
     analysis.files.push(file);
 }
 
-fn describe_reader_error(err: &io::Error) -> String {
+fn describe_reader_error(err: &SimhError) -> String {
     if indicates_position_loss(err) {
         format!(
             "Reader error (position lost – SIMH heuristics triggered): {}",
@@ -339,14 +491,76 @@ fn describe_reader_error(err: &io::Error) -> String {
     }
 }
 
-fn indicates_position_loss(err: &io::Error) -> bool {
-    use std::io::ErrorKind;
+fn indicates_position_loss(err: &SimhError) -> bool {
+    match err {
+        SimhError::TruncatedWord { .. }
+        | SimhError::TruncatedRecord { .. }
+        | SimhError::MissingTrailingLength { .. }
+        | SimhError::LengthMismatch { .. }
+        | SimhError::RecordBeforeBeginningOfTape { .. }
+        | SimhError::PreviousLengthMismatch { .. }
+        | SimhError::SegmentOutOfSequence { .. }
+        | SimhError::MissingRecordStart { .. } => true,
+        SimhError::Io(err) => err.kind() == io::ErrorKind::UnexpectedEof,
+        _ => false,
+    }
+}
 
-    if matches!(err.kind(), ErrorKind::UnexpectedEof) {
-        return true;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtsimh::SimhTapeWriter;
+
+    #[test]
+    fn keeps_tape_descriptions_out_of_the_files() {
+        let description = TapeDescription {
+            operator: Some("J. Citizen".into()),
+            label: Some("REEL 2".into()),
+            ..Default::default()
+        };
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer
+            .write_tape_description(&description.to_bytes())
+            .unwrap();
+        writer.write_record(b"hello").unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        let analysis = analyze_bytes(&writer.into_inner(), false);
+
+        assert_eq!(analysis.descriptions, vec![description]);
+        assert_eq!(analysis.totals.files, 1);
+        assert_eq!(analysis.totals.records, 1);
+        assert_eq!(analysis.totals.data_bytes, 5);
+        assert_eq!(analysis.files[0].records[0].record_index, 1);
+        assert_eq!(analysis.files[0].records[0].class, 0);
+        assert_eq!(
+            analysis
+                .tape_summary
+                .unwrap()
+                .formats
+                .first()
+                .map(String::as_str),
+            Some("Content appears ASCII")
+        );
     }
 
-    let msg = err.to_string();
-    err.kind() == ErrorKind::InvalidData
-        && (msg.contains("trailing length") || msg.contains("truncated SIMH word"))
+    #[test]
+    fn resynchronises_only_when_asked_to() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(b"first").unwrap();
+        writer.write_record(b"second").unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        let mut image = writer.into_inner();
+        // Corrupt the first record's trailing length.
+        image[10..14].copy_from_slice(&[0x55; 4]);
+
+        let strict = analyze_bytes(&image, false);
+        assert_eq!(strict.totals.records, 0);
+        assert_eq!(strict.warnings.len(), 1);
+
+        let recovered = analyze_bytes(&image, true);
+        assert_eq!(recovered.totals.records, 1);
+        assert!(recovered.warnings[0].contains("resynchronised"));
+    }
 }
@@ -1,3 +1,8 @@
-//! Local re-exports of shared SIMH TAP reader primitives.
+//! Local re-exports of shared tape image reader primitives.
 
-pub use rtsimh::{SimhTapeBlock, SimhTapeMark, SimhTapeReader, SimhTapeRecord};
+pub use rtsimh::{
+    E11TapeReader, P7bParity, P7bTapeReader, SimhError, SimhResync, SimhTapeBlock,
+    SimhTapeBlockRef, SimhTapeMark, SimhTapeRecordHeader, SimhTapeSliceReader,
+    SimhTapeStreamReader, TAPE_DESCRIPTION_CLASS, TapeBlockReader, TapeDescription,
+    TapeImageFormat,
+};
@@ -47,10 +47,10 @@ impl RecordSignature {
         if !self.confidence.is_empty() {
             detail.push_str(&format!(" (confidence: {})", self.confidence));
         }
-        if let Some(extra) = &self.details {
-            if !extra.is_empty() {
-                detail.push_str(&format!(" - {}", extra));
-            }
+        if let Some(extra) = &self.details
+            && !extra.is_empty()
+        {
+            detail.push_str(&format!(" - {}", extra));
         }
         detail
     }
@@ -62,24 +62,24 @@ impl RecordSignature {
         }
 
         let mut extras = Vec::new();
-        if let Some(fmt) = &self.format {
-            if !fmt.is_empty() {
-                extras.push(fmt.clone());
-            }
+        if let Some(fmt) = &self.format
+            && !fmt.is_empty()
+        {
+            extras.push(fmt.clone());
         }
-        if let Some(platform) = &self.platform {
-            if !platform.is_empty() {
-                extras.push(platform.clone());
-            }
+        if let Some(platform) = &self.platform
+            && !platform.is_empty()
+        {
+            extras.push(platform.clone());
         }
         if !extras.is_empty() {
             line.push_str(&format!(" -> {}", extras.join(" / ")));
         }
 
-        if let Some(extra) = &self.details {
-            if !extra.is_empty() {
-                line.push_str(&format!(" - {}", extra));
-            }
+        if let Some(extra) = &self.details
+            && !extra.is_empty()
+        {
+            line.push_str(&format!(" - {}", extra));
         }
 
         line
@@ -519,7 +519,7 @@ impl SignatureDetector {
     }
 
     fn detect_pdp11_backup(&self, data: &[u8], signatures: &mut Vec<RecordSignature>) {
-        if data.len() >= 32 && matches!(data.get(0), Some(1..=4)) && data.get(1) == Some(&0x00) {
+        if data.len() >= 32 && matches!(data.first(), Some(1..=4)) && data.get(1) == Some(&0x00) {
             signatures.push(
                 RecordSignature::new("pdp11-backup", "PDP-11 BACKUP save set block")
                     .with_format("PDP-11 BACKUP save set")
@@ -559,15 +559,13 @@ impl SignatureDetector {
     }
 
     fn detect_qic(&self, data: &[u8], signatures: &mut Vec<RecordSignature>) {
-        if data.len() >= 4 {
-            if &data[0..4] == b"QIC\x00" || &data[0..4] == b"\x00QIC" {
-                signatures.push(
-                    RecordSignature::new("qic", "QIC tape format header")
-                        .with_format("QIC tape format")
-                        .with_platform("Quarter-Inch Cartridge")
-                        .with_confidence("high"),
-                );
-            }
+        if data.len() >= 4 && (&data[0..4] == b"QIC\x00" || &data[0..4] == b"\x00QIC") {
+            signatures.push(
+                RecordSignature::new("qic", "QIC tape format header")
+                    .with_format("QIC tape format")
+                    .with_platform("Quarter-Inch Cartridge")
+                    .with_confidence("high"),
+            );
         }
 
         if data.len() >= 516 && &data[512..516] == b"QF\x00\x00" {
@@ -616,16 +614,17 @@ impl SignatureDetector {
         }
         let prefix = &data[0..3];
         let digit = data[3];
-        if matches!(prefix, b"VOL" | b"HDR" | b"EOF" | b"EOV") && digit.is_ascii_digit() {
-            if let Ok(label) = std::str::from_utf8(&data[0..4]) {
-                signatures.push(
-                    RecordSignature::new("ibm-sl", format!("IBM Standard Label format ({label})"))
-                        .with_format("IBM Standard Label")
-                        .with_platform("IBM Mainframe")
-                        .with_confidence("medium")
-                        .with_details("IBM tape label structure detected"),
-                );
-            }
+        if matches!(prefix, b"VOL" | b"HDR" | b"EOF" | b"EOV")
+            && digit.is_ascii_digit()
+            && let Ok(label) = std::str::from_utf8(&data[0..4])
+        {
+            signatures.push(
+                RecordSignature::new("ibm-sl", format!("IBM Standard Label format ({label})"))
+                    .with_format("IBM Standard Label")
+                    .with_platform("IBM Mainframe")
+                    .with_confidence("medium")
+                    .with_details("IBM tape label structure detected"),
+            );
         }
     }
 
@@ -726,15 +725,15 @@ fn push_signature(
     fmt: Option<&'static str>,
     platform: Option<&'static str>,
 ) {
-    if let Some(fmt) = fmt {
-        if !fmt.is_empty() {
-            sig = sig.with_format(fmt);
-        }
+    if let Some(fmt) = fmt
+        && !fmt.is_empty()
+    {
+        sig = sig.with_format(fmt);
     }
-    if let Some(platform) = platform {
-        if !platform.is_empty() {
-            sig = sig.with_platform(platform);
-        }
+    if let Some(platform) = platform
+        && !platform.is_empty()
+    {
+        sig = sig.with_platform(platform);
     }
     signatures.push(sig);
 }
@@ -745,7 +744,7 @@ mod tests {
 
     #[test]
     fn detects_gzip_magic() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let data = b"\x1f\x8brest";
         let signatures = detector.detect(data, 10);
         assert!(signatures.iter().any(|sig| sig.tag == "gzip"));
@@ -753,7 +752,7 @@ mod tests {
 
     #[test]
     fn detects_tar_header() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut block = vec![0u8; 512];
         block[257..263].copy_from_slice(b"ustar\0");
         let signatures = detector.detect(&block, 512);
@@ -762,7 +761,7 @@ mod tests {
 
     #[test]
     fn detects_plain_text_with_line_endings() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let data = b"First line of text\nSecond line of text\nThird line of text\n";
         let signatures = detector.detect(data, data.len() as u32);
         assert!(
@@ -774,7 +773,7 @@ mod tests {
 
     #[test]
     fn block_size_hint_only_when_no_magic() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let data = vec![0u8; 100];
         let signatures = detector.detect(&data, 2048);
         assert!(signatures.iter().any(|sig| sig.tag == "rsx-block"));
@@ -782,7 +781,7 @@ mod tests {
 
     #[test]
     fn detects_unix_compress_format() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut data = vec![0u8; 64];
         data[0] = 0x1f;
         data[1] = 0x9d;
@@ -792,7 +791,7 @@ mod tests {
 
     #[test]
     fn detects_lha_archive_header() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut data = vec![0u8; 16];
         data[2..7].copy_from_slice(b"-lh5-");
         let signatures = detector.detect(&data, data.len() as u32);
@@ -801,7 +800,7 @@ mod tests {
 
     #[test]
     fn detects_arc_header() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut data = vec![0u8; 32];
         data[0] = 0x1a;
         data[1] = 0x05;
@@ -811,7 +810,7 @@ mod tests {
 
     #[test]
     fn detects_zoo_header() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut data = vec![0u8; 32];
         data[0..4].copy_from_slice(b"ZOO ");
         let signatures = detector.detect(&data, data.len() as u32);
@@ -820,7 +819,7 @@ mod tests {
 
     #[test]
     fn detects_arj_header() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut data = vec![0u8; 16];
         data[0] = 0x60;
         data[1] = 0xea;
@@ -830,7 +829,7 @@ mod tests {
 
     #[test]
     fn detects_cabinet_file() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut data = vec![0u8; 32];
         data[0..4].copy_from_slice(b"MSCF");
         let signatures = detector.detect(&data, data.len() as u32);
@@ -839,7 +838,7 @@ mod tests {
 
     #[test]
     fn detects_szdd_file() {
-        let detector = SignatureDetector::default();
+        let detector = SignatureDetector;
         let mut data = vec![0u8; 32];
         data[0..4].copy_from_slice(b"SZDD");
         let signatures = detector.detect(&data, data.len() as u32);
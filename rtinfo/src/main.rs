@@ -4,9 +4,10 @@ mod output;
 use anyhow::{Context, Result};
 use chrono::Local;
 use clap::{ArgGroup, Parser};
+use memmap2::Mmap;
 use output::OutputOptions;
-use rtsimh::VERSION;
-use std::fs;
+use rtsimh::{PROBE_LENGTH, TapeImageFormat, VERSION};
+use std::fs::File;
 use std::io::{self, Read};
 use std::path::Path;
 use std::time::Instant;
@@ -40,6 +41,15 @@ struct Cli {
     #[arg(value_name = "INPUT", default_value = "-")]
     input: String,
 
+    /// Input format: simh, e11, tpc, aws, p7b or raw (detected when omitted)
+    #[arg(long, value_name = "FORMAT")]
+    format: Option<TapeImageFormat>,
+
+    /// Skip damaged data in SIMH and E11 images and resynchronise on the next
+    /// plausible record (stdin is then read into memory first)
+    #[arg(long)]
+    recover: bool,
+
     /// Hide all previews unless explicitly re-enabled via --show-* flags
     #[arg(long)]
     summaries_only: bool,
@@ -67,6 +77,14 @@ struct Cli {
 }
 
 impl Cli {
+    /// The requested format, else the one implied by the file extension, else
+    /// whatever `sample` looks like.
+    fn input_format(&self, sample: &[u8]) -> TapeImageFormat {
+        self.format
+            .or_else(|| TapeImageFormat::from_extension(Path::new(&self.input)))
+            .unwrap_or_else(|| TapeImageFormat::probe(sample))
+    }
+
     fn output_options(&self) -> OutputOptions {
         let mut opts = OutputOptions::default();
         if self.summaries_only {
@@ -124,13 +142,43 @@ fn main() -> Result<()> {
     };
 
     println!("Input: {}", input_path);
+
+    let image = if cli.input == "-" {
+        InputImage::stream(Box::new(io::stdin().lock())).context("failed to read stdin")?
+    } else {
+        let file =
+            File::open(&cli.input).with_context(|| format!("failed to open file {}", cli.input))?;
+        match map_file(&file) {
+            Some(image) => InputImage::Mapped(image),
+            None => InputImage::stream(Box::new(file))
+                .with_context(|| format!("failed to read file {}", cli.input))?,
+        }
+    };
+    let format = cli.input_format(image.sample());
+
+    println!("Format: {}", format.description());
     println!("========================");
     println!("Performing analysis...");
 
-    let data = read_input(&cli.input).context("failed to read input data")?;
-
     let start = Instant::now();
-    let analysis = analyzer::analyze_bytes(&data);
+    let analysis = match image {
+        InputImage::Mapped(image) => analyzer::analyze_image(&image, format, cli.recover),
+        // SIMH and P7B stream straight from the source; the other readers, and
+        // resynchronisation, need the whole image.
+        InputImage::Stream(sample, mut source) => match format {
+            TapeImageFormat::Simh if !cli.recover => {
+                analyzer::analyze_stream(sample.as_slice().chain(source))
+            }
+            TapeImageFormat::P7b => analyzer::analyze_p7b(sample.as_slice().chain(source)),
+            _ => {
+                let mut image = sample;
+                source
+                    .read_to_end(&mut image)
+                    .context("failed to read input")?;
+                analyzer::analyze_image(&image, format, cli.recover)
+            }
+        },
+    };
     let elapsed_ms = start.elapsed().as_millis();
     println!(
         "Analysis took {}ms. Results below.",
@@ -149,16 +197,39 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn read_input(path: &str) -> Result<Vec<u8>> {
-    if path == "-" {
-        let mut buffer = Vec::new();
-        io::stdin()
-            .read_to_end(&mut buffer)
-            .context("stdin read failed")?;
-        Ok(buffer)
-    } else {
-        fs::read(path).with_context(|| format!("failed to read file {path}"))
+enum InputImage {
+    /// A source that can only be read once from the start, such as stdin or a
+    /// FIFO, with the sample already taken from it for format detection.
+    Stream(Vec<u8>, Box<dyn Read>),
+    Mapped(Mmap),
+}
+
+impl InputImage {
+    fn stream(mut source: Box<dyn Read>) -> io::Result<Self> {
+        let mut sample = Vec::with_capacity(PROBE_LENGTH);
+        (&mut source)
+            .take(PROBE_LENGTH as u64)
+            .read_to_end(&mut sample)?;
+        Ok(InputImage::Stream(sample, source))
+    }
+
+    fn sample(&self) -> &[u8] {
+        match self {
+            InputImage::Stream(sample, _) => sample,
+            InputImage::Mapped(image) => &image[..image.len().min(PROBE_LENGTH)],
+        }
+    }
+}
+
+/// Maps regular files; pipes, character devices and anything else that cannot
+/// be mapped are streamed instead.
+fn map_file(file: &File) -> Option<Mmap> {
+    if !file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+        return None;
     }
+    // SAFETY: the mapping is only read and is dropped once analysis finishes; as with
+    // any mmap-based reader, truncating the image underneath us is not supported.
+    unsafe { Mmap::map(file) }.ok()
 }
 
 fn format_with_commas<T: ToString>(value: T) -> String {
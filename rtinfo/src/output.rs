@@ -2,25 +2,22 @@
 
 use crate::analyzer::{AnalyzedRecord, RecordEncoding, TapeAnalysis, TapeSummary};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct OutputOptions {
     pub show_binary: bool,
     pub show_ascii: bool,
     pub show_labels: bool,
 }
 
-impl Default for OutputOptions {
-    fn default() -> Self {
-        Self {
-            show_binary: false,
-            show_ascii: false,
-            show_labels: false,
-        }
-    }
-}
-
 pub fn format_analysis(analysis: &TapeAnalysis, opts: &OutputOptions) -> String {
     let mut lines = Vec::new();
+    for description in &analysis.descriptions {
+        lines.push("Capture metadata:".to_string());
+        for (key, value) in description.fields() {
+            lines.push(format!("  {key}: {value}"));
+        }
+        lines.push(String::new());
+    }
     lines.push(format!(
         "Total files: {}",
         format_with_commas(analysis.totals.files)
@@ -202,11 +199,11 @@ fn coalesce_record_runs(records: &[AnalyzedRecord], opts: &OutputOptions) -> Vec
     for record in records {
         let body = record_body_lines(record, opts);
         let mut extends_current = false;
-        if let Some(run) = current.as_mut() {
-            if run.can_extend(record, &body) {
-                run.extend(record);
-                extends_current = true;
-            }
+        if let Some(run) = current.as_mut()
+            && run.can_extend(record, &body)
+        {
+            run.extend(record);
+            extends_current = true;
         }
 
         if !extends_current {
@@ -0,0 +1,504 @@
+use std::io::{Read, Write};
+
+use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use crate::{
+    MAX_RECORD_LENGTH, SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeRecord,
+    SimhTapeRecordHeader, TapeBlockReader, TapeBlockWriter, VALUE_MASK, read_payload_from,
+    write_tape_mark_only,
+};
+
+const AWS_FORMAT: &str = "AWS";
+const AWS_HEADER_LENGTH: usize = 6;
+const AWS_MAX_CHUNK: u16 = 0xFFFF;
+
+const FLAG_NEW_RECORD: u8 = 0x80;
+const FLAG_TAPE_MARK: u8 = 0x40;
+const FLAG_END_OF_RECORD: u8 = 0x20;
+const FLAG_COMPRESSION_MASK: u8 = 0x03;
+const FLAG_ZLIB: u8 = 0x01;
+const FLAG_BZIP2: u8 = 0x02;
+
+/// Compression applied to HET blocks. `None` produces plain AWSTAPE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HetCompression {
+    #[default]
+    None,
+    Zlib,
+    Bzip2,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AwsHeader {
+    current: u16,
+    previous: u16,
+    flags: u8,
+}
+
+/// Reads AWSTAPE images and their compressed HET variant. Each block is split
+/// into segments behind 6-byte headers carrying the segment length, the previous
+/// segment's length and flags; segments are joined and decompressed into one record.
+/// AWS has no record classes, so every record is returned as class 0.
+pub struct AwsTapeReader<R> {
+    reader: R,
+    safety_limit: u32,
+    offset: u64,
+    previous_length: u16,
+    lookahead: Option<AwsHeader>,
+    pending_double: bool,
+}
+
+impl<R: Read> AwsTapeReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            safety_limit: MAX_RECORD_LENGTH,
+            offset: 0,
+            previous_length: 0,
+            lookahead: None,
+            pending_double: false,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.safety_limit = limit.min(VALUE_MASK);
+        self
+    }
+
+    /// Offset of the next header, counted from where the stream started.
+    pub fn position(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn fetch_header(&mut self) -> SimhResult<Option<AwsHeader>> {
+        let offset = self.offset;
+        let mut buf = [0u8; AWS_HEADER_LENGTH];
+        let mut read = 0;
+        while read < buf.len() {
+            let n = self.reader.read(&mut buf[read..])?;
+            if n == 0 {
+                return if read == 0 {
+                    Ok(None)
+                } else {
+                    Err(SimhError::TruncatedWord { offset })
+                };
+            }
+            read += n;
+        }
+
+        let header = AwsHeader {
+            current: u16::from_le_bytes([buf[0], buf[1]]),
+            previous: u16::from_le_bytes([buf[2], buf[3]]),
+            flags: buf[4],
+        };
+        if header.previous != self.previous_length {
+            return Err(SimhError::PreviousLengthMismatch {
+                offset,
+                expected: u32::from(self.previous_length),
+                found: u32::from(header.previous),
+            });
+        }
+        self.previous_length = header.current;
+        Ok(Some(header))
+    }
+
+    fn read_header(&mut self) -> SimhResult<Option<AwsHeader>> {
+        let header = match self.lookahead.take() {
+            Some(header) => Some(header),
+            None => self.fetch_header()?,
+        };
+        if header.is_some() {
+            self.offset += AWS_HEADER_LENGTH as u64;
+        }
+        Ok(header)
+    }
+
+    fn peek_header(&mut self) -> SimhResult<Option<AwsHeader>> {
+        if self.lookahead.is_none() {
+            self.lookahead = self.fetch_header()?;
+        }
+        Ok(self.lookahead)
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        let offset = self.offset;
+        let Some(mut header) = self.read_header()? else {
+            return Ok(SimhTapeBlock::EndOfStream);
+        };
+
+        if header.flags & FLAG_TAPE_MARK != 0 {
+            let kind = if self.pending_double {
+                self.pending_double = false;
+                SimhTapeMark::Double
+            } else {
+                self.pending_double = self
+                    .peek_header()?
+                    .is_some_and(|next| next.flags & FLAG_TAPE_MARK != 0);
+                SimhTapeMark::Single
+            };
+            return Ok(SimhTapeBlock::TapeMark { offset, kind });
+        }
+        self.pending_double = false;
+
+        if header.flags & FLAG_NEW_RECORD == 0 {
+            return Err(SimhError::SegmentOutOfSequence { offset });
+        }
+
+        let compression = header.flags & FLAG_COMPRESSION_MASK;
+        let mut payload = Vec::new();
+        let mut segment_offset = offset;
+        loop {
+            let length = u32::from(header.current);
+            if payload.len() as u64 + u64::from(length) > u64::from(self.safety_limit) {
+                return Err(SimhError::LengthExceedsLimit {
+                    offset,
+                    length: (payload.len() as u64 + u64::from(length)).min(u64::from(u32::MAX))
+                        as u32,
+                    limit: self.safety_limit,
+                });
+            }
+
+            let start = payload.len();
+            payload.resize(start + length as usize, 0);
+            read_payload_from(
+                &mut self.reader,
+                &mut payload[start..],
+                segment_offset,
+                length,
+            )?;
+            self.offset += u64::from(length);
+
+            if header.flags & FLAG_END_OF_RECORD != 0 {
+                break;
+            }
+
+            segment_offset = self.offset;
+            header = self.read_header()?.ok_or(SimhError::TruncatedRecord {
+                offset,
+                length: payload.len() as u32,
+            })?;
+            if header.flags & (FLAG_NEW_RECORD | FLAG_TAPE_MARK) != 0 {
+                return Err(SimhError::SegmentOutOfSequence {
+                    offset: segment_offset,
+                });
+            }
+        }
+
+        let data = self.decompress(offset, compression, payload)?;
+        let length = data.len() as u32;
+        Ok(SimhTapeBlock::Record(SimhTapeRecord {
+            header: SimhTapeRecordHeader {
+                offset,
+                class: 0,
+                length,
+                trailing_length: None,
+            },
+            data,
+        }))
+    }
+
+    fn decompress(&self, offset: u64, method: u8, payload: Vec<u8>) -> SimhResult<Vec<u8>> {
+        let limit = u64::from(self.safety_limit);
+        let mut data = Vec::new();
+        let result = match method {
+            0 => return Ok(payload),
+            FLAG_ZLIB => ZlibDecoder::new(payload.as_slice())
+                .take(limit + 1)
+                .read_to_end(&mut data),
+            FLAG_BZIP2 => BzDecoder::new(payload.as_slice())
+                .take(limit + 1)
+                .read_to_end(&mut data),
+            method => return Err(SimhError::UnsupportedCompression { offset, method }),
+        };
+
+        result.map_err(|_| SimhError::CorruptCompressedBlock { offset })?;
+        if data.len() as u64 > limit {
+            return Err(SimhError::LengthExceedsLimit {
+                offset,
+                length: data.len() as u32,
+                limit: self.safety_limit,
+            });
+        }
+        Ok(data)
+    }
+}
+
+/// Whether `sample`, the start of an image, holds a consistent chain of AWS
+/// headers. A segment running past the end is accepted unless `complete`.
+pub(crate) fn probe(sample: &[u8], complete: bool) -> bool {
+    let known_flags = FLAG_NEW_RECORD | FLAG_TAPE_MARK | FLAG_END_OF_RECORD | FLAG_COMPRESSION_MASK;
+    let mut offset = 0;
+    let mut previous = 0;
+    let mut in_record = false;
+    let mut segments = 0;
+
+    while offset + AWS_HEADER_LENGTH <= sample.len() {
+        let current = u16::from_le_bytes([sample[offset], sample[offset + 1]]);
+        let flags = sample[offset + 4];
+        if u16::from_le_bytes([sample[offset + 2], sample[offset + 3]]) != previous
+            || flags & !known_flags != 0
+        {
+            return false;
+        }
+        if flags & FLAG_TAPE_MARK != 0 {
+            if in_record || current != 0 {
+                return false;
+            }
+        } else if (flags & FLAG_NEW_RECORD != 0) == in_record || current == 0 {
+            return false;
+        } else {
+            in_record = flags & FLAG_END_OF_RECORD == 0;
+        }
+
+        segments += 1;
+        previous = current;
+        offset += AWS_HEADER_LENGTH + usize::from(current);
+        if offset > sample.len() {
+            return !complete;
+        }
+    }
+    segments > 0 && (!complete || offset == sample.len())
+}
+
+/// Writes AWSTAPE images, or HET images when compression is enabled. Blocks
+/// longer than the chunk size are split into segments; a compressed block that
+/// does not shrink is stored as is.
+pub struct AwsTapeWriter<W: Write> {
+    writer: W,
+    previous_length: u16,
+    chunk_size: u16,
+    compression: HetCompression,
+}
+
+impl<W: Write> AwsTapeWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            previous_length: 0,
+            chunk_size: AWS_MAX_CHUNK,
+            compression: HetCompression::None,
+        }
+    }
+
+    pub fn with_compression(mut self, compression: HetCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: u16) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    fn write_header(&mut self, current: u16, flags: u8) -> SimhResult<()> {
+        let mut header = [0u8; AWS_HEADER_LENGTH];
+        header[0..2].copy_from_slice(&current.to_le_bytes());
+        header[2..4].copy_from_slice(&self.previous_length.to_le_bytes());
+        header[4] = flags;
+        self.writer.write_all(&header)?;
+        self.previous_length = current;
+        Ok(())
+    }
+
+    fn compress(&self, data: &[u8]) -> SimhResult<(Vec<u8>, u8)> {
+        let (compressed, flag) = match self.compression {
+            HetCompression::None => return Ok((data.to_vec(), 0)),
+            HetCompression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                (encoder.finish()?, FLAG_ZLIB)
+            }
+            HetCompression::Bzip2 => {
+                let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(data)?;
+                (encoder.finish()?, FLAG_BZIP2)
+            }
+        };
+
+        if compressed.len() < data.len() {
+            Ok((compressed, flag))
+        } else {
+            Ok((data.to_vec(), 0))
+        }
+    }
+
+    pub fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(0, data)
+    }
+
+    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        if class != 0 {
+            return Err(SimhError::Unsupported {
+                format: AWS_FORMAT,
+                feature: "record classes",
+            });
+        }
+        if data.is_empty() {
+            return Err(SimhError::EmptyRecord);
+        }
+        if data.len() > MAX_RECORD_LENGTH as usize {
+            return Err(SimhError::RecordTooLong {
+                length: data.len(),
+                limit: MAX_RECORD_LENGTH,
+            });
+        }
+
+        let (payload, compression) = self.compress(data)?;
+        let mut segments = payload.chunks(usize::from(self.chunk_size)).peekable();
+        let mut flags = FLAG_NEW_RECORD | compression;
+        while let Some(segment) = segments.next() {
+            if segments.peek().is_none() {
+                flags |= FLAG_END_OF_RECORD;
+            }
+            self.write_header(segment.len() as u16, flags)?;
+            self.writer.write_all(segment)?;
+            flags &= !FLAG_NEW_RECORD;
+        }
+        Ok(())
+    }
+
+    pub fn write_tape_mark(&mut self) -> SimhResult<()> {
+        self.write_header(0, FLAG_TAPE_MARK)
+    }
+
+    /// AWSTAPE has no end-of-medium marker, so this writes nothing.
+    pub fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<R: Read> TapeBlockReader for AwsTapeReader<R> {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        AwsTapeReader::next_block(self)
+    }
+}
+
+impl<W: Write> TapeBlockWriter for AwsTapeWriter<W> {
+    fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        AwsTapeWriter::write_record_with_class(self, class, data)
+    }
+
+    fn write_tape_mark(&mut self) -> SimhResult<()> {
+        AwsTapeWriter::write_tape_mark(self)
+    }
+
+    fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        AwsTapeWriter::write_end_of_medium(self)
+    }
+
+    fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        write_tape_mark_only(self, mark, AWS_FORMAT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(image: &[u8]) -> Vec<SimhTapeBlock> {
+        let mut reader = AwsTapeReader::new(image);
+        let mut blocks = Vec::new();
+        loop {
+            match reader.next_block().unwrap() {
+                SimhTapeBlock::EndOfStream => return blocks,
+                block => blocks.push(block),
+            }
+        }
+    }
+
+    #[test]
+    fn joins_segmented_blocks_and_tracks_tape_marks() {
+        let mut writer = AwsTapeWriter::new(Vec::new()).with_chunk_size(4);
+        writer
+            .write_record(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10])
+            .unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        let image = writer.into_inner();
+
+        assert_eq!(&image[..6], &[4, 0, 0, 0, FLAG_NEW_RECORD, 0]);
+        assert_eq!(&image[10..16], &[4, 0, 4, 0, 0, 0]);
+        assert_eq!(&image[20..26], &[2, 0, 4, 0, FLAG_END_OF_RECORD, 0]);
+
+        let blocks = read_all(&image);
+        assert_eq!(blocks.len(), 3);
+        match &blocks[0] {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.data, (1..=10).collect::<Vec<u8>>());
+                assert_eq!(record.header.length, 10);
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert!(matches!(
+            blocks[1],
+            SimhTapeBlock::TapeMark {
+                offset: 28,
+                kind: SimhTapeMark::Single
+            }
+        ));
+        assert!(matches!(
+            blocks[2],
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Double,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn round_trips_het_compression() {
+        let data: Vec<u8> = b"HERCULES ".iter().copied().cycle().take(4000).collect();
+        for compression in [HetCompression::Zlib, HetCompression::Bzip2] {
+            let mut writer = AwsTapeWriter::new(Vec::new())
+                .with_compression(compression)
+                .with_chunk_size(64);
+            writer.write_record(&data).unwrap();
+            writer.write_record(&[0x42]).unwrap();
+            writer.write_tape_mark().unwrap();
+            let image = writer.into_inner();
+            assert!(image.len() < data.len());
+            assert_ne!(image[4] & FLAG_COMPRESSION_MASK, 0);
+
+            let blocks = read_all(&image);
+            match &blocks[0] {
+                SimhTapeBlock::Record(record) => assert_eq!(record.data, data),
+                other => panic!("expected record, got {:?}", other),
+            }
+            match &blocks[1] {
+                SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![0x42]),
+                other => panic!("expected record, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_broken_previous_length_chain() {
+        let mut writer = AwsTapeWriter::new(Vec::new());
+        writer.write_record(&[1, 2]).unwrap();
+        writer.write_record(&[3, 4]).unwrap();
+        let mut image = writer.into_inner();
+        image[10] = 9;
+
+        let mut reader = AwsTapeReader::new(image.as_slice());
+        reader.next_block().unwrap();
+        assert!(matches!(
+            reader.next_block(),
+            Err(SimhError::PreviousLengthMismatch {
+                offset: 8,
+                expected: 2,
+                found: 9
+            })
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+/// First line of the class E records rttools writes.
+const HEADER: &str = "rttools capture metadata v1";
+
+/// Capture metadata for a class E tape description record. The record holds
+/// plain `Key: value` lines after a header line, so it stays readable in a
+/// hex dump and to tools that know nothing about it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TapeDescription {
+    pub operator: Option<String>,
+    /// Text from the reel's physical label.
+    pub label: Option<String>,
+    pub density: Option<String>,
+    pub drive: Option<String>,
+    pub date: Option<String>,
+    /// Fields this version does not know, in record order.
+    pub other: Vec<(String, String)>,
+}
+
+impl TapeDescription {
+    /// The set fields in record order, keyed as they are written.
+    pub fn fields(&self) -> Vec<(&str, &str)> {
+        let known = [
+            ("Operator", &self.operator),
+            ("Label", &self.label),
+            ("Density", &self.density),
+            ("Drive", &self.drive),
+            ("Date", &self.date),
+        ];
+        known
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value.as_deref()?)))
+            .chain(
+                self.other
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str())),
+            )
+            .collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut text = format!("{HEADER}\n");
+        for (key, value) in self.fields() {
+            // Values are single lines; fold anything else onto one.
+            let value: Vec<&str> = value.lines().map(str::trim).collect();
+            text.push_str(&format!("{key}: {}\n", value.join(" ")));
+        }
+        text.into_bytes()
+    }
+
+    /// Decodes a record written by `to_bytes`; `None` for anything else,
+    /// such as descriptions written by other tools.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(data).ok()?;
+        let mut lines = text.lines();
+        if lines.next()?.trim_end() != HEADER {
+            return None;
+        }
+
+        let mut description = TapeDescription::default();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once(':')?;
+            let value = value.trim().to_string();
+            match key.trim() {
+                "Operator" => description.operator = Some(value),
+                "Label" => description.label = Some(value),
+                "Density" => description.density = Some(value),
+                "Drive" => description.drive = Some(value),
+                "Date" => description.date = Some(value),
+                key => description.other.push((key.to_string(), value)),
+            }
+        }
+        Some(description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_known_and_unknown_fields() {
+        let description = TapeDescription {
+            operator: Some("J. Citizen".into()),
+            label: Some("PAYROLL 1983\nREEL 2 OF 3".into()),
+            density: Some("6250 bpi".into()),
+            date: Some("2026-01-01T09:30:00+10:00".into()),
+            other: vec![("Project".into(), "Rescue: batch 4".into())],
+            ..TapeDescription::default()
+        };
+        let bytes = description.to_bytes();
+        assert!(bytes.starts_with(b"rttools capture metadata v1\nOperator: J. Citizen\n"));
+
+        let parsed = TapeDescription::parse(&bytes).unwrap();
+        assert_eq!(parsed.label.as_deref(), Some("PAYROLL 1983 REEL 2 OF 3"));
+        assert_eq!(parsed.drive, None);
+        assert_eq!(
+            parsed.other,
+            vec![("Project".to_string(), "Rescue: batch 4".to_string())]
+        );
+        assert_eq!(parsed.fields().len(), 5);
+    }
+
+    #[test]
+    fn ignores_foreign_descriptions() {
+        assert_eq!(TapeDescription::parse(b"9TRK 1600"), None);
+        assert_eq!(TapeDescription::parse(&[0xFF, 0xD8, 0xFF, 0xE0]), None);
+        assert_eq!(
+            TapeDescription::parse(b"rttools capture metadata v1\nno separator\n"),
+            None
+        );
+    }
+}
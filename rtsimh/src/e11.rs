@@ -0,0 +1,168 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader, SimhTapeWriter, TapeBlockReader,
+    TapeBlockWriter,
+};
+
+/// Reads Ersatz-11 images, which frame records like SIMH but leave odd-length
+/// records unpadded.
+pub struct E11TapeReader<R> {
+    inner: SimhTapeReader<R>,
+}
+
+impl<R: Read + Seek> E11TapeReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            inner: SimhTapeReader::new(reader).without_padding(),
+        }
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.inner = self.inner.with_limit(limit);
+        self
+    }
+
+    /// See `SimhTapeReader::with_recovery`.
+    pub fn with_recovery(mut self, enabled: bool) -> Self {
+        self.inner = self.inner.with_recovery(enabled);
+        self
+    }
+
+    pub fn position(&mut self) -> SimhResult<u64> {
+        self.inner.position()
+    }
+
+    pub fn set_position(&mut self, offset: u64) -> SimhResult<()> {
+        self.inner.set_position(offset)
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.inner.next_block()
+    }
+
+    pub fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.inner.skip_block()
+    }
+
+    pub fn prev_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.inner.prev_block()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+/// Writes Ersatz-11 images: SIMH framing without the pad byte after odd-length records.
+pub struct E11TapeWriter<W: Write> {
+    inner: SimhTapeWriter<W>,
+}
+
+impl<W: Write> E11TapeWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            inner: SimhTapeWriter::new(writer).without_padding(),
+        }
+    }
+
+    pub fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.inner.write_record(data)
+    }
+
+    pub fn write_bad_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.inner.write_bad_record(data)
+    }
+
+    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        self.inner.write_record_with_class(class, data)
+    }
+
+    pub fn write_tape_mark(&mut self) -> SimhResult<()> {
+        self.inner.write_tape_mark()
+    }
+
+    pub fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        self.inner.write_end_of_medium()
+    }
+
+    pub fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        self.inner.write_mark(mark)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner.into_inner()
+    }
+}
+
+impl<R: Read + Seek> TapeBlockReader for E11TapeReader<R> {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        E11TapeReader::next_block(self)
+    }
+
+    fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        E11TapeReader::skip_block(self)
+    }
+}
+
+impl<W: Write> TapeBlockWriter for E11TapeWriter<W> {
+    fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        E11TapeWriter::write_record_with_class(self, class, data)
+    }
+
+    fn write_tape_mark(&mut self) -> SimhResult<()> {
+        E11TapeWriter::write_tape_mark(self)
+    }
+
+    fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        E11TapeWriter::write_end_of_medium(self)
+    }
+
+    fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        E11TapeWriter::write_mark(self, mark)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn odd_records_are_not_padded() {
+        let mut writer = E11TapeWriter::new(Vec::new());
+        writer.write_record(&[1, 2, 3]).unwrap();
+        writer.write_bad_record(&[4]).unwrap();
+        writer.write_tape_mark().unwrap();
+        let tape = writer.into_inner();
+        assert_eq!(tape.len(), 11 + 9 + 4);
+        assert_eq!(&tape[7..11], &3u32.to_le_bytes());
+
+        let mut reader = E11TapeReader::new(Cursor::new(tape.clone()));
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![1, 2, 3]),
+            other => panic!("expected record, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.header.offset, 11);
+                assert_eq!(record.header.class, 0x8);
+                assert_eq!(record.data, vec![4]);
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::Single);
+                assert_eq!(offset, 20);
+            }
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+
+        reader.set_position(20).unwrap();
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.header.offset, 11),
+            other => panic!("expected record, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::VALUE_MASK;
+
+pub type SimhResult<T> = Result<T, SimhError>;
+
+#[derive(Debug)]
+pub enum SimhError {
+    Io(io::Error),
+    TruncatedWord {
+        offset: u64,
+    },
+    TruncatedRecord {
+        offset: u64,
+        length: u32,
+    },
+    MissingTrailingLength {
+        offset: u64,
+        leading: u32,
+    },
+    LengthMismatch {
+        offset: u64,
+        leading: u32,
+        trailing: u32,
+    },
+    LengthExceedsLimit {
+        offset: u64,
+        length: u32,
+        limit: u32,
+    },
+    IllegalMarker {
+        offset: u64,
+        value: u32,
+    },
+    RecordBeforeBeginningOfTape {
+        offset: u64,
+        trailing: u32,
+    },
+    PreviousLengthMismatch {
+        offset: u64,
+        expected: u32,
+        found: u32,
+    },
+    SegmentOutOfSequence {
+        offset: u64,
+    },
+    UnsupportedCompression {
+        offset: u64,
+        method: u8,
+    },
+    CorruptCompressedBlock {
+        offset: u64,
+    },
+    MissingRecordStart {
+        offset: u64,
+    },
+    ClassOutOfRange {
+        class: u8,
+    },
+    ValueOutOfRange {
+        value: u32,
+    },
+    RecordTooLong {
+        length: usize,
+        limit: u32,
+    },
+    InvalidRecordClass {
+        class: u8,
+    },
+    EmptyRecord,
+    InvalidMarkerValue {
+        class: u8,
+        value: u32,
+    },
+    Unsupported {
+        format: &'static str,
+        feature: &'static str,
+    },
+    NotARecord {
+        offset: u64,
+    },
+    RecordSizeMismatch {
+        offset: u64,
+        existing: u32,
+        replacement: usize,
+    },
+    InvalidIndex(String),
+    NotIndexed {
+        file: usize,
+        record: Option<usize>,
+    },
+}
+
+impl SimhError {
+    /// The closest `io::ErrorKind`, for callers that still reason in those terms.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            SimhError::Io(err) => err.kind(),
+            SimhError::TruncatedWord { .. }
+            | SimhError::TruncatedRecord { .. }
+            | SimhError::MissingTrailingLength { .. }
+            | SimhError::RecordBeforeBeginningOfTape { .. } => io::ErrorKind::UnexpectedEof,
+            SimhError::LengthMismatch { .. }
+            | SimhError::LengthExceedsLimit { .. }
+            | SimhError::IllegalMarker { .. }
+            | SimhError::PreviousLengthMismatch { .. }
+            | SimhError::SegmentOutOfSequence { .. }
+            | SimhError::UnsupportedCompression { .. }
+            | SimhError::CorruptCompressedBlock { .. }
+            | SimhError::MissingRecordStart { .. }
+            | SimhError::InvalidIndex(_) => io::ErrorKind::InvalidData,
+            SimhError::ClassOutOfRange { .. }
+            | SimhError::ValueOutOfRange { .. }
+            | SimhError::RecordTooLong { .. }
+            | SimhError::InvalidRecordClass { .. }
+            | SimhError::EmptyRecord
+            | SimhError::InvalidMarkerValue { .. }
+            | SimhError::Unsupported { .. }
+            | SimhError::NotARecord { .. }
+            | SimhError::RecordSizeMismatch { .. }
+            | SimhError::NotIndexed { .. } => io::ErrorKind::InvalidInput,
+        }
+    }
+
+    /// Image offset of the object that could not be read, when known.
+    pub fn offset(&self) -> Option<u64> {
+        match *self {
+            SimhError::TruncatedWord { offset }
+            | SimhError::TruncatedRecord { offset, .. }
+            | SimhError::MissingTrailingLength { offset, .. }
+            | SimhError::LengthMismatch { offset, .. }
+            | SimhError::LengthExceedsLimit { offset, .. }
+            | SimhError::IllegalMarker { offset, .. }
+            | SimhError::RecordBeforeBeginningOfTape { offset, .. }
+            | SimhError::PreviousLengthMismatch { offset, .. }
+            | SimhError::SegmentOutOfSequence { offset }
+            | SimhError::UnsupportedCompression { offset, .. }
+            | SimhError::CorruptCompressedBlock { offset }
+            | SimhError::MissingRecordStart { offset } => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// True for malformed image data, as opposed to failures of the underlying I/O
+    /// or of the caller's arguments.
+    pub fn is_format_error(&self) -> bool {
+        self.offset().is_some()
+    }
+}
+
+impl fmt::Display for SimhError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimhError::Io(err) => write!(f, "{err}"),
+            SimhError::TruncatedWord { offset } => {
+                write!(f, "truncated SIMH word at offset {offset}")
+            }
+            SimhError::TruncatedRecord { offset, length } => write!(
+                f,
+                "record of length 0x{length:08X} at offset {offset} is truncated"
+            ),
+            SimhError::MissingTrailingLength { offset, leading } => write!(
+                f,
+                "missing trailing record length for record 0x{leading:08X} at offset {offset}"
+            ),
+            SimhError::LengthMismatch {
+                offset,
+                leading,
+                trailing,
+            } => write!(
+                f,
+                "trailing length 0x{trailing:08X} does not match leading length 0x{leading:08X} at offset {offset}"
+            ),
+            SimhError::LengthExceedsLimit {
+                offset,
+                length,
+                limit,
+            } => write!(
+                f,
+                "record length 0x{length:08X} at offset {offset} exceeds SIMH safety ceiling (0x{limit:08X})"
+            ),
+            SimhError::IllegalMarker { offset, value } => write!(
+                f,
+                "illegal forward half-gap marker 0x{value:08X} at offset {offset}"
+            ),
+            SimhError::RecordBeforeBeginningOfTape { offset, trailing } => write!(
+                f,
+                "record length 0x{trailing:08X} at offset {offset} runs past beginning of tape"
+            ),
+            SimhError::PreviousLengthMismatch {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "previous block length 0x{found:04X} at offset {offset} does not match the preceding block length 0x{expected:04X}"
+            ),
+            SimhError::SegmentOutOfSequence { offset } => {
+                write!(f, "block segment at offset {offset} is out of sequence")
+            }
+            SimhError::UnsupportedCompression { offset, method } => write!(
+                f,
+                "unsupported compression method {method} for block at offset {offset}"
+            ),
+            SimhError::CorruptCompressedBlock { offset } => {
+                write!(
+                    f,
+                    "compressed block at offset {offset} cannot be decompressed"
+                )
+            }
+            SimhError::MissingRecordStart { offset } => write!(
+                f,
+                "data at offset {offset} does not begin with a record start flag"
+            ),
+            SimhError::ClassOutOfRange { class } => {
+                write!(f, "SIMH class 0x{class:X} exceeds 4-bit limit")
+            }
+            SimhError::ValueOutOfRange { value } => write!(
+                f,
+                "value 0x{value:08X} exceeds SIMH extended limit 0x{VALUE_MASK:08X}"
+            ),
+            SimhError::RecordTooLong { length, limit } => write!(
+                f,
+                "record length {length} exceeds safety ceiling 0x{limit:08X}"
+            ),
+            SimhError::InvalidRecordClass { class } => write!(
+                f,
+                "SIMH class 0x{class:X} is reserved for markers and cannot hold a record"
+            ),
+            SimhError::EmptyRecord => write!(
+                f,
+                "a zero-length class 0 record would read back as a tape mark"
+            ),
+            SimhError::InvalidMarkerValue { class, value } => write!(
+                f,
+                "value 0x{value:08X} is not valid for a class 0x{class:X} marker"
+            ),
+            SimhError::Unsupported { format, feature } => {
+                write!(f, "{format} images cannot hold {feature}")
+            }
+            SimhError::NotARecord { offset } => {
+                write!(f, "no record to overwrite at offset {offset}")
+            }
+            SimhError::RecordSizeMismatch {
+                offset,
+                existing,
+                replacement,
+            } => write!(
+                f,
+                "replacement of {replacement} bytes does not fit the {existing}-byte record at offset {offset}"
+            ),
+            SimhError::InvalidIndex(reason) => write!(f, "invalid tape index: {reason}"),
+            SimhError::NotIndexed { file, record } => match record {
+                Some(record) => write!(f, "record {record} of file {file} is not in the index"),
+                None => write!(f, "file {file} is not in the index"),
+            },
+        }
+    }
+}
+
+impl Error for SimhError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SimhError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SimhError {
+    fn from(err: io::Error) -> Self {
+        SimhError::Io(err)
+    }
+}
+
+impl From<SimhError> for io::Error {
+    fn from(err: SimhError) -> Self {
+        match err {
+            SimhError::Io(err) => err,
+            other => io::Error::new(other.kind(), other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_io_error_with_matching_kind() {
+        let err = SimhError::LengthMismatch {
+            offset: 88,
+            leading: 0x14A,
+            trailing: 0xADDE,
+        };
+        assert_eq!(err.offset(), Some(88));
+        let io_err: io::Error = err.into();
+        assert_eq!(io_err.kind(), io::ErrorKind::InvalidData);
+        assert!(io_err.to_string().contains("at offset 88"));
+
+        let inner = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let round_trip: io::Error = SimhError::from(inner).into();
+        assert_eq!(round_trip.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!SimhError::from(round_trip).is_format_error());
+    }
+}
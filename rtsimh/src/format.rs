@@ -0,0 +1,301 @@
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{
+    AwsTapeReader, AwsTapeWriter, E11TapeReader, E11TapeWriter, MAX_RECORD_LENGTH, P7bTapeReader,
+    P7bTapeWriter, RawTapeReader, RawTapeWriter, SimhResult, SimhTapeMark, SimhTapeReader,
+    SimhTapeWriter, TAPE_MARK_WORD, TapeBlockReader, TapeBlockWriter, TpcTapeReader, TpcTapeWriter,
+    aws, decode_word, p7b, padded_length, parse_marker, tpc,
+};
+
+/// Bytes read from the start of an image when probing its format.
+pub const PROBE_LENGTH: usize = 64 * 1024;
+
+/// The tape image containers rtsimh can read and write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeImageFormat {
+    Simh,
+    E11,
+    Tpc,
+    Aws,
+    P7b,
+    Raw,
+}
+
+impl TapeImageFormat {
+    pub const ALL: [TapeImageFormat; 6] = [
+        TapeImageFormat::Simh,
+        TapeImageFormat::E11,
+        TapeImageFormat::Tpc,
+        TapeImageFormat::Aws,
+        TapeImageFormat::P7b,
+        TapeImageFormat::Raw,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TapeImageFormat::Simh => "simh",
+            TapeImageFormat::E11 => "e11",
+            TapeImageFormat::Tpc => "tpc",
+            TapeImageFormat::Aws => "aws",
+            TapeImageFormat::P7b => "p7b",
+            TapeImageFormat::Raw => "raw",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            TapeImageFormat::Simh => "SIMH .tap",
+            TapeImageFormat::E11 => "Ersatz-11 .tap",
+            TapeImageFormat::Tpc => "TPC",
+            TapeImageFormat::Aws => "AWSTAPE/HET",
+            TapeImageFormat::P7b => "SIMH P7B (7-track)",
+            TapeImageFormat::Raw => "raw data",
+        }
+    }
+
+    /// Conventional file extension for images written in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            TapeImageFormat::Simh | TapeImageFormat::E11 => "tap",
+            TapeImageFormat::Tpc => "tpc",
+            TapeImageFormat::Aws => "aws",
+            TapeImageFormat::P7b => "p7b",
+            TapeImageFormat::Raw => "bin",
+        }
+    }
+
+    /// Formats whose file extension is unambiguous. `.tap` is shared by SIMH,
+    /// E11 and others, so it maps to nothing and the contents decide.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "tpc" => Some(TapeImageFormat::Tpc),
+            "aws" | "het" => Some(TapeImageFormat::Aws),
+            "p7b" => Some(TapeImageFormat::P7b),
+            _ => None,
+        }
+    }
+
+    /// Picks the format whose framing `sample`, the start of an image, parses
+    /// cleanly under. Samples shorter than `PROBE_LENGTH` are taken to be the
+    /// whole image. SIMH wins wherever it is indistinguishable from E11, a SIMH
+    /// image that goes bad after some intact records is still SIMH, and anything
+    /// unrecognised is raw data.
+    pub fn probe(sample: &[u8]) -> Self {
+        let complete = sample.len() < PROBE_LENGTH;
+        let simh = walk_simh(sample, complete, true);
+        let e11 = walk_simh(sample, complete, false);
+        if sample.is_empty() || simh == SimhWalk::Clean {
+            TapeImageFormat::Simh
+        } else if e11 == SimhWalk::Clean {
+            TapeImageFormat::E11
+        } else if aws::probe(sample, complete) {
+            TapeImageFormat::Aws
+        } else if simh == SimhWalk::Damaged {
+            TapeImageFormat::Simh
+        } else if e11 == SimhWalk::Damaged {
+            TapeImageFormat::E11
+        } else if tpc::probe(sample, complete) {
+            TapeImageFormat::Tpc
+        } else if p7b::probe(sample) {
+            TapeImageFormat::P7b
+        } else {
+            TapeImageFormat::Raw
+        }
+    }
+
+    /// Probes the image behind `reader` and leaves it positioned at the start.
+    pub fn detect<R: Read + Seek>(reader: &mut R) -> SimhResult<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut sample = Vec::with_capacity(PROBE_LENGTH);
+        reader
+            .by_ref()
+            .take(PROBE_LENGTH as u64)
+            .read_to_end(&mut sample)?;
+        reader.seek(SeekFrom::Start(0))?;
+        Ok(Self::probe(&sample))
+    }
+
+    pub fn open_reader<'a, R: Read + Seek + 'a>(self, reader: R) -> Box<dyn TapeBlockReader + 'a> {
+        match self {
+            TapeImageFormat::Simh => Box::new(SimhTapeReader::new(reader)),
+            TapeImageFormat::E11 => Box::new(E11TapeReader::new(reader)),
+            TapeImageFormat::Tpc => Box::new(TpcTapeReader::new(reader)),
+            TapeImageFormat::Aws => Box::new(AwsTapeReader::new(reader)),
+            TapeImageFormat::P7b => Box::new(P7bTapeReader::new(reader)),
+            TapeImageFormat::Raw => Box::new(RawTapeReader::new(reader)),
+        }
+    }
+
+    /// Opens a writer with each format's defaults; AWS output is uncompressed.
+    pub fn open_writer<'a, W: Write + 'a>(self, writer: W) -> Box<dyn TapeBlockWriter + 'a> {
+        match self {
+            TapeImageFormat::Simh => Box::new(SimhTapeWriter::new(writer)),
+            TapeImageFormat::E11 => Box::new(E11TapeWriter::new(writer)),
+            TapeImageFormat::Tpc => Box::new(TpcTapeWriter::new(writer)),
+            TapeImageFormat::Aws => Box::new(AwsTapeWriter::new(writer)),
+            TapeImageFormat::P7b => Box::new(P7bTapeWriter::new(writer)),
+            TapeImageFormat::Raw => Box::new(RawTapeWriter::new(writer)),
+        }
+    }
+}
+
+impl fmt::Display for TapeImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TapeImageFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown tape image format '{name}'"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SimhWalk {
+    Clean,
+    /// Framing broke down after at least one record checked out.
+    Damaged,
+    Invalid,
+}
+
+/// Walks SIMH framing through `sample`, checking every record's trailing length
+/// against its leading one. A record running past the end is accepted unless
+/// `complete` and nothing before it parsed.
+fn walk_simh(sample: &[u8], complete: bool, padded: bool) -> SimhWalk {
+    let word_at = |offset: usize| {
+        u32::from_le_bytes([
+            sample[offset],
+            sample[offset + 1],
+            sample[offset + 2],
+            sample[offset + 3],
+        ])
+    };
+    let mut offset = 0;
+    let mut objects = 0;
+    let mut records = 0;
+    let broken = |records| {
+        if records > 0 {
+            SimhWalk::Damaged
+        } else {
+            SimhWalk::Invalid
+        }
+    };
+
+    while offset + 4 <= sample.len() {
+        let word = word_at(offset);
+        objects += 1;
+        match parse_marker(offset as u64, word) {
+            Err(_) => return broken(records),
+            Ok(Some(SimhTapeMark::EndOfTape)) => return SimhWalk::Clean,
+            Ok(Some(SimhTapeMark::HalfGapForward)) => {
+                offset += 2;
+                continue;
+            }
+            Ok(Some(_)) => {
+                offset += 4;
+                continue;
+            }
+            Ok(None) if word == TAPE_MARK_WORD => {
+                offset += 4;
+                continue;
+            }
+            Ok(None) => {}
+        }
+
+        let (_, length) = decode_word(word);
+        if length > MAX_RECORD_LENGTH {
+            return broken(records);
+        }
+        let trailing_at = offset + 4 + padded_length(length, padded) as usize;
+        if trailing_at + 4 > sample.len() {
+            return if !complete || objects > 1 {
+                SimhWalk::Clean
+            } else {
+                SimhWalk::Invalid
+            };
+        }
+        if word_at(trailing_at) != word {
+            return broken(records);
+        }
+        records += 1;
+        offset = trailing_at + 4;
+    }
+
+    if !complete || offset == sample.len() {
+        SimhWalk::Clean
+    } else {
+        broken(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy_blocks;
+    use std::io::Cursor;
+
+    #[test]
+    fn probes_each_format_it_writes() {
+        let mut simh = SimhTapeWriter::new(Vec::new());
+        simh.write_record(&[0x31, 0x32, 0x73]).unwrap();
+        simh.write_tape_mark().unwrap();
+        simh.write_record(&[0x4F; 81]).unwrap();
+        simh.write_tape_mark().unwrap();
+        simh.write_tape_mark().unwrap();
+        simh.write_end_of_medium().unwrap();
+        let original = simh.into_inner();
+
+        for format in TapeImageFormat::ALL {
+            if format == TapeImageFormat::Raw {
+                continue;
+            }
+            let mut image = Vec::new();
+            copy_blocks(
+                &mut SimhTapeReader::new(Cursor::new(original.clone())),
+                format.open_writer(&mut image).as_mut(),
+            )
+            .unwrap();
+
+            let mut cursor = Cursor::new(image);
+            assert_eq!(
+                TapeImageFormat::detect(&mut cursor).unwrap(),
+                format,
+                "{format}"
+            );
+
+            let mut back = Vec::new();
+            copy_blocks(
+                format.open_reader(cursor).as_mut(),
+                &mut SimhTapeWriter::new(&mut back),
+            )
+            .unwrap();
+            assert_eq!(back, original, "{format}");
+        }
+
+        assert_eq!(
+            TapeImageFormat::probe(b"just some text that is not a tape image\n"),
+            TapeImageFormat::Raw
+        );
+        assert_eq!(TapeImageFormat::probe(&[]), TapeImageFormat::Simh);
+
+        let mut damaged = original.clone();
+        damaged[14] = 0xAA;
+        assert_eq!(TapeImageFormat::probe(&damaged), TapeImageFormat::Simh);
+        assert_eq!("AWS".parse(), Ok(TapeImageFormat::Aws));
+        assert_eq!(
+            TapeImageFormat::from_extension(Path::new("reel.HET")),
+            Some(TapeImageFormat::Aws)
+        );
+        assert_eq!(TapeImageFormat::from_extension(Path::new("reel.tap")), None);
+    }
+}
@@ -0,0 +1,400 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::{
+    SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader, SimhTapeWriter, encode_word,
+};
+
+/// Read/write handle on an existing image, following SIMH's rules for writes
+/// in the middle of a tape.
+///
+/// Writing a record or tape mark logically erases everything after it, which is
+/// recorded by an end-of-medium marker behind the new object. Overwriting a
+/// record with one of the same size and laying erase gaps over existing data
+/// leave the rest of the tape intact.
+pub struct SimhTapeImage<F> {
+    reader: SimhTapeReader<F>,
+}
+
+impl<F: Read + Write + Seek> SimhTapeImage<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            reader: SimhTapeReader::new(inner),
+        }
+    }
+
+    pub fn position(&mut self) -> SimhResult<u64> {
+        self.reader.position()
+    }
+
+    pub fn set_position(&mut self, offset: u64) -> SimhResult<()> {
+        self.reader.set_position(offset)
+    }
+
+    pub fn rewind(&mut self) -> SimhResult<()> {
+        self.reader.set_position(0)
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.reader.next_block()
+    }
+
+    pub fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.reader.skip_block()
+    }
+
+    pub fn prev_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.reader.prev_block()
+    }
+
+    pub fn into_inner(self) -> F {
+        self.reader.into_inner()
+    }
+
+    pub fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(0, data)
+    }
+
+    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        let offset = self.reader.position()?;
+        self.writer_at(offset)?
+            .write_record_with_class(class, data)?;
+        self.truncate_after_write()
+    }
+
+    pub fn write_tape_mark(&mut self) -> SimhResult<()> {
+        let offset = self.reader.position()?;
+        self.writer_at(offset)?.write_tape_mark()?;
+        self.truncate_after_write()
+    }
+
+    /// Replaces the record at the current position with `data`, keeping its class.
+    pub fn overwrite_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        let offset = self.reader.position()?;
+        let class = self.record_class_for_overwrite(offset, data.len())?;
+        self.overwrite_at(offset, class, data)
+    }
+
+    pub fn overwrite_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        let offset = self.reader.position()?;
+        self.record_class_for_overwrite(offset, data.len())?;
+        self.overwrite_at(offset, class, data)
+    }
+
+    /// Overlays at least `length` bytes from the current position with erase gap
+    /// markers, leaving the tape positioned after the gap.
+    ///
+    /// A gap ending inside a record shortens that record, which keeps the data
+    /// beyond the gap under a new leading length. A gap ending inside a marker, or
+    /// leaving no data of the record behind, is extended to the end of that object.
+    /// When the gap is two bytes short of a whole number of markers it starts with
+    /// a half-gap, so the objects after it stay where they were.
+    pub fn erase_gap(&mut self, length: u64) -> SimhResult<()> {
+        if length == 0 {
+            return Ok(());
+        }
+
+        let start = self.reader.position()?;
+        let mut target = (start + length).next_multiple_of(2);
+        if target - start == 2 {
+            target += 2;
+        }
+
+        let mut end = start;
+        let mut past_data = false;
+        let mut end_of_medium = false;
+        let mut shortened = None;
+
+        self.reader.set_position(start)?;
+        while end < target || end - start == 2 {
+            let block = self.reader.skip_block()?;
+            let object_end = self.reader.position()?;
+            match block {
+                SimhTapeBlock::TapeMark {
+                    kind: SimhTapeMark::EndOfTape,
+                    ..
+                } => {
+                    past_data = true;
+                    end_of_medium = true;
+                    break;
+                }
+                SimhTapeBlock::EndOfStream | SimhTapeBlock::BeginningOfTape => {
+                    past_data = true;
+                    break;
+                }
+                SimhTapeBlock::Record(record) if target < object_end => {
+                    let data_end = record.header.offset + 4 + u64::from(record.header.length);
+                    if target + 4 < data_end {
+                        let length = (data_end - target - 4) as u32;
+                        shortened = Some((record.header.class, length, object_end - 4));
+                        end = target;
+                        break;
+                    }
+                    end = object_end;
+                }
+                _ => end = object_end,
+            }
+        }
+
+        // Beyond the last object there is nothing to keep aligned with.
+        let span = if past_data {
+            (target.max(end) - start).next_multiple_of(4)
+        } else {
+            end - start
+        };
+
+        let mut writer = self.writer_at(start)?;
+        if span % 4 == 2 {
+            writer.write_half_gap_forward()?;
+        }
+        writer.write_erase_gap_markers((span / 4) as usize)?;
+        if end_of_medium {
+            writer.write_end_of_medium()?;
+        }
+
+        if let Some((class, length, trailing_at)) = shortened {
+            let word = encode_word(class, length)?.to_le_bytes();
+            let inner = self.reader.get_mut();
+            inner.write_all(&word)?;
+            inner.seek(SeekFrom::Start(trailing_at))?;
+            inner.write_all(&word)?;
+        }
+
+        self.reader.set_position(start + span)
+    }
+
+    fn writer_at(&mut self, offset: u64) -> SimhResult<SimhTapeWriter<&mut F>> {
+        let inner = self.reader.get_mut();
+        inner.seek(SeekFrom::Start(offset))?;
+        Ok(SimhTapeWriter::new(inner))
+    }
+
+    /// Marks the end of medium after the object just written if older data
+    /// follows it, then leaves the tape positioned before that marker.
+    fn truncate_after_write(&mut self) -> SimhResult<()> {
+        let inner = self.reader.get_mut();
+        let end = inner.stream_position()?;
+        let image_end = inner.seek(SeekFrom::End(0))?;
+        if image_end > end {
+            self.writer_at(end)?.write_end_of_medium()?;
+        }
+        self.reader.set_position(end)
+    }
+
+    fn record_class_for_overwrite(&mut self, offset: u64, replacement: usize) -> SimhResult<u8> {
+        let block = self.reader.skip_block();
+        self.reader.set_position(offset)?;
+
+        match block? {
+            SimhTapeBlock::Record(record) if record.header.length as usize == replacement => {
+                Ok(record.header.class)
+            }
+            SimhTapeBlock::Record(record) => Err(SimhError::RecordSizeMismatch {
+                offset,
+                existing: record.header.length,
+                replacement,
+            }),
+            _ => Err(SimhError::NotARecord { offset }),
+        }
+    }
+
+    fn overwrite_at(&mut self, offset: u64, class: u8, data: &[u8]) -> SimhResult<()> {
+        let mut writer = self.writer_at(offset)?;
+        writer.write_record_with_class(class, data)?;
+        let end = self.reader.get_mut().stream_position()?;
+        self.reader.set_position(end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn image_of(records: &[&[u8]]) -> SimhTapeImage<Cursor<Vec<u8>>> {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        for record in records {
+            writer.write_record(record).unwrap();
+        }
+        writer.write_tape_mark().unwrap();
+        SimhTapeImage::new(Cursor::new(writer.into_inner()))
+    }
+
+    fn expect_record(block: SimhTapeBlock) -> Vec<u8> {
+        match block {
+            SimhTapeBlock::Record(record) => record.data,
+            other => panic!("expected record, got {:?}", other),
+        }
+    }
+
+    fn expect_mark(block: SimhTapeBlock) -> (u64, SimhTapeMark) {
+        match block {
+            SimhTapeBlock::TapeMark { offset, kind } => (offset, kind),
+            other => panic!("expected marker, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn write_in_middle_truncates_rest_of_tape() {
+        let mut image = image_of(&[&[1; 4], &[2; 6], &[3; 8]]);
+        image.skip_block().unwrap();
+        image.write_record(&[9, 9]).unwrap();
+        assert_eq!(image.position().unwrap(), 22);
+
+        image.rewind().unwrap();
+        assert_eq!(expect_record(image.next_block().unwrap()), vec![1; 4]);
+        assert_eq!(expect_record(image.next_block().unwrap()), vec![9, 9]);
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (22, SimhTapeMark::EndOfTape)
+        );
+
+        // Appending carries on from the new end and moves the marker along.
+        image.set_position(22).unwrap();
+        image.write_tape_mark().unwrap();
+        image.rewind().unwrap();
+        image.skip_block().unwrap();
+        image.skip_block().unwrap();
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()).1,
+            SimhTapeMark::Single
+        );
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()).1,
+            SimhTapeMark::EndOfTape
+        );
+    }
+
+    #[test]
+    fn overwrites_same_size_record_in_place() {
+        let mut image = image_of(&[&[1; 3], &[2; 5]]);
+        image.overwrite_record(&[7, 8, 9]).unwrap();
+        assert_eq!(image.position().unwrap(), 12);
+
+        assert!(matches!(
+            image.overwrite_record(&[0; 4]),
+            Err(SimhError::RecordSizeMismatch {
+                offset: 12,
+                existing: 5,
+                replacement: 4
+            })
+        ));
+        image.overwrite_record_with_class(0x8, &[6; 5]).unwrap();
+        assert!(matches!(
+            image.overwrite_record(&[0; 1]),
+            Err(SimhError::NotARecord { offset: 26 })
+        ));
+
+        image.rewind().unwrap();
+        assert_eq!(expect_record(image.next_block().unwrap()), vec![7, 8, 9]);
+        match image.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.header.class, 0x8);
+                assert_eq!(record.data, vec![6; 5]);
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()).1,
+            SimhTapeMark::Single
+        );
+    }
+
+    #[test]
+    fn erase_gap_covers_whole_record_with_half_gap() {
+        let mut image = image_of(&[&[1, 2], &[3, 4], &[5, 6, 7, 8]]);
+        image.set_position(10).unwrap();
+        image.erase_gap(1).unwrap();
+        assert_eq!(image.position().unwrap(), 20);
+
+        image.rewind().unwrap();
+        assert_eq!(expect_record(image.next_block().unwrap()), vec![1, 2]);
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (10, SimhTapeMark::HalfGapForward)
+        );
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (12, SimhTapeMark::EraseGap)
+        );
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (16, SimhTapeMark::EraseGap)
+        );
+        assert_eq!(expect_record(image.next_block().unwrap()), vec![5, 6, 7, 8]);
+
+        // Reading back in reverse realigns onto the first record's trailing length.
+        image.set_position(20).unwrap();
+        expect_mark(image.prev_block().unwrap());
+        expect_mark(image.prev_block().unwrap());
+        assert!(matches!(
+            expect_mark(image.prev_block().unwrap()).1,
+            SimhTapeMark::HalfGapReverse { .. }
+        ));
+        assert_eq!(image.position().unwrap(), 10);
+        assert_eq!(expect_record(image.prev_block().unwrap()), vec![1, 2]);
+    }
+
+    #[test]
+    fn erase_gap_shortens_record_it_ends_in() {
+        let payload: Vec<u8> = (0..20).collect();
+        let mut image = image_of(&[&[1, 2], &payload]);
+        image.set_position(10).unwrap();
+        image.erase_gap(6).unwrap();
+        assert_eq!(image.position().unwrap(), 16);
+
+        image.rewind().unwrap();
+        assert_eq!(expect_record(image.next_block().unwrap()), vec![1, 2]);
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (10, SimhTapeMark::HalfGapForward)
+        );
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (12, SimhTapeMark::EraseGap)
+        );
+        match image.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.header.offset, 16);
+                assert_eq!(record.data, payload[6..].to_vec());
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (38, SimhTapeMark::Single)
+        );
+
+        image.set_position(38).unwrap();
+        assert_eq!(
+            expect_record(image.prev_block().unwrap()),
+            payload[6..].to_vec()
+        );
+    }
+
+    #[test]
+    fn erase_gap_past_end_of_medium_moves_marker() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 4]).unwrap();
+        writer.write_end_of_medium().unwrap();
+        let mut image = SimhTapeImage::new(Cursor::new(writer.into_inner()));
+
+        image.set_position(12).unwrap();
+        image.erase_gap(6).unwrap();
+        assert_eq!(image.position().unwrap(), 20);
+
+        image.rewind().unwrap();
+        image.skip_block().unwrap();
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()).1,
+            SimhTapeMark::EraseGap
+        );
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()).1,
+            SimhTapeMark::EraseGap
+        );
+        assert_eq!(
+            expect_mark(image.next_block().unwrap()),
+            (20, SimhTapeMark::EndOfTape)
+        );
+    }
+}
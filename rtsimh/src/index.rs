@@ -0,0 +1,594 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader};
+
+const INDEX_MAGIC: &[u8; 8] = b"RTSIMHIX";
+const INDEX_VERSION: u32 = 2;
+
+const TAG_RECORD: u8 = 0;
+const TAG_TAPE_MARK: u8 = 1;
+const TAG_DOUBLE_TAPE_MARK: u8 = 2;
+const TAG_END_OF_TAPE: u8 = 3;
+const TAG_ERASE_GAP: u8 = 4;
+const TAG_HALF_GAP_FORWARD: u8 = 5;
+const TAG_HALF_GAP_REVERSE: u8 = 6;
+const TAG_PRIVATE: u8 = 7;
+const TAG_RESERVED: u8 = 8;
+const TAG_DAMAGED: u8 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimhIndexEntryKind {
+    Record {
+        class: u8,
+        length: u32,
+    },
+    Marker(SimhTapeMark),
+    /// A span a recovering reader could not parse and skipped.
+    Damaged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimhIndexEntry {
+    pub offset: u64,
+    pub kind: SimhIndexEntryKind,
+}
+
+impl SimhIndexEntry {
+    pub fn is_record(&self) -> bool {
+        matches!(self.kind, SimhIndexEntryKind::Record { .. })
+    }
+
+    pub fn is_tape_mark(&self) -> bool {
+        matches!(
+            self.kind,
+            SimhIndexEntryKind::Marker(SimhTapeMark::Single | SimhTapeMark::Double)
+        )
+    }
+
+    pub fn is_end_of_medium(&self) -> bool {
+        matches!(
+            self.kind,
+            SimhIndexEntryKind::Marker(SimhTapeMark::EndOfTape)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimhSpaceStop {
+    Completed,
+    TapeMark,
+    EndOfMedium,
+    BeginningOfTape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimhSpaceResult {
+    pub spaced: usize,
+    pub stop: SimhSpaceStop,
+}
+
+/// Offsets of every object in a SIMH image, gathered in a single forward pass
+/// so readers can be positioned without streaming from the start of the tape.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimhTapeIndex {
+    entries: Vec<SimhIndexEntry>,
+    file_starts: Vec<usize>,
+    image_length: u64,
+}
+
+impl SimhTapeIndex {
+    pub fn build<R: Read + Seek>(reader: &mut SimhTapeReader<R>) -> SimhResult<Self> {
+        reader.set_position(0)?;
+        let mut entries = Vec::new();
+
+        loop {
+            match reader.skip_block()? {
+                SimhTapeBlock::Record(record) => entries.push(SimhIndexEntry {
+                    offset: record.header.offset,
+                    kind: SimhIndexEntryKind::Record {
+                        class: record.header.class,
+                        length: record.header.length,
+                    },
+                }),
+                SimhTapeBlock::TapeMark { offset, kind } => {
+                    entries.push(SimhIndexEntry {
+                        offset,
+                        kind: SimhIndexEntryKind::Marker(kind),
+                    });
+                    if kind == SimhTapeMark::EndOfTape {
+                        break;
+                    }
+                }
+                SimhTapeBlock::Resync(resync) => entries.push(SimhIndexEntry {
+                    offset: resync.start,
+                    kind: SimhIndexEntryKind::Damaged,
+                }),
+                SimhTapeBlock::EndOfStream | SimhTapeBlock::BeginningOfTape => break,
+            }
+        }
+
+        let image_length = reader.position()?;
+        reader.set_position(0)?;
+        Ok(Self::from_entries(entries, image_length))
+    }
+
+    fn from_entries(entries: Vec<SimhIndexEntry>, image_length: u64) -> Self {
+        let mut file_starts = vec![0];
+        for (idx, entry) in entries.iter().enumerate() {
+            if entry.is_tape_mark() {
+                file_starts.push(idx + 1);
+            }
+        }
+
+        Self {
+            entries,
+            file_starts,
+            image_length,
+        }
+    }
+
+    pub fn entries(&self) -> &[SimhIndexEntry] {
+        &self.entries
+    }
+
+    /// Number of bytes covered by the scan; compare against the image size to
+    /// detect a stale sidecar.
+    pub fn image_length(&self) -> u64 {
+        self.image_length
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.file_starts.len()
+    }
+
+    pub fn file_offset(&self, file: usize) -> Option<u64> {
+        let &start = self.file_starts.get(file)?;
+        Some(self.offset_of_entry(start))
+    }
+
+    pub fn file_records(&self, file: usize) -> impl Iterator<Item = &SimhIndexEntry> {
+        let start = self
+            .file_starts
+            .get(file)
+            .copied()
+            .unwrap_or(self.entries.len());
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| !entry.is_tape_mark() && !entry.is_end_of_medium())
+            .filter(|entry| entry.is_record())
+    }
+
+    pub fn record_offset(&self, file: usize, record: usize) -> Option<u64> {
+        self.file_records(file)
+            .nth(record)
+            .map(|entry| entry.offset)
+    }
+
+    pub fn seek_to_file<R: Read + Seek>(
+        &self,
+        reader: &mut SimhTapeReader<R>,
+        file: usize,
+    ) -> SimhResult<()> {
+        let offset = self
+            .file_offset(file)
+            .ok_or(SimhError::NotIndexed { file, record: None })?;
+        reader.set_position(offset)
+    }
+
+    pub fn seek_to_record<R: Read + Seek>(
+        &self,
+        reader: &mut SimhTapeReader<R>,
+        file: usize,
+        record: usize,
+    ) -> SimhResult<()> {
+        let offset = self
+            .record_offset(file, record)
+            .ok_or(SimhError::NotIndexed {
+                file,
+                record: Some(record),
+            })?;
+        reader.set_position(offset)
+    }
+
+    /// Spaces forward over up to `count` records, stopping after a tape mark or
+    /// before end of medium as a drive would.
+    pub fn space_records_forward<R: Read + Seek>(
+        &self,
+        reader: &mut SimhTapeReader<R>,
+        count: usize,
+    ) -> SimhResult<SimhSpaceResult> {
+        let mut idx = self.entry_at_or_after(reader.position()?);
+        let mut spaced = 0;
+        let mut stop = SimhSpaceStop::Completed;
+
+        while spaced < count {
+            let Some(entry) = self.entries.get(idx) else {
+                stop = SimhSpaceStop::EndOfMedium;
+                break;
+            };
+            if entry.is_end_of_medium() {
+                stop = SimhSpaceStop::EndOfMedium;
+                break;
+            }
+            idx += 1;
+            if entry.is_tape_mark() {
+                stop = SimhSpaceStop::TapeMark;
+                break;
+            }
+            if entry.is_record() {
+                spaced += 1;
+            }
+        }
+
+        reader.set_position(self.offset_of_entry(idx))?;
+        Ok(SimhSpaceResult { spaced, stop })
+    }
+
+    /// Spaces backward over up to `count` records, stopping after crossing a
+    /// tape mark or at beginning of tape. As with SIMH's reverse space, a
+    /// crossed tape mark leaves the tape on its BOT side.
+    pub fn space_records_reverse<R: Read + Seek>(
+        &self,
+        reader: &mut SimhTapeReader<R>,
+        count: usize,
+    ) -> SimhResult<SimhSpaceResult> {
+        let mut idx = self.entry_at_or_after(reader.position()?);
+        let mut spaced = 0;
+        let mut stop = SimhSpaceStop::Completed;
+
+        while spaced < count {
+            if idx == 0 {
+                stop = SimhSpaceStop::BeginningOfTape;
+                break;
+            }
+            idx -= 1;
+            let entry = &self.entries[idx];
+            if entry.is_tape_mark() {
+                stop = SimhSpaceStop::TapeMark;
+                break;
+            }
+            if entry.is_record() {
+                spaced += 1;
+            }
+        }
+
+        reader.set_position(self.offset_of_entry(idx))?;
+        Ok(SimhSpaceResult { spaced, stop })
+    }
+
+    /// Spaces forward past `count` tape marks, leaving the tape at the start of
+    /// the following file.
+    pub fn space_files_forward<R: Read + Seek>(
+        &self,
+        reader: &mut SimhTapeReader<R>,
+        count: usize,
+    ) -> SimhResult<SimhSpaceResult> {
+        let mut idx = self.entry_at_or_after(reader.position()?);
+        let mut spaced = 0;
+        let mut stop = SimhSpaceStop::Completed;
+
+        while spaced < count {
+            let Some(entry) = self.entries.get(idx) else {
+                stop = SimhSpaceStop::EndOfMedium;
+                break;
+            };
+            if entry.is_end_of_medium() {
+                stop = SimhSpaceStop::EndOfMedium;
+                break;
+            }
+            idx += 1;
+            if entry.is_tape_mark() {
+                spaced += 1;
+            }
+        }
+
+        reader.set_position(self.offset_of_entry(idx))?;
+        Ok(SimhSpaceResult { spaced, stop })
+    }
+
+    /// Spaces backward past `count` tape marks, leaving the tape positioned on
+    /// the BOT side of the last mark crossed.
+    pub fn space_files_reverse<R: Read + Seek>(
+        &self,
+        reader: &mut SimhTapeReader<R>,
+        count: usize,
+    ) -> SimhResult<SimhSpaceResult> {
+        let mut idx = self.entry_at_or_after(reader.position()?);
+        let mut spaced = 0;
+        let mut stop = SimhSpaceStop::Completed;
+
+        while spaced < count {
+            if idx == 0 {
+                stop = SimhSpaceStop::BeginningOfTape;
+                break;
+            }
+            idx -= 1;
+            if self.entries[idx].is_tape_mark() {
+                spaced += 1;
+            }
+        }
+
+        reader.set_position(self.offset_of_entry(idx))?;
+        Ok(SimhSpaceResult { spaced, stop })
+    }
+
+    fn entry_at_or_after(&self, offset: u64) -> usize {
+        self.entries.partition_point(|entry| entry.offset < offset)
+    }
+
+    /// Offset of an entry, or the end of the scanned image past the last one.
+    fn offset_of_entry(&self, idx: usize) -> u64 {
+        self.entries
+            .get(idx)
+            .map_or(self.image_length, |entry| entry.offset)
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> SimhResult<()> {
+        writer.write_all(INDEX_MAGIC)?;
+        writer.write_all(&INDEX_VERSION.to_le_bytes())?;
+        writer.write_all(&self.image_length.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+
+        for entry in &self.entries {
+            let (tag, class, value) = encode_entry_kind(entry.kind);
+            writer.write_all(&entry.offset.to_le_bytes())?;
+            writer.write_all(&[tag, class])?;
+            writer.write_all(&value.to_le_bytes())?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> SimhResult<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            return Err(SimhError::InvalidIndex(
+                "not an rtsimh tape index".to_string(),
+            ));
+        }
+
+        let version = read_u32(&mut reader)?;
+        if !(1..=INDEX_VERSION).contains(&version) {
+            return Err(SimhError::InvalidIndex(format!(
+                "unsupported version {version}"
+            )));
+        }
+
+        let image_length = read_u64(&mut reader)?;
+        let count = read_u64(&mut reader)?;
+        let mut entries = Vec::new();
+        let mut last_offset = None;
+
+        for _ in 0..count {
+            let offset = read_u64(&mut reader)?;
+            let mut tag_class = [0u8; 2];
+            reader.read_exact(&mut tag_class)?;
+            let value = read_u32(&mut reader)?;
+
+            if last_offset.is_some_and(|last| offset <= last) {
+                return Err(SimhError::InvalidIndex(format!(
+                    "entry offset {offset} is out of order"
+                )));
+            }
+            last_offset = Some(offset);
+
+            entries.push(SimhIndexEntry {
+                offset,
+                kind: decode_entry_kind(tag_class[0], tag_class[1], value)?,
+            });
+        }
+
+        Ok(Self::from_entries(entries, image_length))
+    }
+}
+
+fn encode_entry_kind(kind: SimhIndexEntryKind) -> (u8, u8, u32) {
+    match kind {
+        SimhIndexEntryKind::Record { class, length } => (TAG_RECORD, class, length),
+        SimhIndexEntryKind::Marker(mark) => match mark {
+            SimhTapeMark::Single => (TAG_TAPE_MARK, 0, 0),
+            SimhTapeMark::Double => (TAG_DOUBLE_TAPE_MARK, 0, 0),
+            SimhTapeMark::EndOfTape => (TAG_END_OF_TAPE, 0, 0),
+            SimhTapeMark::EraseGap => (TAG_ERASE_GAP, 0, 0),
+            SimhTapeMark::HalfGapForward => (TAG_HALF_GAP_FORWARD, 0, 0),
+            SimhTapeMark::HalfGapReverse { value } => (TAG_HALF_GAP_REVERSE, 0, u32::from(value)),
+            SimhTapeMark::Private { class, value } => (TAG_PRIVATE, class, value),
+            SimhTapeMark::Reserved { class, value } => (TAG_RESERVED, class, value),
+        },
+        SimhIndexEntryKind::Damaged => (TAG_DAMAGED, 0, 0),
+    }
+}
+
+fn decode_entry_kind(tag: u8, class: u8, value: u32) -> SimhResult<SimhIndexEntryKind> {
+    let mark = match tag {
+        TAG_RECORD => {
+            return Ok(SimhIndexEntryKind::Record {
+                class,
+                length: value,
+            });
+        }
+        TAG_DAMAGED => return Ok(SimhIndexEntryKind::Damaged),
+        TAG_TAPE_MARK => SimhTapeMark::Single,
+        TAG_DOUBLE_TAPE_MARK => SimhTapeMark::Double,
+        TAG_END_OF_TAPE => SimhTapeMark::EndOfTape,
+        TAG_ERASE_GAP => SimhTapeMark::EraseGap,
+        TAG_HALF_GAP_FORWARD => SimhTapeMark::HalfGapForward,
+        TAG_HALF_GAP_REVERSE => SimhTapeMark::HalfGapReverse {
+            value: u16::try_from(value).map_err(|_| {
+                SimhError::InvalidIndex(format!(
+                    "reverse half-gap value 0x{value:08X} out of range"
+                ))
+            })?,
+        },
+        TAG_PRIVATE => SimhTapeMark::Private { class, value },
+        TAG_RESERVED => SimhTapeMark::Reserved { class, value },
+        other => {
+            return Err(SimhError::InvalidIndex(format!(
+                "unknown entry tag {other}"
+            )));
+        }
+    };
+    Ok(SimhIndexEntryKind::Marker(mark))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> SimhResult<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> SimhResult<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimhTapeWriter;
+    use std::io::Cursor;
+
+    fn sample_tape() -> Vec<u8> {
+        let mut writer = SimhTapeWriter::new(Cursor::new(Vec::new()));
+        writer.write_record(&[1, 2, 3]).unwrap();
+        writer.write_record(&[4, 5]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_erase_gap_markers(1).unwrap();
+        writer.write_record(&[6; 10]).unwrap();
+        writer.write_bad_record(&[7; 4]).unwrap();
+        writer.write_record(&[8; 6]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_end_of_medium().unwrap();
+        writer.into_inner().into_inner()
+    }
+
+    fn record_at<R: Read + Seek>(reader: &mut SimhTapeReader<R>) -> Vec<u8> {
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => record.data,
+            other => panic!("expected record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn builds_file_and_record_offsets() {
+        let mut reader = SimhTapeReader::new(Cursor::new(sample_tape()));
+        let index = SimhTapeIndex::build(&mut reader).unwrap();
+
+        assert_eq!(index.entries().len(), 10);
+        assert_eq!(index.file_count(), 4);
+        assert_eq!(index.file_records(0).count(), 2);
+        assert_eq!(index.file_records(1).count(), 3);
+        assert_eq!(index.file_records(2).count(), 0);
+        assert_eq!(index.file_offset(1), Some(26));
+        assert_eq!(index.file_offset(3), Some(82));
+
+        index.seek_to_record(&mut reader, 1, 1).unwrap();
+        assert_eq!(record_at(&mut reader), vec![7; 4]);
+
+        index.seek_to_file(&mut reader, 1).unwrap();
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::EraseGap),
+            other => panic!("expected erase gap, got {:?}", other),
+        }
+
+        assert!(matches!(
+            index.seek_to_file(&mut reader, 4),
+            Err(SimhError::NotIndexed {
+                file: 4,
+                record: None
+            })
+        ));
+    }
+
+    #[test]
+    fn spaces_records_and_files() {
+        let mut reader = SimhTapeReader::new(Cursor::new(sample_tape()));
+        let index = SimhTapeIndex::build(&mut reader).unwrap();
+
+        let result = index.space_records_forward(&mut reader, 5).unwrap();
+        assert_eq!(result.spaced, 2);
+        assert_eq!(result.stop, SimhSpaceStop::TapeMark);
+        assert_eq!(reader.position().unwrap(), 26);
+
+        let result = index.space_records_forward(&mut reader, 1).unwrap();
+        assert_eq!(result.spaced, 1);
+        assert_eq!(result.stop, SimhSpaceStop::Completed);
+
+        let result = index.space_records_reverse(&mut reader, 3).unwrap();
+        assert_eq!(result.spaced, 1);
+        assert_eq!(result.stop, SimhSpaceStop::TapeMark);
+        assert_eq!(reader.position().unwrap(), 22);
+
+        let result = index.space_records_reverse(&mut reader, 3).unwrap();
+        assert_eq!(result.spaced, 2);
+        assert_eq!(result.stop, SimhSpaceStop::BeginningOfTape);
+        assert_eq!(reader.position().unwrap(), 0);
+
+        let result = index.space_files_forward(&mut reader, 2).unwrap();
+        assert_eq!(result.spaced, 2);
+        assert_eq!(result.stop, SimhSpaceStop::Completed);
+        assert_eq!(reader.position().unwrap(), index.file_offset(2).unwrap());
+
+        let result = index.space_files_forward(&mut reader, 2).unwrap();
+        assert_eq!(result.spaced, 1);
+        assert_eq!(result.stop, SimhSpaceStop::EndOfMedium);
+
+        let result = index.space_files_reverse(&mut reader, 2).unwrap();
+        assert_eq!(result.spaced, 2);
+        assert_eq!(reader.position().unwrap(), 74);
+    }
+
+    #[test]
+    fn records_damaged_spans_and_unpadded_ends() {
+        let mut tape = SimhTapeWriter::new(Cursor::new(Vec::new()));
+        tape.write_record(&[1; 4]).unwrap();
+        let mut tape = tape.into_inner().into_inner();
+        tape.extend_from_slice(&100u32.to_le_bytes());
+        tape.extend_from_slice(&[2; 10]);
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape)).with_recovery(true);
+        let index = SimhTapeIndex::build(&mut reader).unwrap();
+        assert_eq!(
+            index.entries()[1],
+            SimhIndexEntry {
+                offset: 12,
+                kind: SimhIndexEntryKind::Damaged
+            }
+        );
+        assert_eq!(index.image_length(), 26);
+
+        let mut sidecar = Vec::new();
+        index.write_to(&mut sidecar).unwrap();
+        assert_eq!(
+            SimhTapeIndex::read_from(Cursor::new(sidecar)).unwrap(),
+            index
+        );
+
+        let mut tape = SimhTapeWriter::new(Cursor::new(Vec::new())).without_padding();
+        tape.write_record(&[3; 5]).unwrap();
+        let tape = tape.into_inner().into_inner();
+        let mut reader = SimhTapeReader::new(Cursor::new(tape)).without_padding();
+        let index = SimhTapeIndex::build(&mut reader).unwrap();
+        let result = index.space_records_forward(&mut reader, 2).unwrap();
+        assert_eq!(result.stop, SimhSpaceStop::EndOfMedium);
+        assert_eq!(reader.position().unwrap(), 13);
+    }
+
+    #[test]
+    fn round_trips_through_sidecar() {
+        let mut reader = SimhTapeReader::new(Cursor::new(sample_tape()));
+        let index = SimhTapeIndex::build(&mut reader).unwrap();
+
+        let mut sidecar = Vec::new();
+        index.write_to(&mut sidecar).unwrap();
+        let restored = SimhTapeIndex::read_from(Cursor::new(sidecar)).unwrap();
+        assert_eq!(restored, index);
+
+        let err = SimhTapeIndex::read_from(Cursor::new(b"garbage!".to_vec())).unwrap_err();
+        assert!(matches!(err, SimhError::InvalidIndex(_)));
+    }
+}
@@ -1,5 +1,36 @@
 use std::io::{self, Read, Seek, SeekFrom, Write};
 
+mod aws;
+mod description;
+mod e11;
+mod error;
+mod format;
+mod image;
+mod index;
+mod p7b;
+mod raw;
+mod slice;
+mod stream;
+mod tpc;
+mod traits;
+
+pub use aws::{AwsTapeReader, AwsTapeWriter, HetCompression};
+pub use description::TapeDescription;
+pub use e11::{E11TapeReader, E11TapeWriter};
+pub use error::{SimhError, SimhResult};
+pub use format::{PROBE_LENGTH, TapeImageFormat};
+pub use image::SimhTapeImage;
+pub use index::{
+    SimhIndexEntry, SimhIndexEntryKind, SimhSpaceResult, SimhSpaceStop, SimhTapeIndex,
+};
+pub use p7b::{P7bParity, P7bTapeReader, P7bTapeWriter};
+pub use raw::{DEFAULT_RAW_BLOCK_SIZE, RawTapeReader, RawTapeWriter};
+pub use slice::{SimhTapeBlockRef, SimhTapeRecordRef, SimhTapeSliceReader};
+pub use stream::SimhTapeStreamReader;
+pub use tpc::{TpcTapeReader, TpcTapeWriter};
+use traits::write_tape_mark_only;
+pub use traits::{TapeBlockReader, TapeBlockWriter, copy_blocks};
+
 pub const VERSION: &str = "1.0.0";
 pub const AUTHOR: &str = "ACMS (Australia Computer Museum Society)";
 
@@ -13,27 +44,26 @@ const TAPE_MARK_WORD: u32 = 0x0000_0000;
 const ERASE_GAP_WORD: u32 = 0xFFFF_FFFE;
 const END_OF_MEDIUM_WORD: u32 = 0xFFFF_FFFF;
 const FORWARD_HALF_GAP_WORD: u32 = 0xFFFE_FFFF;
+/// A forward half-gap is the two `FFFF` bytes left in front of an erase gap
+/// marker when a gap covers a span two bytes short of whole markers.
+const HALF_GAP_LENGTH: u64 = 2;
 const FORWARD_HALF_GAP_ILLEGAL_START: u32 = 0xFFFE_0000;
 const FORWARD_HALF_GAP_ILLEGAL_END: u32 = 0xFFFE_FFFE;
 const REVERSE_HALF_GAP_START: u32 = 0xFFFF_0000;
 const REVERSE_HALF_GAP_END: u32 = 0xFFFF_FFFD;
 
+const BAD_RECORD_CLASS: u8 = 0x8;
 const PRIVATE_MARKER_CLASS: u8 = 0x7;
+pub const TAPE_DESCRIPTION_CLASS: u8 = 0xE;
 const RESERVED_MARKER_CLASS: u8 = 0xF;
 
-fn encode_word(class: u8, value: u32) -> io::Result<u32> {
+fn encode_word(class: u8, value: u32) -> SimhResult<u32> {
     if class > 0xF {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            format!("SIMH class 0x{class:X} exceeds 4-bit limit"),
-        ));
+        return Err(SimhError::ClassOutOfRange { class });
     }
 
     if value > VALUE_MASK {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            format!("record length 0x{value:08X} exceeds SIMH extended limit 0x{VALUE_MASK:08X}"),
-        ));
+        return Err(SimhError::ValueOutOfRange { value });
     }
 
     Ok(((class as u32) << CLASS_SHIFT) | (value & VALUE_MASK))
@@ -46,51 +76,228 @@ fn decode_word(word: u32) -> (u8, u32) {
     )
 }
 
+/// Reads the word at `offset`; `offset` is only used to describe a truncated word.
+fn read_word_from<R: Read>(reader: &mut R, offset: u64) -> SimhResult<Option<u32>> {
+    let mut buf = [0u8; 4];
+    let mut read = 0;
+    while read < buf.len() {
+        let n = reader.read(&mut buf[read..])?;
+        if n == 0 {
+            return if read == 0 {
+                Ok(None)
+            } else {
+                Err(SimhError::TruncatedWord { offset })
+            };
+        }
+        read += n;
+    }
+    Ok(Some(u32::from_le_bytes(buf)))
+}
+
+/// Fills `buf` with the payload of the record at `offset`, reporting a short
+/// read as a truncated record rather than a bare I/O error.
+fn read_payload_from<R: Read>(
+    reader: &mut R,
+    buf: &mut [u8],
+    offset: u64,
+    length: u32,
+) -> SimhResult<()> {
+    reader.read_exact(buf).map_err(|err| {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            SimhError::TruncatedRecord { offset, length }
+        } else {
+            SimhError::Io(err)
+        }
+    })
+}
+
+fn ensure_length_within_limit(offset: u64, length: u32, limit: u32) -> SimhResult<()> {
+    if length > limit {
+        return Err(SimhError::LengthExceedsLimit {
+            offset,
+            length,
+            limit,
+        });
+    }
+    Ok(())
+}
+
+fn parse_marker(offset: u64, raw: u32) -> SimhResult<Option<SimhTapeMark>> {
+    match raw {
+        END_OF_MEDIUM_WORD => return Ok(Some(SimhTapeMark::EndOfTape)),
+        ERASE_GAP_WORD => return Ok(Some(SimhTapeMark::EraseGap)),
+        FORWARD_HALF_GAP_WORD => {
+            return Ok(Some(SimhTapeMark::HalfGapForward));
+        }
+        word if (FORWARD_HALF_GAP_ILLEGAL_START..=FORWARD_HALF_GAP_ILLEGAL_END).contains(&word) => {
+            return Err(SimhError::IllegalMarker {
+                offset,
+                value: word,
+            });
+        }
+        word if (REVERSE_HALF_GAP_START..=REVERSE_HALF_GAP_END).contains(&word) => {
+            return Ok(Some(SimhTapeMark::HalfGapReverse { value: word as u16 }));
+        }
+        _ => {}
+    }
+
+    let (class, value) = decode_word(raw);
+
+    if class == PRIVATE_MARKER_CLASS {
+        return Ok(Some(SimhTapeMark::Private { class, value }));
+    }
+
+    if class == RESERVED_MARKER_CLASS {
+        return Ok(Some(SimhTapeMark::Reserved { class, value }));
+    }
+
+    Ok(None)
+}
+
+/// A leading word read forward, other than a tape mark.
+enum ForwardWord {
+    Marker(SimhTapeMark),
+    Record { class: u8, length: u32 },
+}
+
+/// Decodes a leading word read forward into a marker or a record header. Tape
+/// marks are left to the caller, since each reader looks ahead for a double
+/// mark its own way.
+fn decode_forward(offset: u64, word: u32, limit: u32) -> SimhResult<ForwardWord> {
+    if let Some(kind) = parse_marker(offset, word)? {
+        return Ok(ForwardWord::Marker(kind));
+    }
+    let (class, length) = decode_word(word);
+    ensure_length_within_limit(offset, length, limit)?;
+    Ok(ForwardWord::Record { class, length })
+}
+
+/// Checks the trailing length of the record at `offset` against its leading one.
+fn check_trailing_length(offset: u64, leading: u32, trailing: Option<u32>) -> SimhResult<()> {
+    match trailing {
+        None => Err(SimhError::MissingTrailingLength { offset, leading }),
+        Some(trailing) if trailing != leading => Err(SimhError::LengthMismatch {
+            offset,
+            leading,
+            trailing,
+        }),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Bytes a record payload occupies in the image. SIMH pads odd lengths to a
+/// word boundary; E11 does not.
+fn padded_length(length: u32, padded: bool) -> u64 {
+    u64::from(length) + u64::from(padded && !length.is_multiple_of(2))
+}
+
+/// Recovery heuristic shared by the readers: an offset is a plausible resume point
+/// if it holds a record whose leading and trailing lengths agree, or a tape mark
+/// followed by such a record, another tape mark, end of medium or end of image.
+fn is_plausible_object(
+    word_at: &mut impl FnMut(u64) -> SimhResult<u32>,
+    offset: u64,
+    word: u32,
+    image_end: u64,
+    limit: u32,
+    padded: bool,
+) -> SimhResult<bool> {
+    if word == TAPE_MARK_WORD {
+        let next = offset + 4;
+        if next + 4 > image_end {
+            return Ok(next == image_end);
+        }
+        let following = word_at(next)?;
+        return Ok(following == TAPE_MARK_WORD
+            || following == END_OF_MEDIUM_WORD
+            || is_plausible_record(word_at, next, following, image_end, limit, padded)?);
+    }
+
+    is_plausible_record(word_at, offset, word, image_end, limit, padded)
+}
+
+fn is_plausible_record(
+    word_at: &mut impl FnMut(u64) -> SimhResult<u32>,
+    offset: u64,
+    word: u32,
+    image_end: u64,
+    limit: u32,
+    padded: bool,
+) -> SimhResult<bool> {
+    let (class, length) = decode_word(word);
+    if class == PRIVATE_MARKER_CLASS
+        || class == RESERVED_MARKER_CLASS
+        || length > limit
+        || (length == 0 && class == 0)
+    {
+        return Ok(false);
+    }
+
+    let trailing_at = offset + 4 + padded_length(length, padded);
+    if trailing_at + 4 > image_end {
+        return Ok(false);
+    }
+    Ok(word_at(trailing_at)? == word)
+}
+
 pub struct SimhTapeWriter<W: Write> {
     writer: W,
+    padded: bool,
 }
 
 impl<W: Write> SimhTapeWriter<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self {
+            writer,
+            padded: true,
+        }
     }
 
-    fn normalize_length(&self, data_len: usize) -> io::Result<u32> {
-        let len = u32::try_from(data_len).map_err(|_| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("record length {} does not fit in 32 bits", data_len),
-            )
-        })?;
+    pub(crate) fn without_padding(mut self) -> Self {
+        self.padded = false;
+        self
+    }
 
-        if len > MAX_RECORD_LENGTH {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!(
-                    "record length 0x{len:08X} exceeds safety ceiling 0x{MAX_RECORD_LENGTH:08X}"
-                ),
-            ));
+    fn normalize_length(&self, data_len: usize) -> SimhResult<u32> {
+        match u32::try_from(data_len) {
+            Ok(len) if len <= MAX_RECORD_LENGTH => Ok(len),
+            _ => Err(SimhError::RecordTooLong {
+                length: data_len,
+                limit: MAX_RECORD_LENGTH,
+            }),
         }
-
-        Ok(len)
     }
 
-    pub fn write_record(&mut self, data: &[u8]) -> io::Result<()> {
+    pub fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
         self.write_record_with_class(0, data)
     }
 
-    pub fn write_bad_record(&mut self, data: &[u8]) -> io::Result<()> {
-        self.write_record_with_class(0x8, data)
+    pub fn write_bad_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(BAD_RECORD_CLASS, data)
+    }
+
+    /// Writes a class E tape description record.
+    pub fn write_tape_description(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(TAPE_DESCRIPTION_CLASS, data)
     }
 
-    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> io::Result<()> {
+    /// Classes 7 and F are marker classes, so records using them are rejected,
+    /// as is an empty class 0 record, which would be indistinguishable from a tape mark.
+    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        if class == PRIVATE_MARKER_CLASS || class == RESERVED_MARKER_CLASS {
+            return Err(SimhError::InvalidRecordClass { class });
+        }
+        if class == 0 && data.is_empty() {
+            return Err(SimhError::EmptyRecord);
+        }
+
         let len = self.normalize_length(data.len())?;
         let word = encode_word(class, len)?;
 
         self.writer.write_all(&word.to_le_bytes())?;
         self.writer.write_all(data)?;
 
-        if len % 2 != 0 {
+        if self.padded && !len.is_multiple_of(2) {
             self.writer.write_all(&[0])?;
         }
 
@@ -98,24 +305,78 @@ impl<W: Write> SimhTapeWriter<W> {
         Ok(())
     }
 
-    pub fn write_tape_mark(&mut self) -> io::Result<()> {
-        self.writer.write_all(&TAPE_MARK_WORD.to_le_bytes())
+    pub fn write_tape_mark(&mut self) -> SimhResult<()> {
+        self.writer.write_all(&TAPE_MARK_WORD.to_le_bytes())?;
+        Ok(())
     }
 
-    pub fn write_end_of_medium(&mut self) -> io::Result<()> {
-        self.writer.write_all(&END_OF_MEDIUM_WORD.to_le_bytes())
+    pub fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        self.writer.write_all(&END_OF_MEDIUM_WORD.to_le_bytes())?;
+        Ok(())
     }
 
-    pub fn write_erase_gap_markers(&mut self, count: usize) -> io::Result<()> {
+    pub fn write_erase_gap_markers(&mut self, count: usize) -> SimhResult<()> {
         for _ in 0..count {
             self.writer.write_all(&ERASE_GAP_WORD.to_le_bytes())?;
         }
         Ok(())
     }
 
-    pub fn write_private_marker(&mut self, value: u32) -> io::Result<()> {
+    /// Writes the two bytes of a forward half-gap. Together with the erase gap
+    /// marker that must follow, they read back as `FFFEFFFF`.
+    pub fn write_half_gap_forward(&mut self) -> SimhResult<()> {
+        self.writer
+            .write_all(&FORWARD_HALF_GAP_WORD.to_le_bytes()[..HALF_GAP_LENGTH as usize])?;
+        Ok(())
+    }
+
+    pub fn write_private_marker(&mut self, value: u32) -> SimhResult<()> {
         let word = encode_word(PRIVATE_MARKER_CLASS, value)?;
-        self.writer.write_all(&word.to_le_bytes())
+        self.writer.write_all(&word.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Class F values from 0x0FFE0000 upwards encode the gap and end-of-medium
+    /// markers, so they are refused here.
+    pub fn write_reserved_marker(&mut self, value: u32) -> SimhResult<()> {
+        let word = encode_word(RESERVED_MARKER_CLASS, value)?;
+        if word >= FORWARD_HALF_GAP_ILLEGAL_START {
+            return Err(SimhError::InvalidMarkerValue {
+                class: RESERVED_MARKER_CLASS,
+                value,
+            });
+        }
+        self.writer.write_all(&word.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Writes any marker the readers can report. A `Double` tape mark is the
+    /// second of a pair, so it is written as a single tape mark. A reverse
+    /// half-gap only appears when reading backwards into an erased record, so
+    /// it is refused; `SimhTapeImage::erase_gap` produces one where it belongs.
+    pub fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        match mark {
+            SimhTapeMark::Single | SimhTapeMark::Double => self.write_tape_mark(),
+            SimhTapeMark::EndOfTape => self.write_end_of_medium(),
+            SimhTapeMark::EraseGap => self.write_erase_gap_markers(1),
+            SimhTapeMark::HalfGapForward => self.write_half_gap_forward(),
+            SimhTapeMark::HalfGapReverse { .. } => Err(SimhError::Unsupported {
+                format: "SIMH",
+                feature: "standalone reverse half-gaps",
+            }),
+            SimhTapeMark::Private { class, value } => {
+                if class != PRIVATE_MARKER_CLASS {
+                    return Err(SimhError::InvalidMarkerValue { class, value });
+                }
+                self.write_private_marker(value)
+            }
+            SimhTapeMark::Reserved { class, value } => {
+                if class != RESERVED_MARKER_CLASS {
+                    return Err(SimhError::InvalidMarkerValue { class, value });
+                }
+                self.write_reserved_marker(value)
+            }
+        }
     }
 
     pub fn into_inner(self) -> W {
@@ -130,7 +391,7 @@ pub enum SimhTapeMark {
     EndOfTape,
     EraseGap,
     HalfGapForward,
-    HalfGapReverse,
+    HalfGapReverse { value: u16 },
     Private { class: u8, value: u32 },
     Reserved { class: u8, value: u32 },
 }
@@ -149,17 +410,31 @@ pub struct SimhTapeRecord {
     pub data: Vec<u8>,
 }
 
+/// A damaged span skipped by a reader in recovery mode. `start` is where the
+/// failed object began and `end` is where a plausible object was found again.
+#[derive(Debug)]
+pub struct SimhResync {
+    pub start: u64,
+    pub end: u64,
+    pub error: SimhError,
+}
+
 #[derive(Debug)]
 pub enum SimhTapeBlock {
     Record(SimhTapeRecord),
     TapeMark { offset: u64, kind: SimhTapeMark },
     EndOfStream,
+    BeginningOfTape,
+    Resync(SimhResync),
 }
 
 pub struct SimhTapeReader<R> {
     reader: R,
     safety_limit: u32,
     pending_double: bool,
+    pending_reverse_double: bool,
+    recovery: bool,
+    padded: bool,
 }
 
 impl<R: Read + Seek> SimhTapeReader<R> {
@@ -168,55 +443,65 @@ impl<R: Read + Seek> SimhTapeReader<R> {
             reader,
             safety_limit: MAX_RECORD_LENGTH,
             pending_double: false,
+            pending_reverse_double: false,
+            recovery: false,
+            padded: true,
         }
     }
 
+    pub(crate) fn without_padding(mut self) -> Self {
+        self.padded = false;
+        self
+    }
+
     pub fn with_limit(mut self, limit: u32) -> Self {
         self.safety_limit = limit.min(VALUE_MASK);
         self
     }
 
-    fn read_word(&mut self) -> io::Result<Option<u32>> {
-        let mut buf = [0u8; 4];
-        let mut read = 0;
-        while read < buf.len() {
-            let n = self.reader.read(&mut buf[read..])?;
-            if n == 0 {
-                return if read == 0 {
-                    Ok(None)
-                } else {
-                    Err(io::Error::new(
-                        io::ErrorKind::UnexpectedEof,
-                        "truncated SIMH word",
-                    ))
-                };
-            }
-            read += n;
-        }
-        Ok(Some(u32::from_le_bytes(buf)))
+    /// In recovery mode, malformed data no longer fails `next_block`/`skip_block`.
+    /// The reader scans forward to the next plausible object and reports the
+    /// skipped range as `SimhTapeBlock::Resync`.
+    pub fn with_recovery(mut self, enabled: bool) -> Self {
+        self.recovery = enabled;
+        self
     }
 
-    fn ensure_length_within_bounds(&self, length: u32) -> io::Result<()> {
-        if length > self.safety_limit {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "record length 0x{length:08X} exceeds SIMH safety ceiling (0x{:08X})",
-                    self.safety_limit
-                ),
-            ));
-        }
+    pub fn position(&mut self) -> SimhResult<u64> {
+        Ok(self.reader.stream_position()?)
+    }
+
+    pub fn set_position(&mut self, offset: u64) -> SimhResult<()> {
+        self.pending_double = false;
+        self.pending_reverse_double = false;
+        self.reader.seek(SeekFrom::Start(offset))?;
         Ok(())
     }
 
-    fn consume_tape_mark_kind(&mut self) -> io::Result<SimhTapeMark> {
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    fn read_word(&mut self, offset: u64) -> SimhResult<Option<u32>> {
+        read_word_from(&mut self.reader, offset)
+    }
+
+    fn ensure_length_within_bounds(&self, offset: u64, length: u32) -> SimhResult<()> {
+        ensure_length_within_limit(offset, length, self.safety_limit)
+    }
+
+    fn consume_tape_mark_kind(&mut self) -> SimhResult<SimhTapeMark> {
         if self.pending_double {
             self.pending_double = false;
             return Ok(SimhTapeMark::Double);
         }
 
         let peek_pos = self.reader.stream_position()?;
-        match self.read_word()? {
+        match self.read_word(peek_pos)? {
             Some(word) if word == TAPE_MARK_WORD => {
                 self.pending_double = true;
                 self.reader.seek(SeekFrom::Start(peek_pos))?;
@@ -230,93 +515,205 @@ impl<R: Read + Seek> SimhTapeReader<R> {
         }
     }
 
-    fn try_parse_marker(&mut self, raw: u32) -> io::Result<Option<SimhTapeMark>> {
-        match raw {
-            END_OF_MEDIUM_WORD => return Ok(Some(SimhTapeMark::EndOfTape)),
-            ERASE_GAP_WORD => return Ok(Some(SimhTapeMark::EraseGap)),
-            FORWARD_HALF_GAP_WORD => {
-                return Ok(Some(SimhTapeMark::HalfGapForward));
-            }
-            word if (FORWARD_HALF_GAP_ILLEGAL_START..=FORWARD_HALF_GAP_ILLEGAL_END)
-                .contains(&word) =>
-            {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!("illegal forward half-gap marker 0x{word:08X}"),
-                ));
-            }
-            word if (REVERSE_HALF_GAP_START..=REVERSE_HALF_GAP_END).contains(&word) => {
-                return Ok(Some(SimhTapeMark::HalfGapReverse));
-            }
-            _ => {}
-        }
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.read_forward_recovering(true)
+    }
+
+    /// Like `next_block`, but seeks over record payloads instead of reading them.
+    /// Records are returned with an empty `data` vector.
+    pub fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.read_forward_recovering(false)
+    }
 
-        let (class, value) = decode_word(raw);
+    fn read_forward_recovering(&mut self, read_payload: bool) -> SimhResult<SimhTapeBlock> {
+        if !self.recovery {
+            return self.read_forward(read_payload);
+        }
 
-        if class == PRIVATE_MARKER_CLASS {
-            return Ok(Some(SimhTapeMark::Private { class, value }));
+        let start = self.reader.stream_position()?;
+        match self.read_forward(read_payload) {
+            Err(error) if error.is_format_error() => {
+                self.pending_double = false;
+                let end = self.resynchronize(start + 1)?;
+                Ok(SimhTapeBlock::Resync(SimhResync { start, end, error }))
+            }
+            other => other,
         }
+    }
+
+    /// Scans forward from `from` for the next offset holding either a record whose
+    /// leading and trailing lengths agree, or a tape mark followed by such a record,
+    /// another tape mark or the end of the image. Leaves the reader there.
+    fn resynchronize(&mut self, from: u64) -> SimhResult<u64> {
+        const WINDOW: usize = 64 * 1024;
+
+        let image_end = self.reader.seek(SeekFrom::End(0))?;
+        let mut window = vec![0u8; WINDOW];
+        let mut base = from;
+
+        while base + 4 <= image_end {
+            self.reader.seek(SeekFrom::Start(base))?;
+            let available = ((image_end - base) as usize).min(WINDOW);
+            self.reader.read_exact(&mut window[..available])?;
+
+            for idx in 0..=available - 4 {
+                let candidate = base + idx as u64;
+                let word = u32::from_le_bytes(window[idx..idx + 4].try_into().unwrap());
+                let (limit, padded) = (self.safety_limit, self.padded);
+                let mut word_at = |offset| self.read_word_at(offset);
+                if is_plausible_object(&mut word_at, candidate, word, image_end, limit, padded)? {
+                    self.reader.seek(SeekFrom::Start(candidate))?;
+                    return Ok(candidate);
+                }
+            }
 
-        if class == RESERVED_MARKER_CLASS {
-            return Ok(Some(SimhTapeMark::Reserved { class, value }));
+            // Overlap windows so words straddling the boundary are still examined.
+            base += (available - 3) as u64;
         }
 
-        Ok(None)
+        self.reader.seek(SeekFrom::Start(image_end))?;
+        Ok(image_end)
     }
 
-    pub fn next_block(&mut self) -> io::Result<SimhTapeBlock> {
-        loop {
-            let offset = self.reader.stream_position()?;
-            let Some(word) = self.read_word()? else {
-                return Ok(SimhTapeBlock::EndOfStream);
-            };
+    fn read_forward(&mut self, read_payload: bool) -> SimhResult<SimhTapeBlock> {
+        self.pending_reverse_double = false;
 
-            if word == TAPE_MARK_WORD {
-                let kind = self.consume_tape_mark_kind()?;
-                return Ok(SimhTapeBlock::TapeMark { offset, kind });
-            }
+        let offset = self.reader.stream_position()?;
+        let Some(word) = self.read_word(offset)? else {
+            return Ok(SimhTapeBlock::EndOfStream);
+        };
+
+        if word == TAPE_MARK_WORD {
+            let kind = self.consume_tape_mark_kind()?;
+            return Ok(SimhTapeBlock::TapeMark { offset, kind });
+        }
 
-            if let Some(kind) = self.try_parse_marker(word)? {
+        let (class, length) = match decode_forward(offset, word, self.safety_limit)? {
+            ForwardWord::Marker(kind) => {
+                if kind == SimhTapeMark::HalfGapForward {
+                    self.reader
+                        .seek(SeekFrom::Start(offset + HALF_GAP_LENGTH))?;
+                }
                 return Ok(SimhTapeBlock::TapeMark { offset, kind });
             }
+            ForwardWord::Record { class, length } => (class, length),
+        };
 
-            let (class, length) = decode_word(word);
-            self.ensure_length_within_bounds(length)?;
-
-            let mut data = vec![0u8; length as usize];
-            self.reader.read_exact(&mut data)?;
+        let padded = padded_length(length, self.padded);
+        let mut data = Vec::new();
+        if read_payload {
+            data.resize(length as usize, 0);
+            read_payload_from(&mut self.reader, &mut data, offset, length)?;
 
-            if length % 2 != 0 {
+            if padded > u64::from(length) {
                 let mut pad = [0u8; 1];
-                self.reader.read_exact(&mut pad)?;
+                read_payload_from(&mut self.reader, &mut pad, offset, length)?;
             }
+        } else {
+            self.reader.seek(SeekFrom::Current(padded as i64))?;
+        }
 
-            let trailing = self.read_word()?.ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "missing trailing record length",
-                )
-            })?;
-
-            if trailing != word {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!(
-                        "trailing length 0x{trailing:08X} does not match leading length 0x{word:08X}"
-                    ),
-                ));
-            }
+        let trailing_at = offset + 4 + padded;
+        check_trailing_length(offset, word, self.read_word(trailing_at)?)?;
+
+        Ok(SimhTapeBlock::Record(SimhTapeRecord {
+            header: SimhTapeRecordHeader {
+                offset,
+                class,
+                length,
+                trailing_length: Some(length),
+            },
+            data,
+        }))
+    }
 
-            return Ok(SimhTapeBlock::Record(SimhTapeRecord {
-                header: SimhTapeRecordHeader {
+    /// Reads the object immediately preceding the current position and leaves
+    /// the tape positioned before it, mirroring a drive's read-reverse.
+    pub fn prev_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.pending_double = false;
+
+        let end = self.reader.stream_position()?;
+        if end == 0 {
+            self.pending_reverse_double = false;
+            return Ok(SimhTapeBlock::BeginningOfTape);
+        }
+
+        let offset = end
+            .checked_sub(4)
+            .ok_or(SimhError::TruncatedWord { offset: 0 })?;
+        let word = self.read_word_at(offset)?;
+
+        if word == TAPE_MARK_WORD {
+            let kind = self.consume_reverse_tape_mark_kind(offset)?;
+            self.reader.seek(SeekFrom::Start(offset))?;
+            return Ok(SimhTapeBlock::TapeMark { offset, kind });
+        }
+        self.pending_reverse_double = false;
+
+        if let Some(kind) = parse_marker(offset, word)? {
+            // A reverse half-gap is half an overwritten gap marker plus half of the
+            // preceding trailing length, so realign onto the length word.
+            let position = if matches!(kind, SimhTapeMark::HalfGapReverse { .. }) {
+                end - 2
+            } else {
+                offset
+            };
+            self.reader.seek(SeekFrom::Start(position))?;
+            return Ok(SimhTapeBlock::TapeMark { offset, kind });
+        }
+
+        let (class, length) = decode_word(word);
+        self.ensure_length_within_bounds(offset, length)?;
+
+        let padded = padded_length(length, self.padded);
+        let start =
+            offset
+                .checked_sub(padded + 4)
+                .ok_or(SimhError::RecordBeforeBeginningOfTape {
                     offset,
-                    class,
-                    length,
-                    trailing_length: Some(length),
-                },
-                data,
-            }));
+                    trailing: word,
+                })?;
+
+        let leading = self.read_word_at(start)?;
+        if leading != word {
+            return Err(SimhError::LengthMismatch {
+                offset: start,
+                leading,
+                trailing: word,
+            });
+        }
+
+        let mut data = vec![0u8; length as usize];
+        read_payload_from(&mut self.reader, &mut data, start, length)?;
+        self.reader.seek(SeekFrom::Start(start))?;
+
+        Ok(SimhTapeBlock::Record(SimhTapeRecord {
+            header: SimhTapeRecordHeader {
+                offset: start,
+                class,
+                length,
+                trailing_length: Some(length),
+            },
+            data,
+        }))
+    }
+
+    fn read_word_at(&mut self, offset: u64) -> SimhResult<u32> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.read_word(offset)?
+            .ok_or(SimhError::TruncatedWord { offset })
+    }
+
+    fn consume_reverse_tape_mark_kind(&mut self, offset: u64) -> SimhResult<SimhTapeMark> {
+        if self.pending_reverse_double {
+            self.pending_reverse_double = false;
+            return Ok(SimhTapeMark::Double);
+        }
+
+        if offset >= 4 && self.read_word_at(offset - 4)? == TAPE_MARK_WORD {
+            self.pending_reverse_double = true;
         }
+        Ok(SimhTapeMark::Single)
     }
 }
 
@@ -370,7 +767,7 @@ mod tests {
         let word = encode_word(class, len).unwrap();
         buf.extend_from_slice(&word.to_le_bytes());
         buf.extend_from_slice(payload);
-        if len % 2 != 0 {
+        if !len.is_multiple_of(2) {
             buf.push(0);
         }
         buf.extend_from_slice(&word.to_le_bytes());
@@ -436,7 +833,7 @@ mod tests {
     #[test]
     fn handles_half_gap_and_erase_markers() {
         let mut tape = Vec::new();
-        tape.extend_from_slice(&FORWARD_HALF_GAP_WORD.to_le_bytes());
+        tape.extend_from_slice(&[0xFF, 0xFF]);
         tape.extend_from_slice(&ERASE_GAP_WORD.to_le_bytes());
 
         let mut reader = SimhTapeReader::new(Cursor::new(tape));
@@ -445,11 +842,273 @@ mod tests {
             SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::HalfGapForward),
             other => panic!("expected half-gap marker, got {:?}", other),
         }
+        assert_eq!(reader.position().unwrap(), 2);
 
         match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::EraseGap);
+                assert_eq!(offset, 2);
+            }
+            other => panic!("expected erase-gap marker, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_blocks_in_reverse_to_bot() {
+        let mut tape = Vec::new();
+        emit_record(&mut tape, 0, &[0x01, 0x02, 0x03]);
+        tape.extend_from_slice(&TAPE_MARK_WORD.to_le_bytes());
+        emit_record(&mut tape, 0x8, &[0xAA, 0xBB]);
+        tape.extend_from_slice(&TAPE_MARK_WORD.to_le_bytes());
+        tape.extend_from_slice(&TAPE_MARK_WORD.to_le_bytes());
+        let end = tape.len() as u64;
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape));
+        reader.set_position(end).unwrap();
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::Single);
+                assert_eq!(offset, end - 4);
+            }
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::Double),
+            other => panic!("expected double tape mark, got {:?}", other),
+        }
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.header.class, 0x8);
+                assert_eq!(record.header.offset, 16);
+                assert_eq!(record.data, vec![0xAA, 0xBB]);
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert_eq!(reader.position().unwrap(), 16);
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::Single);
+                assert_eq!(offset, 12);
+            }
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.header.offset, 0);
+                assert_eq!(record.data, vec![1, 2, 3]);
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::BeginningOfTape => {}
+            other => panic!("expected beginning of tape, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reverse_half_gap_realigns_onto_trailing_length() {
+        let mut tape = Vec::new();
+        emit_record(&mut tape, 0, &[0x01, 0x02]);
+        // Upper half of an erase gap marker left behind by the record above.
+        tape.extend_from_slice(&[0xFF, 0xFF]);
+        tape.extend_from_slice(&ERASE_GAP_WORD.to_le_bytes());
+        let end = tape.len() as u64;
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape));
+        reader.set_position(end).unwrap();
+
+        match reader.prev_block().unwrap() {
             SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::EraseGap),
             other => panic!("expected erase-gap marker, got {:?}", other),
         }
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, .. } => {
+                assert_eq!(kind, SimhTapeMark::HalfGapReverse { value: 0 })
+            }
+            other => panic!("expected reverse half-gap marker, got {:?}", other),
+        }
+        assert_eq!(reader.position().unwrap(), 10);
+
+        match reader.prev_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![0x01, 0x02]),
+            other => panic!("expected record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reverse_read_rejects_mismatched_lengths() {
+        let mut tape = Vec::new();
+        tape.extend_from_slice(&4u32.to_le_bytes());
+        tape.extend_from_slice(&[0x01, 0x02]);
+        tape.extend_from_slice(&2u32.to_le_bytes());
+        let end = tape.len() as u64;
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape));
+        reader.set_position(end).unwrap();
+        match reader.prev_block().unwrap_err() {
+            SimhError::LengthMismatch {
+                offset,
+                leading,
+                trailing,
+            } => {
+                assert_eq!(offset, 0);
+                assert_eq!(leading, 4);
+                assert_eq!(trailing, 2);
+            }
+            other => panic!("expected length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn writer_round_trips_every_construct() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_tape_description(b"9TRK 1600").unwrap();
+        writer.write_record(&[1, 2, 3]).unwrap();
+        writer.write_bad_record(&[]).unwrap();
+        writer.write_record_with_class(0x3, &[4, 5]).unwrap();
+        writer.write_half_gap_forward().unwrap();
+        writer.write_erase_gap_markers(2).unwrap();
+        writer.write_private_marker(0x0ABC_DEF0).unwrap();
+        writer.write_reserved_marker(0x0123_4567).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_end_of_medium().unwrap();
+        let tape = writer.into_inner();
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape.clone()));
+        let mut copy = SimhTapeWriter::new(Vec::new());
+        loop {
+            match reader.next_block().unwrap() {
+                SimhTapeBlock::Record(record) => copy
+                    .write_record_with_class(record.header.class, &record.data)
+                    .unwrap(),
+                SimhTapeBlock::TapeMark { kind, .. } => copy.write_mark(kind).unwrap(),
+                SimhTapeBlock::EndOfStream => break,
+                other => panic!("unexpected block {:?}", other),
+            }
+        }
+        assert_eq!(copy.into_inner(), tape);
+    }
+
+    #[test]
+    fn writer_rejects_marker_classes_and_values() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        assert!(matches!(
+            writer.write_record_with_class(0x7, &[1]),
+            Err(SimhError::InvalidRecordClass { class: 0x7 })
+        ));
+        assert!(matches!(
+            writer.write_record_with_class(0xF, &[1]),
+            Err(SimhError::InvalidRecordClass { class: 0xF })
+        ));
+        assert!(matches!(
+            writer.write_record(&[]),
+            Err(SimhError::EmptyRecord)
+        ));
+        assert!(matches!(
+            writer.write_mark(SimhTapeMark::HalfGapReverse { value: 0 }),
+            Err(SimhError::Unsupported { .. })
+        ));
+        assert!(matches!(
+            writer.write_reserved_marker(0x0FFE_0000),
+            Err(SimhError::InvalidMarkerValue { .. })
+        ));
+        assert!(writer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn recovery_mode_skips_damaged_span() {
+        let mut tape = Vec::new();
+        emit_record(&mut tape, 0, &[0x11; 4]);
+        // Record whose trailing length was damaged.
+        tape.extend_from_slice(&6u32.to_le_bytes());
+        tape.extend_from_slice(&[0x22; 6]);
+        tape.extend_from_slice(&0x0000_BEEFu32.to_le_bytes());
+        tape.extend_from_slice(&[0x5A; 3]);
+        let resume = tape.len() as u64;
+        emit_record(&mut tape, 0, &[0x33; 5]);
+        tape.extend_from_slice(&TAPE_MARK_WORD.to_le_bytes());
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape.clone()));
+        assert!(reader.next_block().is_ok());
+        assert_eq!(
+            reader.next_block().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape)).with_recovery(true);
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::Record(_)
+        ));
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Resync(resync) => {
+                assert_eq!(resync.start, 12);
+                assert_eq!(resync.end, resume);
+                assert!(matches!(
+                    resync.error,
+                    SimhError::LengthMismatch {
+                        offset: 12,
+                        leading: 6,
+                        trailing: 0xBEEF
+                    }
+                ));
+            }
+            other => panic!("expected resync, got {:?}", other),
+        }
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![0x33; 5]),
+            other => panic!("expected record, got {:?}", other),
+        }
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::Single),
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recovery_mode_reports_truncated_tail() {
+        let mut tape = Vec::new();
+        emit_record(&mut tape, 0, &[0x11; 4]);
+        tape.extend_from_slice(&100u32.to_le_bytes());
+        tape.extend_from_slice(&[0x22; 10]);
+        let len = tape.len() as u64;
+
+        let mut reader = SimhTapeReader::new(Cursor::new(tape)).with_recovery(true);
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::Record(_)
+        ));
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Resync(resync) => {
+                assert_eq!(resync.start, 12);
+                assert_eq!(resync.end, len);
+                assert!(matches!(
+                    resync.error,
+                    SimhError::TruncatedRecord {
+                        offset: 12,
+                        length: 100
+                    }
+                ));
+            }
+            other => panic!("expected resync, got {:?}", other),
+        }
+
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::EndOfStream
+        ));
     }
 
     #[test]
@@ -0,0 +1,458 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::{
+    MAX_RECORD_LENGTH, SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeRecord,
+    SimhTapeRecordHeader, TapeBlockReader, TapeBlockWriter, VALUE_MASK, write_tape_mark_only,
+};
+
+const P7B_FORMAT: &str = "P7B";
+
+const P7B_START_OF_RECORD: u8 = 0x80;
+const P7B_PARITY: u8 = 0x40;
+const P7B_DATA_PARITY: u8 = P7B_PARITY | 0x3F;
+const P7B_TAPE_MARK: u8 = 0x0F;
+
+/// Lateral parity of a 7-track character: binary records are written with odd
+/// parity and BCD records with even parity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum P7bParity {
+    Odd,
+    Even,
+}
+
+impl P7bParity {
+    /// Whether `character` (six data bits plus the parity bit) satisfies this parity.
+    pub fn check(self, character: u8) -> bool {
+        let odd = (character & P7B_DATA_PARITY).count_ones() % 2 == 1;
+        odd == (self == P7bParity::Odd)
+    }
+}
+
+/// Reads SIMH P7B 7-track images. Every character carries six data bits, its
+/// parity bit and a record-start flag; a record runs until the next flagged
+/// character and a lone flagged 017 character with even parity is a tape mark.
+/// Records are returned as class 0 with the parity bit kept in bit 6, as SIMH
+/// does.
+pub struct P7bTapeReader<R> {
+    reader: BufReader<R>,
+    safety_limit: u32,
+    parity: Option<P7bParity>,
+    offset: u64,
+    lookahead: Option<(u64, Vec<u8>)>,
+    pending_double: bool,
+    record_parity: Option<P7bParity>,
+    parity_errors: Vec<usize>,
+}
+
+impl<R: Read> P7bTapeReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            safety_limit: MAX_RECORD_LENGTH,
+            parity: None,
+            offset: 0,
+            lookahead: None,
+            pending_double: false,
+            record_parity: None,
+            parity_errors: Vec::new(),
+        }
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.safety_limit = limit.min(VALUE_MASK);
+        self
+    }
+
+    /// Checks every record against `parity`. By default each record is checked
+    /// against whichever parity most of its characters have.
+    pub fn with_parity(mut self, parity: P7bParity) -> Self {
+        self.parity = Some(parity);
+        self
+    }
+
+    /// Offset of the next record, counted from where the stream started.
+    pub fn position(&self) -> u64 {
+        self.lookahead
+            .as_ref()
+            .map_or(self.offset, |(offset, _)| *offset)
+    }
+
+    /// Parity the last record returned was checked against.
+    pub fn record_parity(&self) -> Option<P7bParity> {
+        self.record_parity
+    }
+
+    /// Indexes of the characters in the last record returned whose parity bit is wrong.
+    pub fn parity_errors(&self) -> &[usize] {
+        &self.parity_errors
+    }
+
+    /// Returns the underlying reader. Input already buffered by the reader is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    fn fetch_record(&mut self) -> SimhResult<Option<(u64, Vec<u8>)>> {
+        let offset = self.offset;
+        let first = match self.reader.fill_buf()?.first() {
+            Some(&first) => first,
+            None => return Ok(None),
+        };
+        if first & P7B_START_OF_RECORD == 0 {
+            return Err(SimhError::MissingRecordStart { offset });
+        }
+        self.reader.consume(1);
+
+        let mut data = vec![first & P7B_DATA_PARITY];
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            let end = buf
+                .iter()
+                .position(|&byte| byte & P7B_START_OF_RECORD != 0)
+                .unwrap_or(buf.len());
+            let complete = end < buf.len();
+            let length = data.len() as u64 + end as u64;
+            if length > u64::from(self.safety_limit) {
+                return Err(SimhError::LengthExceedsLimit {
+                    offset,
+                    length: length.min(u64::from(u32::MAX)) as u32,
+                    limit: self.safety_limit,
+                });
+            }
+            data.extend_from_slice(&buf[..end]);
+            self.reader.consume(end);
+            if complete {
+                break;
+            }
+        }
+
+        self.offset += data.len() as u64;
+        Ok(Some((offset, data)))
+    }
+
+    fn read_record(&mut self) -> SimhResult<Option<(u64, Vec<u8>)>> {
+        match self.lookahead.take() {
+            Some(record) => Ok(Some(record)),
+            None => self.fetch_record(),
+        }
+    }
+
+    fn peek_record(&mut self) -> SimhResult<Option<&[u8]>> {
+        if self.lookahead.is_none() {
+            self.lookahead = self.fetch_record()?;
+        }
+        Ok(self.lookahead.as_ref().map(|(_, data)| data.as_slice()))
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.record_parity = None;
+        self.parity_errors.clear();
+
+        let Some((offset, data)) = self.read_record()? else {
+            return Ok(SimhTapeBlock::EndOfStream);
+        };
+
+        if is_tape_mark(&data) {
+            let kind = if self.pending_double {
+                self.pending_double = false;
+                SimhTapeMark::Double
+            } else {
+                self.pending_double = self.peek_record()?.is_some_and(is_tape_mark);
+                SimhTapeMark::Single
+            };
+            return Ok(SimhTapeBlock::TapeMark { offset, kind });
+        }
+        self.pending_double = false;
+
+        // A lone 017 with its parity bit set is a tape mark whose parity is
+        // wrong; it stays a record, checked against the tape mark's even parity.
+        let parity = if data == [P7B_PARITY | P7B_TAPE_MARK] {
+            P7bParity::Even
+        } else {
+            self.parity.unwrap_or_else(|| majority_parity(&data))
+        };
+        self.parity_errors.extend(
+            data.iter()
+                .enumerate()
+                .filter(|&(_, &character)| !parity.check(character))
+                .map(|(index, _)| index),
+        );
+        self.record_parity = Some(parity);
+
+        let length = data.len() as u32;
+        Ok(SimhTapeBlock::Record(SimhTapeRecord {
+            header: SimhTapeRecordHeader {
+                offset,
+                class: 0,
+                length,
+                trailing_length: None,
+            },
+            data,
+        }))
+    }
+}
+
+/// Only 017 with even parity is a tape mark, as in SIMH.
+fn is_tape_mark(data: &[u8]) -> bool {
+    data == [P7B_TAPE_MARK]
+}
+
+/// Ties go to odd parity, since a binary record can hold any bit pattern.
+fn majority_parity(data: &[u8]) -> P7bParity {
+    let odd = data
+        .iter()
+        .filter(|&&character| P7bParity::Odd.check(character))
+        .count();
+    if odd * 2 >= data.len() {
+        P7bParity::Odd
+    } else {
+        P7bParity::Even
+    }
+}
+
+/// Whether `sample`, the start of an image, looks like P7B: it must open with a
+/// record start, and records must be longer than random data would give and
+/// mostly keep to one parity.
+pub(crate) fn probe(sample: &[u8]) -> bool {
+    if sample
+        .first()
+        .is_none_or(|&first| first & P7B_START_OF_RECORD == 0)
+    {
+        return false;
+    }
+
+    let mut records = 0;
+    let mut errors = 0;
+    let mut start = 0;
+    while start < sample.len() {
+        let end = sample[start + 1..]
+            .iter()
+            .position(|&byte| byte & P7B_START_OF_RECORD != 0)
+            .map_or(sample.len(), |position| start + 1 + position);
+        let record = &sample[start..end];
+        let parity = majority_parity(record);
+        errors += record
+            .iter()
+            .filter(|&&character| !parity.check(character))
+            .count();
+        records += 1;
+        start = end;
+    }
+    sample.len() >= records * 2 && errors * 8 <= sample.len()
+}
+
+/// Writes SIMH P7B images. Record data must already hold 7-bit characters with
+/// their parity bit, as read from a 7-track `.tap`; the record-start flag is added
+/// here. Only class 0 records can be represented and the image ends where the file does.
+pub struct P7bTapeWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> P7bTapeWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(0, data)
+    }
+
+    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        if class != 0 {
+            return Err(SimhError::Unsupported {
+                format: P7B_FORMAT,
+                feature: "record classes",
+            });
+        }
+        let Some((&first, rest)) = data.split_first() else {
+            return Err(SimhError::EmptyRecord);
+        };
+        if data.iter().any(|&byte| byte & P7B_START_OF_RECORD != 0) {
+            return Err(SimhError::Unsupported {
+                format: P7B_FORMAT,
+                feature: "8-bit characters",
+            });
+        }
+        if is_tape_mark(data) {
+            return Err(SimhError::Unsupported {
+                format: P7B_FORMAT,
+                feature: "a record holding only the tape mark character",
+            });
+        }
+
+        self.writer.write_all(&[first | P7B_START_OF_RECORD])?;
+        self.writer.write_all(rest)?;
+        Ok(())
+    }
+
+    pub fn write_tape_mark(&mut self) -> SimhResult<()> {
+        self.writer
+            .write_all(&[P7B_START_OF_RECORD | P7B_TAPE_MARK])?;
+        Ok(())
+    }
+
+    /// P7B has no end-of-medium marker, so this writes nothing.
+    pub fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<R: Read> TapeBlockReader for P7bTapeReader<R> {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        P7bTapeReader::next_block(self)
+    }
+}
+
+impl<W: Write> TapeBlockWriter for P7bTapeWriter<W> {
+    fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        P7bTapeWriter::write_record_with_class(self, class, data)
+    }
+
+    fn write_tape_mark(&mut self) -> SimhResult<()> {
+        P7bTapeWriter::write_tape_mark(self)
+    }
+
+    fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        P7bTapeWriter::write_end_of_medium(self)
+    }
+
+    fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        write_tape_mark_only(self, mark, P7B_FORMAT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SimhTapeReader, SimhTapeWriter, copy_blocks};
+    use std::io::Cursor;
+
+    #[test]
+    fn splits_records_and_reports_parity_errors() {
+        // "AB" in BCD with even parity, then a binary record with one bad character.
+        let tape = [
+            0x80 | 0x71,
+            0x72,
+            0x80 | 0x0F,
+            0x80 | 0x01,
+            0x40 | 0x03,
+            0x03,
+            0x80 | 0x0F,
+            0x80 | 0x0F,
+        ];
+        let mut reader = P7bTapeReader::new(&tape[..]);
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![0x71, 0x72]),
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert_eq!(reader.record_parity(), Some(P7bParity::Even));
+        assert!(reader.parity_errors().is_empty());
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { offset, kind } => {
+                assert_eq!(offset, 2);
+                assert_eq!(kind, SimhTapeMark::Single);
+            }
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.header.offset, 3);
+                assert_eq!(record.data, vec![0x01, 0x43, 0x03]);
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert_eq!(reader.record_parity(), Some(P7bParity::Odd));
+        assert_eq!(reader.parity_errors(), &[2]);
+
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single,
+                ..
+            }
+        ));
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Double,
+                ..
+            }
+        ));
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::EndOfStream
+        ));
+
+        let mut odd = P7bTapeReader::new(&tape[..]).with_parity(P7bParity::Odd);
+        odd.next_block().unwrap();
+        assert_eq!(odd.parity_errors(), &[0, 1]);
+
+        let mut bad_mark = P7bTapeReader::new(&[0x80 | 0x4F, 0x80 | 0x0F][..]);
+        match bad_mark.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![0x4F]),
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert_eq!(bad_mark.parity_errors(), &[0]);
+        assert!(matches!(
+            bad_mark.next_block().unwrap(),
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single,
+                ..
+            }
+        ));
+
+        let mut orphan = P7bTapeReader::new(&[0x01u8, 0x81][..]);
+        assert!(matches!(
+            orphan.next_block(),
+            Err(SimhError::MissingRecordStart { offset: 0 })
+        ));
+    }
+
+    #[test]
+    fn converts_to_and_from_simh() {
+        let mut simh = SimhTapeWriter::new(Vec::new());
+        simh.write_record(&[0x31, 0x32, 0x73]).unwrap();
+        simh.write_tape_mark().unwrap();
+        simh.write_record(&[0x4F, 0x0F]).unwrap();
+        simh.write_tape_mark().unwrap();
+        simh.write_tape_mark().unwrap();
+        simh.write_end_of_medium().unwrap();
+        let original = simh.into_inner();
+
+        let mut p7b = P7bTapeWriter::new(Vec::new());
+        copy_blocks(
+            &mut SimhTapeReader::new(Cursor::new(original.clone())),
+            &mut p7b,
+        )
+        .unwrap();
+        let p7b_image = p7b.into_inner();
+        assert_eq!(
+            p7b_image,
+            vec![0xB1, 0x32, 0x73, 0x8F, 0xCF, 0x0F, 0x8F, 0x8F]
+        );
+
+        let mut back = SimhTapeWriter::new(Vec::new());
+        copy_blocks(&mut P7bTapeReader::new(p7b_image.as_slice()), &mut back).unwrap();
+        assert_eq!(back.into_inner(), original);
+
+        let mut p7b = P7bTapeWriter::new(Vec::new());
+        assert!(matches!(
+            p7b.write_record(&[0x41, 0x80]),
+            Err(SimhError::Unsupported { format: "P7B", .. })
+        ));
+        assert!(matches!(
+            p7b.write_record(&[0x0F]),
+            Err(SimhError::Unsupported { format: "P7B", .. })
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::{Read, Write};
+
+use crate::{
+    SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeRecord, SimhTapeRecordHeader,
+    TapeBlockReader, TapeBlockWriter, write_tape_mark_only,
+};
+
+const RAW_FORMAT: &str = "raw";
+
+/// Block size used when nothing better is known: tar's default of 20 × 512 bytes.
+pub const DEFAULT_RAW_BLOCK_SIZE: usize = 10240;
+
+/// Reads an unframed data file as a single tape file of fixed-size class 0
+/// records, the last of which may be short.
+pub struct RawTapeReader<R> {
+    reader: R,
+    block_size: usize,
+    offset: u64,
+}
+
+impl<R: Read> RawTapeReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            block_size: DEFAULT_RAW_BLOCK_SIZE,
+            offset: 0,
+        }
+    }
+
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Offset of the next record, counted from where the stream started.
+    pub fn position(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        let offset = self.offset;
+        let mut data = Vec::with_capacity(self.block_size);
+        (&mut self.reader)
+            .take(self.block_size as u64)
+            .read_to_end(&mut data)?;
+        if data.is_empty() {
+            return Ok(SimhTapeBlock::EndOfStream);
+        }
+
+        self.offset += data.len() as u64;
+        let length = data.len() as u32;
+        Ok(SimhTapeBlock::Record(SimhTapeRecord {
+            header: SimhTapeRecordHeader {
+                offset,
+                class: 0,
+                length,
+                trailing_length: None,
+            },
+            data,
+        }))
+    }
+}
+
+/// Writes record data back to back with no framing. Tape marks cannot be
+/// written, so callers wanting one file per tape file must split the output
+/// themselves; the end of medium is simply the end of the data.
+pub struct RawTapeWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> RawTapeWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(0, data)
+    }
+
+    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        if class != 0 {
+            return Err(SimhError::Unsupported {
+                format: RAW_FORMAT,
+                feature: "record classes",
+            });
+        }
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+
+    pub fn write_tape_mark(&mut self) -> SimhResult<()> {
+        Err(SimhError::Unsupported {
+            format: RAW_FORMAT,
+            feature: "tape marks",
+        })
+    }
+
+    pub fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<R: Read> TapeBlockReader for RawTapeReader<R> {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        RawTapeReader::next_block(self)
+    }
+}
+
+impl<W: Write> TapeBlockWriter for RawTapeWriter<W> {
+    fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        RawTapeWriter::write_record_with_class(self, class, data)
+    }
+
+    fn write_tape_mark(&mut self) -> SimhResult<()> {
+        RawTapeWriter::write_tape_mark(self)
+    }
+
+    fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        RawTapeWriter::write_end_of_medium(self)
+    }
+
+    fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        write_tape_mark_only(self, mark, RAW_FORMAT)
+    }
+
+    fn keeps_record_boundaries(&self) -> bool {
+        false
+    }
+}
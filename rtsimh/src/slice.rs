@@ -0,0 +1,256 @@
+use crate::{
+    ForwardWord, HALF_GAP_LENGTH, MAX_RECORD_LENGTH, SimhError, SimhResult, SimhResync,
+    SimhTapeMark, SimhTapeRecord, SimhTapeRecordHeader, TAPE_MARK_WORD, VALUE_MASK,
+    check_trailing_length, decode_forward, is_plausible_object, padded_length,
+};
+
+/// A record whose payload borrows from the image being read.
+#[derive(Debug, Clone, Copy)]
+pub struct SimhTapeRecordRef<'a> {
+    pub header: SimhTapeRecordHeader,
+    pub data: &'a [u8],
+}
+
+impl SimhTapeRecordRef<'_> {
+    pub fn to_owned_record(&self) -> SimhTapeRecord {
+        SimhTapeRecord {
+            header: self.header,
+            data: self.data.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SimhTapeBlockRef<'a> {
+    Record(SimhTapeRecordRef<'a>),
+    TapeMark { offset: u64, kind: SimhTapeMark },
+    EndOfStream,
+    Resync(SimhResync),
+}
+
+/// Reads an image held entirely in memory (for example a memory-mapped file)
+/// without allocating per record.
+pub struct SimhTapeSliceReader<'a> {
+    image: &'a [u8],
+    offset: usize,
+    safety_limit: u32,
+    pending_double: bool,
+    recovery: bool,
+}
+
+impl<'a> SimhTapeSliceReader<'a> {
+    pub fn new(image: &'a [u8]) -> Self {
+        Self {
+            image,
+            offset: 0,
+            safety_limit: MAX_RECORD_LENGTH,
+            pending_double: false,
+            recovery: false,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.safety_limit = limit.min(VALUE_MASK);
+        self
+    }
+
+    /// See `SimhTapeReader::with_recovery`.
+    pub fn with_recovery(mut self, enabled: bool) -> Self {
+        self.recovery = enabled;
+        self
+    }
+
+    pub fn position(&self) -> u64 {
+        self.offset as u64
+    }
+
+    pub fn set_position(&mut self, offset: u64) {
+        self.pending_double = false;
+        self.offset = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(self.image.len());
+    }
+
+    fn word_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.image.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn take_word(&mut self) -> SimhResult<Option<u32>> {
+        if self.offset >= self.image.len() {
+            return Ok(None);
+        }
+        let word = self.word_at(self.offset).ok_or(SimhError::TruncatedWord {
+            offset: self.offset as u64,
+        })?;
+        self.offset += 4;
+        Ok(Some(word))
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlockRef<'a>> {
+        let start = self.offset;
+        match self.read_forward() {
+            Err(error) if self.recovery && error.is_format_error() => {
+                self.pending_double = false;
+                let end = self.resynchronize(start + 1);
+                self.offset = end;
+                Ok(SimhTapeBlockRef::Resync(SimhResync {
+                    start: start as u64,
+                    end: end as u64,
+                    error,
+                }))
+            }
+            other => other,
+        }
+    }
+
+    fn resynchronize(&self, from: usize) -> usize {
+        let image_end = self.image.len() as u64;
+        let mut word_at = |offset: u64| {
+            self.word_at(offset as usize)
+                .ok_or(SimhError::TruncatedWord { offset })
+        };
+
+        for candidate in from..self.image.len().saturating_sub(3) {
+            let Some(word) = self.word_at(candidate) else {
+                break;
+            };
+            let plausible = is_plausible_object(
+                &mut word_at,
+                candidate as u64,
+                word,
+                image_end,
+                self.safety_limit,
+                true,
+            );
+            if matches!(plausible, Ok(true)) {
+                return candidate;
+            }
+        }
+        self.image.len()
+    }
+
+    fn read_forward(&mut self) -> SimhResult<SimhTapeBlockRef<'a>> {
+        let offset = self.offset as u64;
+        let Some(word) = self.take_word()? else {
+            return Ok(SimhTapeBlockRef::EndOfStream);
+        };
+
+        if word == TAPE_MARK_WORD {
+            let kind = if self.pending_double {
+                self.pending_double = false;
+                SimhTapeMark::Double
+            } else {
+                self.pending_double = self.word_at(self.offset) == Some(TAPE_MARK_WORD);
+                SimhTapeMark::Single
+            };
+            return Ok(SimhTapeBlockRef::TapeMark { offset, kind });
+        }
+        self.pending_double = false;
+
+        let (class, length) = match decode_forward(offset, word, self.safety_limit)? {
+            ForwardWord::Marker(kind) => {
+                if kind == SimhTapeMark::HalfGapForward {
+                    self.offset = offset as usize + HALF_GAP_LENGTH as usize;
+                }
+                return Ok(SimhTapeBlockRef::TapeMark { offset, kind });
+            }
+            ForwardWord::Record { class, length } => (class, length),
+        };
+
+        let data_start = self.offset;
+        let data_end = data_start.saturating_add(length as usize);
+        let trailing_at = data_start.saturating_add(padded_length(length, true) as usize);
+        let data = self
+            .image
+            .get(data_start..data_end)
+            .ok_or(SimhError::TruncatedRecord { offset, length })?;
+
+        check_trailing_length(offset, word, self.word_at(trailing_at))?;
+        self.offset = trailing_at + 4;
+
+        Ok(SimhTapeBlockRef::Record(SimhTapeRecordRef {
+            header: SimhTapeRecordHeader {
+                offset,
+                class,
+                length,
+                trailing_length: Some(length),
+            },
+            data,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimhTapeWriter;
+
+    #[test]
+    fn borrows_payloads_from_image() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1, 2, 3]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_end_of_medium().unwrap();
+        let tape = writer.into_inner();
+
+        let mut reader = SimhTapeSliceReader::new(&tape);
+        match reader.next_block().unwrap() {
+            SimhTapeBlockRef::Record(record) => {
+                assert_eq!(record.data, &[1, 2, 3]);
+                assert_eq!(record.data.as_ptr(), tape[4..].as_ptr());
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlockRef::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::Single),
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlockRef::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::Double),
+            other => panic!("expected double tape mark, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlockRef::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::EndOfTape),
+            other => panic!("expected end-of-tape mark, got {:?}", other),
+        }
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlockRef::EndOfStream
+        ));
+    }
+
+    #[test]
+    fn recovers_from_truncated_record() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1, 2, 3, 4]).unwrap();
+        let mut tape = writer.into_inner();
+        tape.extend_from_slice(&64u32.to_le_bytes());
+        tape.extend_from_slice(&[0xEE; 6]);
+
+        let mut reader = SimhTapeSliceReader::new(&tape);
+        reader.next_block().unwrap();
+        assert!(matches!(
+            reader.next_block().unwrap_err(),
+            SimhError::TruncatedRecord {
+                offset: 12,
+                length: 64
+            }
+        ));
+
+        let mut reader = SimhTapeSliceReader::new(&tape).with_recovery(true);
+        reader.next_block().unwrap();
+        match reader.next_block().unwrap() {
+            SimhTapeBlockRef::Resync(resync) => {
+                assert_eq!(resync.start, 12);
+                assert_eq!(resync.end, tape.len() as u64);
+            }
+            other => panic!("expected resync, got {:?}", other),
+        }
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlockRef::EndOfStream
+        ));
+    }
+}
@@ -0,0 +1,291 @@
+use std::io::{self, Read};
+
+use crate::{
+    ForwardWord, HALF_GAP_LENGTH, MAX_RECORD_LENGTH, SimhError, SimhResult, SimhTapeBlock,
+    SimhTapeMark, SimhTapeRecord, SimhTapeRecordHeader, TAPE_MARK_WORD, VALUE_MASK,
+    check_trailing_length, decode_forward, padded_length, read_payload_from, read_word_from,
+};
+
+/// Forward-only counterpart of `SimhTapeReader` for sources that cannot seek,
+/// such as pipes and stdin. Double tape marks are detected with a one-word
+/// lookahead buffer instead of seeking back, and the two bytes a forward
+/// half-gap gives back are held in `pushback`. A lookahead that fails is
+/// reported by the read it belongs to, not the tape mark before it.
+pub struct SimhTapeStreamReader<R> {
+    reader: R,
+    safety_limit: u32,
+    offset: u64,
+    lookahead: Option<u32>,
+    deferred: Option<SimhError>,
+    pushback: Option<[u8; 2]>,
+    pending_double: bool,
+}
+
+impl<R: Read> SimhTapeStreamReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            safety_limit: MAX_RECORD_LENGTH,
+            offset: 0,
+            lookahead: None,
+            deferred: None,
+            pushback: None,
+            pending_double: false,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.safety_limit = limit.min(VALUE_MASK);
+        self
+    }
+
+    /// Offset of the next object, counted from where the stream started.
+    pub fn position(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_word(&mut self) -> SimhResult<Option<u32>> {
+        if let Some(err) = self.deferred.take() {
+            return Err(err);
+        }
+        let word = match self.lookahead.take() {
+            Some(word) => Some(word),
+            None => self.fetch_word()?,
+        };
+        if word.is_some() {
+            self.offset += 4;
+        }
+        Ok(word)
+    }
+
+    fn peek_word(&mut self) -> SimhResult<Option<u32>> {
+        if self.lookahead.is_none() {
+            self.lookahead = self.fetch_word()?;
+        }
+        Ok(self.lookahead)
+    }
+
+    fn fetch_word(&mut self) -> SimhResult<Option<u32>> {
+        match self.pushback.take() {
+            Some(half) => read_word_from(&mut half.as_slice().chain(&mut self.reader), self.offset),
+            None => read_word_from(&mut self.reader, self.offset),
+        }
+    }
+
+    fn consume_tape_mark_kind(&mut self) -> SimhResult<SimhTapeMark> {
+        if self.pending_double {
+            self.pending_double = false;
+            return Ok(SimhTapeMark::Double);
+        }
+
+        match self.peek_word() {
+            Ok(word) => self.pending_double = word == Some(TAPE_MARK_WORD),
+            Err(err) => self.deferred = Some(err),
+        }
+        Ok(SimhTapeMark::Single)
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.read_forward(true)
+    }
+
+    /// Like `next_block`, but discards record payloads instead of returning them.
+    /// Records are returned with an empty `data` vector.
+    pub fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.read_forward(false)
+    }
+
+    fn read_forward(&mut self, read_payload: bool) -> SimhResult<SimhTapeBlock> {
+        let offset = self.offset;
+        let Some(word) = self.read_word()? else {
+            return Ok(SimhTapeBlock::EndOfStream);
+        };
+
+        if word == TAPE_MARK_WORD {
+            let kind = self.consume_tape_mark_kind()?;
+            return Ok(SimhTapeBlock::TapeMark { offset, kind });
+        }
+        self.pending_double = false;
+
+        let (class, length) = match decode_forward(offset, word, self.safety_limit)? {
+            ForwardWord::Marker(kind) => {
+                if kind == SimhTapeMark::HalfGapForward {
+                    let bytes = word.to_le_bytes();
+                    self.pushback = Some([bytes[2], bytes[3]]);
+                    self.offset -= HALF_GAP_LENGTH;
+                }
+                return Ok(SimhTapeBlock::TapeMark { offset, kind });
+            }
+            ForwardWord::Record { class, length } => (class, length),
+        };
+
+        let padded = padded_length(length, true);
+        let mut data = Vec::new();
+        if read_payload {
+            data.resize(length as usize, 0);
+            read_payload_from(&mut self.reader, &mut data, offset, length)?;
+            if padded > u64::from(length) {
+                let mut pad = [0u8; 1];
+                read_payload_from(&mut self.reader, &mut pad, offset, length)?;
+            }
+        } else {
+            let skipped = io::copy(&mut (&mut self.reader).take(padded), &mut io::sink())?;
+            if skipped != padded {
+                return Err(SimhError::TruncatedRecord { offset, length });
+            }
+        }
+        self.offset += padded;
+
+        let trailing = self.read_word()?;
+        check_trailing_length(offset, word, trailing)?;
+
+        Ok(SimhTapeBlock::Record(SimhTapeRecord {
+            header: SimhTapeRecordHeader {
+                offset,
+                class,
+                length,
+                trailing_length: Some(length),
+            },
+            data,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimhTapeWriter;
+
+    /// Hands out at most three bytes per read to exercise partial reads.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn streams_records_and_double_tape_mark() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1, 2, 3]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_record(&[4, 5]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        let tape = writer.into_inner();
+
+        let mut reader = SimhTapeStreamReader::new(Trickle(&tape));
+
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![1, 2, 3]),
+            other => panic!("expected record, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::Single);
+                assert_eq!(offset, 12);
+            }
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+        match reader.skip_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.header.offset, 16);
+                assert_eq!(record.header.length, 2);
+                assert!(record.data.is_empty());
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::Single),
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::Double),
+            other => panic!("expected double tape mark, got {:?}", other),
+        }
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::EndOfStream
+        ));
+        assert_eq!(reader.position(), tape.len() as u64);
+    }
+
+    #[test]
+    fn realigns_after_forward_half_gap() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_half_gap_forward().unwrap();
+        writer.write_erase_gap_markers(1).unwrap();
+        writer.write_record(&[1, 2]).unwrap();
+        let tape = writer.into_inner();
+
+        let mut reader = SimhTapeStreamReader::new(Trickle(&tape));
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::HalfGapForward);
+                assert_eq!(offset, 0);
+            }
+            other => panic!("expected half-gap, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::EraseGap);
+                assert_eq!(offset, 2);
+            }
+            other => panic!("expected erase gap, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => assert_eq!(record.data, vec![1, 2]),
+            other => panic!("expected record, got {:?}", other),
+        }
+        assert_eq!(reader.position(), tape.len() as u64);
+    }
+
+    #[test]
+    fn rejects_mismatched_trailing_length() {
+        let mut tape = Vec::new();
+        tape.extend_from_slice(&2u32.to_le_bytes());
+        tape.extend_from_slice(&[0xAA, 0xBB]);
+        tape.extend_from_slice(&4u32.to_le_bytes());
+
+        let mut reader = SimhTapeStreamReader::new(tape.as_slice());
+        match reader.next_block().unwrap_err() {
+            SimhError::LengthMismatch {
+                offset,
+                leading,
+                trailing,
+            } => {
+                assert_eq!(offset, 0);
+                assert_eq!(leading, 2);
+                assert_eq!(trailing, 4);
+            }
+            other => panic!("expected length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_a_failed_lookahead_on_the_next_read() {
+        let mut tape = TAPE_MARK_WORD.to_le_bytes().to_vec();
+        tape.extend_from_slice(&[0x12, 0x34]);
+
+        let mut reader = SimhTapeStreamReader::new(tape.as_slice());
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::Single);
+                assert_eq!(offset, 0);
+            }
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+        assert!(matches!(
+            reader.next_block(),
+            Err(SimhError::TruncatedWord { offset: 4 })
+        ));
+    }
+}
@@ -0,0 +1,324 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeRecord, SimhTapeRecordHeader,
+    TapeBlockReader, TapeBlockWriter, read_payload_from, write_tape_mark_only,
+};
+
+const TPC_FORMAT: &str = "TPC";
+const TPC_MAX_RECORD_LENGTH: u32 = 0xFFFF;
+
+/// Reads TPC images: a 16-bit little-endian length before each record, odd
+/// records padded to a word, no trailing length, and a zero length as the tape mark.
+/// TPC has no classes or markers, so every record is returned as class 0.
+pub struct TpcTapeReader<R> {
+    reader: R,
+    offset: u64,
+    lookahead: Option<u16>,
+    pending_double: bool,
+}
+
+impl<R: Read> TpcTapeReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            offset: 0,
+            lookahead: None,
+            pending_double: false,
+        }
+    }
+
+    /// Offset of the next object, counted from where the stream started.
+    pub fn position(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn fetch_length(&mut self) -> SimhResult<Option<u16>> {
+        let mut buf = [0u8; 2];
+        let mut read = 0;
+        while read < buf.len() {
+            let n = self.reader.read(&mut buf[read..])?;
+            if n == 0 {
+                return if read == 0 {
+                    Ok(None)
+                } else {
+                    Err(SimhError::TruncatedWord {
+                        offset: self.offset,
+                    })
+                };
+            }
+            read += n;
+        }
+        Ok(Some(u16::from_le_bytes(buf)))
+    }
+
+    fn read_length(&mut self) -> SimhResult<Option<u16>> {
+        let length = match self.lookahead.take() {
+            Some(length) => Some(length),
+            None => self.fetch_length()?,
+        };
+        if length.is_some() {
+            self.offset += 2;
+        }
+        Ok(length)
+    }
+
+    fn peek_length(&mut self) -> SimhResult<Option<u16>> {
+        if self.lookahead.is_none() {
+            self.lookahead = self.fetch_length()?;
+        }
+        Ok(self.lookahead)
+    }
+
+    pub fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.read_forward(true)
+    }
+
+    /// Like `next_block`, but discards record payloads instead of returning them.
+    pub fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.read_forward(false)
+    }
+
+    fn read_forward(&mut self, read_payload: bool) -> SimhResult<SimhTapeBlock> {
+        let offset = self.offset;
+        let Some(length) = self.read_length()? else {
+            return Ok(SimhTapeBlock::EndOfStream);
+        };
+
+        if length == 0 {
+            let kind = if self.pending_double {
+                self.pending_double = false;
+                SimhTapeMark::Double
+            } else {
+                self.pending_double = self.peek_length()? == Some(0);
+                SimhTapeMark::Single
+            };
+            return Ok(SimhTapeBlock::TapeMark { offset, kind });
+        }
+        self.pending_double = false;
+
+        let length = u32::from(length);
+        let padded = u64::from(length) + u64::from(length % 2);
+        let mut data = Vec::new();
+        if read_payload {
+            data.resize(length as usize, 0);
+            read_payload_from(&mut self.reader, &mut data, offset, length)?;
+            if !length.is_multiple_of(2) {
+                let mut pad = [0u8; 1];
+                read_payload_from(&mut self.reader, &mut pad, offset, length)?;
+            }
+        } else {
+            let skipped = io::copy(&mut (&mut self.reader).take(padded), &mut io::sink())?;
+            if skipped != padded {
+                return Err(SimhError::TruncatedRecord { offset, length });
+            }
+        }
+        self.offset += padded;
+
+        Ok(SimhTapeBlock::Record(SimhTapeRecord {
+            header: SimhTapeRecordHeader {
+                offset,
+                class: 0,
+                length,
+                trailing_length: None,
+            },
+            data,
+        }))
+    }
+}
+
+/// Whether `sample`, the start of an image, walks cleanly as TPC. TPC has no
+/// redundancy to check, so a tape mark or an exact end of image is required.
+pub(crate) fn probe(sample: &[u8], complete: bool) -> bool {
+    let mut offset = 0;
+    let mut records = 0;
+    let mut marks = 0;
+
+    while offset + 2 <= sample.len() {
+        let length = usize::from(u16::from_le_bytes([sample[offset], sample[offset + 1]]));
+        offset += 2;
+        if length == 0 {
+            marks += 1;
+            continue;
+        }
+        records += 1;
+        offset += length + length % 2;
+        if offset > sample.len() {
+            return !complete && marks > 0;
+        }
+    }
+    records + marks > 0 && (marks > 0 || (complete && offset == sample.len()))
+}
+
+/// Writes TPC images. Only good (class 0) records of up to 65535 bytes can be
+/// represented, and the image simply ends where the file does.
+pub struct TpcTapeWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TpcTapeWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(0, data)
+    }
+
+    pub fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        if class != 0 {
+            return Err(SimhError::Unsupported {
+                format: TPC_FORMAT,
+                feature: "record classes",
+            });
+        }
+        if data.is_empty() {
+            return Err(SimhError::EmptyRecord);
+        }
+        let length = u16::try_from(data.len()).map_err(|_| SimhError::RecordTooLong {
+            length: data.len(),
+            limit: TPC_MAX_RECORD_LENGTH,
+        })?;
+
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(data)?;
+        if !length.is_multiple_of(2) {
+            self.writer.write_all(&[0])?;
+        }
+        Ok(())
+    }
+
+    pub fn write_tape_mark(&mut self) -> SimhResult<()> {
+        self.writer.write_all(&0u16.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// TPC has no end-of-medium marker, so this writes nothing.
+    pub fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<R: Read> TapeBlockReader for TpcTapeReader<R> {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        TpcTapeReader::next_block(self)
+    }
+
+    fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        TpcTapeReader::skip_block(self)
+    }
+}
+
+impl<W: Write> TapeBlockWriter for TpcTapeWriter<W> {
+    fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        TpcTapeWriter::write_record_with_class(self, class, data)
+    }
+
+    fn write_tape_mark(&mut self) -> SimhResult<()> {
+        TpcTapeWriter::write_tape_mark(self)
+    }
+
+    fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        TpcTapeWriter::write_end_of_medium(self)
+    }
+
+    fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        write_tape_mark_only(self, mark, TPC_FORMAT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SimhTapeReader, SimhTapeWriter, copy_blocks};
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_lengths_marks_and_padding() {
+        let mut writer = TpcTapeWriter::new(Vec::new());
+        writer.write_record(&[1, 2, 3]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        let tape = writer.into_inner();
+        assert_eq!(tape, vec![3, 0, 1, 2, 3, 0, 0, 0, 0, 0]);
+
+        let mut reader = TpcTapeReader::new(tape.as_slice());
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::Record(record) => {
+                assert_eq!(record.data, vec![1, 2, 3]);
+                assert_eq!(record.header.trailing_length, None);
+            }
+            other => panic!("expected record, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, offset } => {
+                assert_eq!(kind, SimhTapeMark::Single);
+                assert_eq!(offset, 6);
+            }
+            other => panic!("expected tape mark, got {:?}", other),
+        }
+        match reader.next_block().unwrap() {
+            SimhTapeBlock::TapeMark { kind, .. } => assert_eq!(kind, SimhTapeMark::Double),
+            other => panic!("expected double tape mark, got {:?}", other),
+        }
+        assert!(matches!(
+            reader.next_block().unwrap(),
+            SimhTapeBlock::EndOfStream
+        ));
+
+        let mut truncated = TpcTapeReader::new(&[8u8, 0, 1, 2][..]);
+        assert!(matches!(
+            truncated.next_block(),
+            Err(SimhError::TruncatedRecord {
+                offset: 0,
+                length: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn converts_through_common_traits() {
+        let mut simh = SimhTapeWriter::new(Vec::new());
+        simh.write_record(&[5; 7]).unwrap();
+        simh.write_tape_mark().unwrap();
+        simh.write_record(&[6; 2]).unwrap();
+        simh.write_end_of_medium().unwrap();
+        let original = simh.into_inner();
+
+        let mut tpc = TpcTapeWriter::new(Vec::new());
+        copy_blocks(
+            &mut SimhTapeReader::new(Cursor::new(original.clone())),
+            &mut tpc,
+        )
+        .unwrap();
+        let tpc_image = tpc.into_inner();
+
+        let mut back = SimhTapeWriter::new(Vec::new());
+        copy_blocks(&mut TpcTapeReader::new(tpc_image.as_slice()), &mut back).unwrap();
+        assert_eq!(back.into_inner(), original);
+
+        let mut simh = SimhTapeWriter::new(Vec::new());
+        simh.write_mark(SimhTapeMark::EraseGap).unwrap();
+        simh.write_record(&[7; 4]).unwrap();
+        simh.write_end_of_medium().unwrap();
+        let gapped = simh.into_inner();
+        let mut tpc = TpcTapeWriter::new(Vec::new());
+        let dropped = copy_blocks(&mut SimhTapeReader::new(Cursor::new(gapped)), &mut tpc).unwrap();
+        assert_eq!(dropped, 1);
+        assert_eq!(tpc.into_inner(), vec![4, 0, 7, 7, 7, 7]);
+
+        let mut tpc = TpcTapeWriter::new(Vec::new());
+        assert!(matches!(
+            tpc.write_bad_record(&[1]),
+            Err(SimhError::Unsupported { format: "TPC", .. })
+        ));
+    }
+}
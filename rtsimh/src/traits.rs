@@ -0,0 +1,127 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader, SimhTapeStreamReader,
+    SimhTapeWriter,
+};
+
+/// Forward block access shared by the readers of every supported image format.
+pub trait TapeBlockReader {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock>;
+
+    /// Like `next_block`, but readers may leave record payloads unread.
+    fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        self.next_block()
+    }
+}
+
+/// Record and tape mark output shared by the writers of every supported image format.
+pub trait TapeBlockWriter {
+    fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()>;
+
+    fn write_tape_mark(&mut self) -> SimhResult<()>;
+
+    fn write_end_of_medium(&mut self) -> SimhResult<()>;
+
+    /// Writes a tape mark, gap or marker object. Formats without gaps or markers
+    /// return `SimhError::Unsupported` for anything but tape marks and end of medium.
+    fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()>;
+
+    fn write_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(0, data)
+    }
+
+    fn write_bad_record(&mut self, data: &[u8]) -> SimhResult<()> {
+        self.write_record_with_class(0x8, data)
+    }
+
+    /// False for formats that join records into one stream of bytes.
+    fn keeps_record_boundaries(&self) -> bool {
+        true
+    }
+}
+
+/// Copies records and tape marks until the end of the source, then ends the
+/// target with an end-of-medium marker. Gaps, private or reserved markers and
+/// damaged spans the reader skipped are dropped, since most formats cannot
+/// hold them; returns how many were dropped so callers can report the loss.
+pub fn copy_blocks(
+    reader: &mut dyn TapeBlockReader,
+    writer: &mut dyn TapeBlockWriter,
+) -> SimhResult<usize> {
+    let mut dropped = 0;
+    loop {
+        match reader.next_block()? {
+            SimhTapeBlock::Record(record) => {
+                writer.write_record_with_class(record.header.class, &record.data)?
+            }
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::Single | SimhTapeMark::Double,
+                ..
+            } => writer.write_tape_mark()?,
+            SimhTapeBlock::TapeMark {
+                kind: SimhTapeMark::EndOfTape,
+                ..
+            }
+            | SimhTapeBlock::EndOfStream
+            | SimhTapeBlock::BeginningOfTape => break,
+            SimhTapeBlock::TapeMark { .. } | SimhTapeBlock::Resync(_) => dropped += 1,
+        }
+    }
+    writer.write_end_of_medium()?;
+    Ok(dropped)
+}
+
+/// `write_mark` for formats whose only objects besides records are tape marks.
+pub(crate) fn write_tape_mark_only<W: TapeBlockWriter + ?Sized>(
+    writer: &mut W,
+    mark: SimhTapeMark,
+    format: &'static str,
+) -> SimhResult<()> {
+    match mark {
+        SimhTapeMark::Single | SimhTapeMark::Double => writer.write_tape_mark(),
+        SimhTapeMark::EndOfTape => writer.write_end_of_medium(),
+        _ => Err(SimhError::Unsupported {
+            format,
+            feature: "gaps or markers",
+        }),
+    }
+}
+
+impl<R: Read + Seek> TapeBlockReader for SimhTapeReader<R> {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        SimhTapeReader::next_block(self)
+    }
+
+    fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        SimhTapeReader::skip_block(self)
+    }
+}
+
+impl<R: Read> TapeBlockReader for SimhTapeStreamReader<R> {
+    fn next_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        SimhTapeStreamReader::next_block(self)
+    }
+
+    fn skip_block(&mut self) -> SimhResult<SimhTapeBlock> {
+        SimhTapeStreamReader::skip_block(self)
+    }
+}
+
+impl<W: Write> TapeBlockWriter for SimhTapeWriter<W> {
+    fn write_record_with_class(&mut self, class: u8, data: &[u8]) -> SimhResult<()> {
+        SimhTapeWriter::write_record_with_class(self, class, data)
+    }
+
+    fn write_tape_mark(&mut self) -> SimhResult<()> {
+        SimhTapeWriter::write_tape_mark(self)
+    }
+
+    fn write_end_of_medium(&mut self) -> SimhResult<()> {
+        SimhTapeWriter::write_end_of_medium(self)
+    }
+
+    fn write_mark(&mut self, mark: SimhTapeMark) -> SimhResult<()> {
+        SimhTapeWriter::write_mark(self, mark)
+    }
+}
@@ -0,0 +1,190 @@
+mod verify;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use verify::{Report, Severity};
+
+// Exit codes; 2 is left to clap for usage errors.
+const EXIT_VALID: u8 = 0;
+const EXIT_WARNINGS: u8 = 1;
+const EXIT_INVALID: u8 = 3;
+const EXIT_UNREADABLE: u8 = 4;
+
+#[derive(Debug, Parser)]
+#[command(
+    author,
+    version,
+    about = "ACMS rtverify SIMH tape image structural validator.",
+    after_help = "EXIT STATUS:\n  \
+                  0  every image is valid (informational findings only)\n  \
+                  1  warnings: legal but unusual content, such as reserved classes\n  \
+                  2  usage error\n  \
+                  3  at least one image breaks the SIMH format specification\n  \
+                  4  at least one image could not be read\n\n\
+                  EXAMPLES:\n  \
+                  rtverify mytape.tap\n  \
+                  rtverify --json *.tap > findings.json"
+)]
+struct Cli {
+    /// SIMH tape image file(s) to validate
+    #[arg(value_name = "FILES", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Print the findings as a JSON document instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Verdict {
+    Valid,
+    Warnings,
+    Invalid,
+    Unreadable,
+}
+
+impl Verdict {
+    fn exit_code(self) -> u8 {
+        match self {
+            Verdict::Valid => EXIT_VALID,
+            Verdict::Warnings => EXIT_WARNINGS,
+            Verdict::Invalid => EXIT_INVALID,
+            Verdict::Unreadable => EXIT_UNREADABLE,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FileReport {
+    path: String,
+    verdict: Verdict,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(flatten)]
+    report: Option<Report>,
+}
+
+#[derive(Debug, Serialize)]
+struct Output {
+    verdict: Verdict,
+    files: Vec<FileReport>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let files: Vec<FileReport> = cli
+        .files
+        .iter()
+        .map(|path| match verify_file(path) {
+            Ok(report) => FileReport {
+                path: path.display().to_string(),
+                verdict: match report.severity() {
+                    Some(Severity::Error) => Verdict::Invalid,
+                    Some(Severity::Warning) => Verdict::Warnings,
+                    Some(Severity::Info) | None => Verdict::Valid,
+                },
+                error: None,
+                report: Some(report),
+            },
+            Err(err) => FileReport {
+                path: path.display().to_string(),
+                verdict: Verdict::Unreadable,
+                error: Some(format!("{err:#}")),
+                report: None,
+            },
+        })
+        .collect();
+    let output = Output {
+        verdict: files
+            .iter()
+            .map(|file| file.verdict)
+            .max()
+            .unwrap_or(Verdict::Valid),
+        files,
+    };
+
+    if cli.json {
+        match serde_json::to_string_pretty(&output) {
+            Ok(json) => println!("{json}"),
+            Err(err) => {
+                eprintln!("Error: failed to encode findings: {err}");
+                return ExitCode::from(EXIT_UNREADABLE);
+            }
+        }
+    } else {
+        print_text(&output);
+    }
+
+    ExitCode::from(output.verdict.exit_code())
+}
+
+fn verify_file(path: &Path) -> Result<Report> {
+    let open = || File::open(path).with_context(|| format!("failed to open {}", path.display()));
+    let report = verify::verify(BufReader::new(open()?), open()?)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(report)
+}
+
+fn print_text(output: &Output) {
+    for file in &output.files {
+        let Some(report) = &file.report else {
+            println!(
+                "{}: UNREADABLE - {}",
+                file.path,
+                file.error.as_deref().unwrap_or_default()
+            );
+            continue;
+        };
+
+        let verdict = match file.verdict {
+            Verdict::Valid => "VALID",
+            Verdict::Warnings => "WARNINGS",
+            Verdict::Invalid => "INVALID",
+            Verdict::Unreadable => "UNREADABLE",
+        };
+        println!(
+            "{}: {} - {} errors, {} warnings",
+            file.path,
+            verdict,
+            report.count(Severity::Error),
+            report.count(Severity::Warning)
+        );
+
+        let summary = &report.summary;
+        println!(
+            "  {} bytes: {} records ({} bad, {} data bytes), {} tape marks, {} erase gaps, {} half-gaps, {} markers{}",
+            report.size,
+            summary.records,
+            summary.bad_records,
+            summary.data_bytes,
+            summary.tape_marks,
+            summary.erase_gaps,
+            summary.half_gaps,
+            summary.markers,
+            if summary.end_of_medium { ", EOM" } else { "" }
+        );
+        for finding in &report.findings {
+            let severity = match finding.severity {
+                Severity::Info => "info",
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            let repeats = if finding.count > 1 {
+                format!(" (x{})", finding.count)
+            } else {
+                String::new()
+            };
+            println!(
+                "  [{severity}] {} at offset {}{repeats}: {}",
+                finding.code, finding.offset, finding.message
+            );
+        }
+    }
+}
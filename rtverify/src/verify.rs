@@ -0,0 +1,343 @@
+use rtsimh::{SimhError, SimhResult, SimhTapeBlock, SimhTapeMark, SimhTapeReader};
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Largest length a 28-bit SIMH length field can hold. The validator checks
+/// framing rather than plausibility, so it accepts anything the format can.
+const LENGTH_LIMIT: u32 = 0x0FFF_FFFF;
+const HALF_GAP_LENGTH: u64 = 2;
+const BAD_RECORD_CLASS: u8 = 0x8;
+const TAPE_DESCRIPTION_CLASS: u8 = 0xE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Legal, but worth knowing about before archiving.
+    Info,
+    /// Legal, but outside what current SIMH readers interpret.
+    Warning,
+    /// Breaks the format specification.
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub code: &'static str,
+    /// Offset of the first occurrence.
+    pub offset: u64,
+    pub count: u64,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Summary {
+    pub records: u64,
+    pub bad_records: u64,
+    pub tape_marks: u64,
+    pub erase_gaps: u64,
+    pub half_gaps: u64,
+    pub markers: u64,
+    pub end_of_medium: bool,
+    pub data_bytes: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub size: u64,
+    pub summary: Summary,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    /// The most severe finding, if there are any.
+    pub fn severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+
+    /// Records a finding, folding repeats of the same message into one entry.
+    fn add(&mut self, severity: Severity, code: &'static str, offset: u64, message: String) {
+        match self
+            .findings
+            .iter_mut()
+            .find(|finding| finding.code == code && finding.message == message)
+        {
+            Some(finding) => finding.count += 1,
+            None => self.findings.push(Finding {
+                severity,
+                code,
+                offset,
+                count: 1,
+                message,
+            }),
+        }
+    }
+}
+
+/// Walks a SIMH image front to back and checks it against the extended format
+/// specification. `pads` is a second handle on the same image, used to read
+/// the pad bytes the tape reader skips over.
+pub fn verify<R: Read + Seek, P: Read + Seek>(image: R, mut pads: P) -> SimhResult<Report> {
+    let mut report = Report {
+        size: pads.seek(SeekFrom::End(0))?,
+        ..Default::default()
+    };
+    let mut reader = SimhTapeReader::new(image)
+        .with_limit(LENGTH_LIMIT)
+        .with_recovery(true);
+    let mut record_end = None;
+    let mut pending_half_gap = None;
+
+    loop {
+        let block = reader.skip_block()?;
+        if let Some(at) = pending_half_gap.take()
+            && !matches!(
+                block,
+                SimhTapeBlock::TapeMark {
+                    offset,
+                    kind: SimhTapeMark::EraseGap,
+                } if offset == at + HALF_GAP_LENGTH
+            )
+        {
+            report.add(
+                Severity::Error,
+                "misplaced_half_gap",
+                at,
+                "forward half-gap is not followed by an erase gap".to_string(),
+            );
+        }
+
+        let previous_end = record_end.take();
+        match block {
+            SimhTapeBlock::Record(record) => {
+                let header = record.header;
+                let padded = u64::from(header.length) + u64::from(header.length % 2);
+                record_end = Some(header.offset + 8 + padded);
+                report.summary.records += 1;
+                report.summary.data_bytes += u64::from(header.length);
+                check_class(&mut report, header.offset, header.class);
+
+                if header.length % 2 == 1 {
+                    let pad_at = header.offset + 4 + u64::from(header.length);
+                    let mut pad = [0u8; 1];
+                    pads.seek(SeekFrom::Start(pad_at))?;
+                    pads.read_exact(&mut pad)?;
+                    if pad[0] != 0 {
+                        report.add(
+                            Severity::Error,
+                            "nonzero_pad",
+                            pad_at,
+                            "odd-length record has a non-zero pad byte".to_string(),
+                        );
+                    }
+                }
+            }
+            SimhTapeBlock::TapeMark { offset, kind } => match kind {
+                SimhTapeMark::Single | SimhTapeMark::Double => report.summary.tape_marks += 1,
+                SimhTapeMark::EndOfTape => {
+                    report.summary.end_of_medium = true;
+                    let after = report.size - (offset + 4);
+                    if after > 0 {
+                        report.add(
+                            Severity::Error,
+                            "data_after_eom",
+                            offset + 4,
+                            format!("{after} bytes follow the end-of-medium marker"),
+                        );
+                    }
+                    break;
+                }
+                SimhTapeMark::EraseGap => report.summary.erase_gaps += 1,
+                SimhTapeMark::HalfGapForward => {
+                    report.summary.half_gaps += 1;
+                    if previous_end != Some(offset) {
+                        report.add(
+                            Severity::Error,
+                            "misplaced_half_gap",
+                            offset,
+                            "forward half-gap does not directly follow a record".to_string(),
+                        );
+                    }
+                    pending_half_gap = Some(offset);
+                }
+                SimhTapeMark::HalfGapReverse { value } => {
+                    report.summary.half_gaps += 1;
+                    report.add(
+                        Severity::Error,
+                        "misplaced_half_gap",
+                        offset,
+                        format!(
+                            "reverse half-gap 0xFFFF{value:04X} found on a forward read, where no object can begin with one"
+                        ),
+                    );
+                }
+                SimhTapeMark::Private { .. } => {
+                    report.summary.markers += 1;
+                    report.add(
+                        Severity::Info,
+                        "private_marker",
+                        offset,
+                        "class 7 private markers present".to_string(),
+                    );
+                }
+                SimhTapeMark::Reserved { value, .. } => {
+                    report.summary.markers += 1;
+                    report.add(
+                        Severity::Warning,
+                        "reserved_marker",
+                        offset,
+                        format!("reserved class F marker 0x{:08X}", 0xF000_0000 | value),
+                    );
+                }
+            },
+            SimhTapeBlock::Resync(resync) => {
+                report.add(
+                    Severity::Error,
+                    framing_code(&resync.error),
+                    resync.start,
+                    format!(
+                        "{}; {} bytes skipped",
+                        resync.error,
+                        resync.end - resync.start
+                    ),
+                );
+            }
+            SimhTapeBlock::EndOfStream | SimhTapeBlock::BeginningOfTape => break,
+        }
+    }
+
+    Ok(report)
+}
+
+fn check_class(report: &mut Report, offset: u64, class: u8) {
+    match class {
+        0 => {}
+        BAD_RECORD_CLASS => {
+            report.summary.bad_records += 1;
+            report.add(
+                Severity::Info,
+                "bad_record",
+                offset,
+                "class 8 bad records present".to_string(),
+            );
+        }
+        TAPE_DESCRIPTION_CLASS => report.add(
+            Severity::Info,
+            "tape_description",
+            offset,
+            "class E tape description records present".to_string(),
+        ),
+        0x1..=0x6 => report.add(
+            Severity::Info,
+            "private_record",
+            offset,
+            format!("class {class} private records present"),
+        ),
+        _ => report.add(
+            Severity::Warning,
+            "reserved_record_class",
+            offset,
+            format!("records use reserved class {class:X}"),
+        ),
+    }
+}
+
+fn framing_code(error: &SimhError) -> &'static str {
+    match error {
+        SimhError::TruncatedWord { .. } => "truncated_word",
+        SimhError::TruncatedRecord { .. } | SimhError::MissingTrailingLength { .. } => {
+            "truncated_record"
+        }
+        SimhError::LengthMismatch { .. } => "length_mismatch",
+        SimhError::LengthExceedsLimit { .. } => "length_exceeds_limit",
+        SimhError::IllegalMarker { .. } => "illegal_marker",
+        _ => "framing",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtsimh::SimhTapeWriter;
+    use std::io::Cursor;
+
+    fn verify_bytes(bytes: &[u8]) -> Report {
+        verify(Cursor::new(bytes), Cursor::new(bytes)).unwrap()
+    }
+
+    fn codes(report: &Report) -> Vec<(&'static str, u64, u64)> {
+        report
+            .findings
+            .iter()
+            .map(|finding| (finding.code, finding.offset, finding.count))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_well_formed_images() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 3]).unwrap();
+        writer.write_half_gap_forward().unwrap();
+        writer.write_erase_gap_markers(2).unwrap();
+        writer.write_bad_record(&[2; 4]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_end_of_medium().unwrap();
+        let report = verify_bytes(&writer.into_inner());
+
+        assert_eq!(report.severity(), Some(Severity::Info));
+        assert_eq!(codes(&report), vec![("bad_record", 22, 1)]);
+        assert_eq!(report.summary.records, 2);
+        assert_eq!(report.summary.tape_marks, 2);
+        assert_eq!(report.summary.erase_gaps, 2);
+        assert_eq!(report.summary.half_gaps, 1);
+        assert!(report.summary.end_of_medium);
+        assert!(verify_bytes(&[]).findings.is_empty());
+    }
+
+    #[test]
+    fn reports_each_structural_violation() {
+        let mut writer = SimhTapeWriter::new(Vec::new());
+        writer.write_record(&[1; 3]).unwrap();
+        writer.write_record_with_class(0xA, &[2; 2]).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_half_gap_forward().unwrap();
+        writer.write_erase_gap_markers(1).unwrap();
+        writer.write_tape_mark().unwrap();
+        writer.write_reserved_marker(5).unwrap();
+        writer.write_record(&[3; 4]).unwrap();
+        writer.write_end_of_medium().unwrap();
+        let mut bytes = writer.into_inner();
+        bytes[7] = 0x55;
+        bytes.extend_from_slice(&[0; 4]);
+        bytes[48] = 0x05;
+
+        let report = verify_bytes(&bytes);
+        assert_eq!(report.severity(), Some(Severity::Error));
+        assert_eq!(
+            codes(&report),
+            vec![
+                ("nonzero_pad", 7, 1),
+                ("reserved_record_class", 12, 1),
+                ("misplaced_half_gap", 26, 1),
+                ("reserved_marker", 36, 1),
+                ("length_mismatch", 40, 1),
+            ]
+        );
+        assert!(!report.summary.end_of_medium);
+
+        let mut after_eom = SimhTapeWriter::new(Vec::new());
+        after_eom.write_tape_mark().unwrap();
+        after_eom.write_end_of_medium().unwrap();
+        after_eom.write_record(&[4; 2]).unwrap();
+        let report = verify_bytes(&after_eom.into_inner());
+        assert_eq!(codes(&report), vec![("data_after_eom", 8, 1)]);
+    }
+}